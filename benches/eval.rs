@@ -0,0 +1,77 @@
+//! Compares `Expr::eval` with evaluating the compiled expression, one point or a batch at a
+//! time. Run with `cargo bench`, adding `--features rayon` for parallel batches.
+
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use num_complex::Complex64;
+
+use mathlib::{parse::parse, var::Var};
+
+const ITERATIONS: u32 = 200_000;
+
+fn time(mut f: impl FnMut(u32)) -> Duration {
+    // Warm up caches and the branch predictor before measuring.
+    for i in 0..ITERATIONS / 10 {
+        f(i);
+    }
+
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        f(i);
+    }
+    start.elapsed()
+}
+
+fn bench(name: &str, input: &str) {
+    let expr = parse(input).unwrap();
+    let (x, y, z) = (Var::new("x"), Var::new("y"), Var::new("z"));
+    let compiled = expr.compile(&[&x, &y, &z]).unwrap();
+
+    let value = |i: u32| Complex64::new(f64::from(i % 100) / 10.0 + 0.5, 0.0);
+
+    let mut ctx = HashMap::new();
+    let tree = time(|i| {
+        ctx.insert(x.as_ref(), value(i));
+        ctx.insert(y.as_ref(), value(i + 1));
+        ctx.insert(z.as_ref(), value(i + 2));
+        black_box(black_box(&expr).eval(&ctx).unwrap());
+    });
+    let flat = time(|i| {
+        let values = [value(i), value(i + 1), value(i + 2)];
+        black_box(black_box(&compiled).eval(&values).unwrap());
+    });
+
+    let columns: Vec<Vec<Complex64>> = (0..3)
+        .map(|column| (0..ITERATIONS).map(|i| value(i + column)).collect())
+        .collect();
+    let columns: Vec<&[Complex64]> = columns.iter().map(Vec::as_slice).collect();
+    let mut out = vec![Complex64::new(0.0, 0.0); ITERATIONS as usize];
+    let start = Instant::now();
+    black_box(compiled.eval_batch(&columns, &mut out));
+    let batch = start.elapsed();
+
+    let per_iteration = |duration: Duration| duration.as_nanos() as f64 / f64::from(ITERATIONS);
+    println!(
+        "{name:<12} Expr::eval {:>8.1} ns  Compiled::eval {:>8.1} ns ({:.1}x)  \
+         eval_batch {:>8.1} ns ({:.1}x)",
+        per_iteration(tree),
+        per_iteration(flat),
+        tree.as_secs_f64() / flat.as_secs_f64(),
+        per_iteration(batch),
+        tree.as_secs_f64() / batch.as_secs_f64()
+    );
+}
+
+fn main() {
+    bench("polynomial", "3x^3 - 2x^2 y + x y^2 z - 7z + 1");
+    bench(
+        "functions",
+        "sin(x) y^2 + exp(-x/2) cos(y) - sqrt(x^2 + y^2 + z^2)",
+    );
+    bench(
+        "many vars",
+        "x y z + x z + y z + x + y + z + x^2 + y^2 + z^2",
+    );
+}
@@ -1,28 +1,133 @@
+use num_integer::{Integer, Roots};
+use num_traits::Pow;
 use std::mem::swap;
 
 /// Returns the Greatest Common Denominator (or greatest common factor) of it's two arguments.
-pub fn euclid_gcd(x: u32, y: u32) -> u32 {
+pub fn euclid_gcd<T: Integer + Clone>(x: T, y: T) -> T {
     let mut a = x;
     let mut b = y;
 
-    if b == 0 {
+    if b.is_zero() {
         return a;
     }
 
-    while a != 0 {
+    while !a.is_zero() {
         if a >= b {
-            a %= b;
+            a = a % b.clone();
         } else {
             swap(&mut a, &mut b);
         }
     }
 
-    return b;
+    b
+}
+
+/// Returns the content of `values`, the greatest common divisor of their absolute values, which
+/// is zero only if they all are.
+pub fn content<T: Integer + Clone>(values: &[T]) -> T {
+    values
+        .iter()
+        .fold(T::zero(), |gcd, value| euclid_gcd(gcd, abs(value.clone())))
+}
+
+/// Divides `values` by their content, leaving coprime integers. Zeros are left as they are.
+pub fn primitive_part<T: Integer + Clone>(values: &[T]) -> Vec<T> {
+    let content = content(values);
+    if content.is_zero() {
+        return values.to_vec();
+    }
+
+    values
+        .iter()
+        .map(|value| value.clone() / content.clone())
+        .collect()
+}
+
+fn abs<T: Integer>(n: T) -> T {
+    if n < T::zero() {
+        T::zero() - n
+    } else {
+        n
+    }
+}
+
+/// Returns the exact `k`-th root of `n`, if `n` is a perfect `k`-th power.
+pub fn exact_root<T>(n: &T, k: u32) -> Option<T>
+where
+    T: Integer + Roots + Clone + Pow<u32, Output = T>,
+{
+    if k == 0 {
+        return None;
+    }
+
+    let root = n.nth_root(k);
+    (root.clone().pow(k) == *n).then_some(root)
+}
+
+/// Primes below this bound are found by trial division in [`factorize`].
+const TRIAL_DIVISION_BOUND: u32 = 1 << 16;
+
+/// Factors `n` into `(factor, multiplicity)` pairs, in ascending order of factor.
+///
+/// Prime factors below [`TRIAL_DIVISION_BOUND`] are found by trial division. If anything is
+/// left over after that, it is reported as the largest perfect power it is, so the last base
+/// may be composite when `n` has several large prime factors.
+pub fn factorize<T>(n: &T) -> Vec<(T, u32)>
+where
+    T: Integer + Roots + Clone + Pow<u32, Output = T> + From<u32>,
+{
+    let mut factors = vec![];
+    let mut rest = n.clone();
+
+    if rest.is_zero() {
+        return factors;
+    }
+
+    let mut p = 2;
+    while p < TRIAL_DIVISION_BOUND {
+        let divisor = T::from(p);
+        if divisor.clone() * divisor.clone() > rest {
+            // Whatever is left has no factor below its square root, so it is prime.
+            if !rest.is_one() {
+                factors.push((rest, 1));
+            }
+            return factors;
+        }
+
+        let mut multiplicity = 0;
+        while rest.is_multiple_of(&divisor) {
+            rest = rest / divisor.clone();
+            multiplicity += 1;
+        }
+        if multiplicity > 0 {
+            factors.push((divisor, multiplicity));
+        }
+
+        p += if p == 2 { 1 } else { 2 };
+    }
+
+    if !rest.is_one() {
+        // All remaining prime factors are at least the bound, which limits the exponent.
+        let mut exponent = 1;
+        let mut k = 2;
+        while T::from(TRIAL_DIVISION_BOUND).pow(k) <= rest {
+            match exact_root(&rest, k) {
+                Some(root) => {
+                    rest = root;
+                    exponent *= k;
+                }
+                None => k += 1,
+            }
+        }
+        factors.push((rest, exponent));
+    }
+
+    factors
 }
 
 #[cfg(test)]
 mod test {
-    use crate::algo::euclid_gcd;
+    use crate::algo::{content, euclid_gcd, exact_root, factorize, primitive_part};
 
     #[test]
     fn gcd_x_and_zero_is_x() {
@@ -66,4 +171,42 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn content_and_primitive_part() {
+        assert_eq!(6, content(&[12, -18, 0, 30]));
+        assert_eq!(vec![2, -3, 0, 5], primitive_part(&[12, -18, 0, 30]));
+        assert_eq!(1, content(&[-1]));
+        assert_eq!(0, content::<i64>(&[]));
+        assert_eq!(vec![0, 0], primitive_part(&[0, 0]));
+    }
+
+    #[test]
+    fn exact_root_of_perfect_powers() {
+        for root in [0, 1, 2, 3, 10, 255, 65535] {
+            for k in 1..=4 {
+                if let Some(n) = u64::checked_pow(root, k) {
+                    assert_eq!(Some(root), exact_root(&n, k));
+                }
+            }
+        }
+
+        assert_eq!(None, exact_root(&8u64, 2));
+        assert_eq!(None, exact_root(&10u64, 3));
+        assert_eq!(Some(4294967295), exact_root(&18446744065119617025u64, 2));
+    }
+
+    #[test]
+    fn factorize_small_and_large_numbers() {
+        assert_eq!(Vec::<(u64, u32)>::new(), factorize(&1u64));
+        assert_eq!(vec![(2, 3), (3, 2), (5, 1)], factorize(&360u64));
+        assert_eq!(vec![(65537, 1)], factorize(&65537u64));
+        assert_eq!(vec![(2, 1), (65537, 2)], factorize(&(2 * 65537u64 * 65537)));
+        assert_eq!(vec![(4294967291, 1)], factorize(&4294967291u64));
+        // The square of a prime above the trial division bound.
+        assert_eq!(
+            vec![(4294967291, 2)],
+            factorize(&(4294967291u64 * 4294967291))
+        );
+    }
 }
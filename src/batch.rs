@@ -0,0 +1,104 @@
+use num_complex::Complex64;
+
+use crate::{
+    compile::Compiled,
+    expr::{EvalError, Expr},
+    var::Var,
+};
+
+/// The number of points each thread evaluates at a time.
+#[cfg(feature = "rayon")]
+const CHUNK_SIZE: usize = 1024;
+
+impl Expr {
+    /// Evaluates the expression at many points at once, where `columns` holds the values of each
+    /// variable at every point and `out` receives the results. The number of points is the
+    /// length of `out`.
+    ///
+    /// The expression is compiled once, see [`Expr::compile`], so this fails as a whole only if
+    /// a variable has no column or a function is called with the wrong number of arguments.
+    /// Errors at single points are returned along with the index of the point, and leave NaN in
+    /// `out`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a column is not as long as `out`.
+    pub fn eval_batch(
+        &self,
+        columns: &[(&Var, &[Complex64])],
+        out: &mut [Complex64],
+    ) -> Result<Vec<(usize, EvalError)>, EvalError> {
+        let vars: Vec<&Var> = columns.iter().map(|(var, _)| *var).collect();
+        let values: Vec<&[Complex64]> = columns.iter().map(|(_, values)| *values).collect();
+
+        Ok(self.compile(&vars)?.eval_batch(&values, out))
+    }
+}
+
+impl Compiled {
+    /// Evaluates the compiled expression at many points at once, where `columns[i]` holds the
+    /// values of the `i`-th variable at every point. See [`Expr::eval_batch`].
+    ///
+    /// With the `rayon` feature the points are evaluated in parallel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer columns than variables or a column is not as long as `out`.
+    pub fn eval_batch(
+        &self,
+        columns: &[&[Complex64]],
+        out: &mut [Complex64],
+    ) -> Vec<(usize, EvalError)> {
+        assert!(
+            columns.len() >= self.vars().len(),
+            "expected a column for each of the {} variables, got {}",
+            self.vars().len(),
+            columns.len()
+        );
+        for column in columns {
+            assert_eq!(
+                column.len(),
+                out.len(),
+                "columns must have one value per point"
+            );
+        }
+
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+
+            out.par_chunks_mut(CHUNK_SIZE)
+                .enumerate()
+                .flat_map_iter(|(chunk, out)| self.eval_points(columns, chunk * CHUNK_SIZE, out))
+                .collect()
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        self.eval_points(columns, 0, out)
+    }
+
+    /// Evaluates the points starting at `offset` into `out`.
+    fn eval_points(
+        &self,
+        columns: &[&[Complex64]],
+        offset: usize,
+        out: &mut [Complex64],
+    ) -> Vec<(usize, EvalError)> {
+        let mut errors = vec![];
+        let mut values = vec![Complex64::new(0.0, 0.0); columns.len()];
+        let mut stack = vec![];
+
+        for (i, result) in out.iter_mut().enumerate() {
+            for (value, column) in values.iter_mut().zip(columns) {
+                *value = column[offset + i];
+            }
+
+            *result = self.eval_with(&values, &mut stack).unwrap_or_else(|error| {
+                errors.push((offset + i, error));
+                Complex64::new(f64::NAN, f64::NAN)
+            });
+        }
+
+        errors
+    }
+}
@@ -0,0 +1,306 @@
+//! Compiling expressions into a flat list of instructions, for evaluating the same expression
+//! many times.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use num_complex::Complex64;
+
+use crate::{
+    expr::{self, EvalError, EvalResult, Expr},
+    function::{Arity, FuncDef},
+    num::Num,
+    var::{Var, VarMap},
+};
+
+impl Expr {
+    /// Compiles the expression for fast repeated evaluation with [`Compiled::eval`], where the
+    /// value of `vars[i]` is passed in slot `i`.
+    ///
+    /// Fails if the expression uses a variable that is not in `vars`, or calls a function with
+    /// the wrong number of arguments.
+    pub fn compile(&self, vars: &[&Var]) -> Result<Compiled, EvalError> {
+        let mut compiler = Compiler {
+            slots: HashMap::new(),
+            instructions: vec![],
+            nodes: 0,
+            depth: 0,
+            stack_size: 0,
+        };
+        for (slot, var) in vars.iter().enumerate() {
+            compiler.slots.entry(*var).or_insert(slot);
+        }
+
+        compiler.expr(self)?;
+
+        Ok(Compiled {
+            vars: vars.iter().map(|var| (*var).clone()).collect(),
+            instructions: compiler.instructions,
+            expr: self.clone(),
+            stack_size: compiler.stack_size,
+        })
+    }
+}
+
+/// An expression compiled into instructions for a stack machine, see [`Expr::compile`].
+///
+/// Variables are resolved to slots once, so evaluation only indexes into a slice instead of
+/// hashing every variable occurrence.
+#[derive(Debug, Clone)]
+pub struct Compiled {
+    vars: Vec<Var>,
+    instructions: Vec<Instruction>,
+    /// The compiled expression, whose subexpressions are reported in errors.
+    expr: Expr,
+    stack_size: usize,
+}
+
+impl Compiled {
+    /// The variables in slot order.
+    pub fn vars(&self) -> &[Var] {
+        &self.vars
+    }
+
+    /// Evaluates the expression with `values[i]` as the value of the `i`-th variable. Gives the
+    /// same result as [`Expr::eval`].
+    pub fn eval(&self, values: &[Complex64]) -> EvalResult {
+        self.eval_with(values, &mut Vec::with_capacity(self.stack_size))
+    }
+
+    /// Evaluates the expression like [`Compiled::eval`], using `stack` as the stack so that it
+    /// can be reused between points. The stack is left empty.
+    pub(crate) fn eval_with(&self, values: &[Complex64], stack: &mut Vec<Complex64>) -> EvalResult {
+        if let Some(var) = self.vars.get(values.len()) {
+            return Err(EvalError::VarMissing {
+                name: var.get_name(),
+            });
+        }
+
+        stack.clear();
+        for instruction in &self.instructions {
+            let value = match instruction {
+                Instruction::Const(value) => *value,
+
+                Instruction::Load(slot) => values[*slot],
+
+                Instruction::Sum { count, source } => {
+                    let mut sum = Complex64::new(0.0, 0.0);
+                    for term in stack.drain(stack.len() - count..) {
+                        sum += term;
+                    }
+                    self.defined(sum, *source)?
+                }
+
+                Instruction::Product { count, source } => {
+                    let mut product = Complex64::new(1.0, 0.0);
+                    for factor in stack.drain(stack.len() - count..) {
+                        product *= factor;
+                    }
+                    self.defined(product, *source)?
+                }
+
+                Instruction::Pow { source } => {
+                    let exponent = stack.pop().unwrap();
+                    let base_value = stack.pop().unwrap();
+
+                    if base_value == Complex64::new(0.0, 0.0) && exponent.re < 0.0 {
+                        // The base comes right after the power in preorder.
+                        return Err(EvalError::DivisionByZero {
+                            divisor: self.source(source + 1),
+                        });
+                    }
+
+                    self.defined(expr::pow(base_value, exponent), *source)?
+                }
+
+                Instruction::Call {
+                    def,
+                    count,
+                    globals,
+                } => {
+                    let args = stack.split_off(stack.len() - count);
+                    let globals: VarMap<Complex64> = globals
+                        .iter()
+                        .map(|(var, slot)| (var, values[*slot]))
+                        .collect();
+                    def.eval(args, &globals)?
+                }
+
+                Instruction::Undefined(source) => {
+                    return Err(EvalError::Undefined {
+                        expr: self.source(*source),
+                    })
+                }
+            };
+
+            stack.push(value);
+        }
+
+        Ok(stack.pop().unwrap())
+    }
+
+    /// Returns `value` unless it is NaN, like `Expr::defined`.
+    fn defined(&self, value: Complex64, source: usize) -> EvalResult {
+        if value.is_nan() {
+            Err(EvalError::Undefined {
+                expr: self.source(source),
+            })
+        } else {
+            Ok(value)
+        }
+    }
+
+    /// The subexpression with the given index in preorder.
+    fn source(&self, index: usize) -> Expr {
+        fn find<'e>(expr: &'e Expr, index: &mut usize) -> Option<&'e Expr> {
+            if *index == 0 {
+                return Some(expr);
+            }
+            *index -= 1;
+
+            match expr {
+                Expr::Sum(children) | Expr::Product(children) | Expr::Function(_, children) => {
+                    children.iter().find_map(|child| find(child, index))
+                }
+                Expr::Pow(base, exponent) => find(base, index).or_else(|| find(exponent, index)),
+                Expr::Var(_) | Expr::Const(_) => None,
+            }
+        }
+
+        find(&self.expr, &mut { index }).unwrap().clone()
+    }
+}
+
+/// An instruction of a [`Compiled`] expression. A `source` is the index in preorder of the
+/// subexpression the instruction evaluates, which is only looked up to report an error.
+#[derive(Debug, Clone)]
+enum Instruction {
+    Const(Complex64),
+    /// Pushes the value of a slot.
+    Load(usize),
+    /// Replaces the top `count` values with their sum.
+    Sum {
+        count: usize,
+        source: usize,
+    },
+    /// Replaces the top `count` values with their product.
+    Product {
+        count: usize,
+        source: usize,
+    },
+    /// Replaces a base and an exponent with the power.
+    Pow {
+        source: usize,
+    },
+    /// Replaces the top `count` values with the function applied to them. `globals` are the
+    /// free variables of the function along with their slots.
+    Call {
+        def: Arc<dyn FuncDef>,
+        count: usize,
+        globals: Vec<(Var, usize)>,
+    },
+    Undefined(usize),
+}
+
+struct Compiler<'v> {
+    slots: HashMap<&'v Var, usize>,
+    instructions: Vec<Instruction>,
+    /// The number of subexpressions visited so far.
+    nodes: usize,
+    /// The number of values on the stack after the instructions so far.
+    depth: usize,
+    stack_size: usize,
+}
+
+impl Compiler<'_> {
+    fn expr(&mut self, expr: &Expr) -> Result<(), EvalError> {
+        let source = self.nodes;
+        self.nodes += 1;
+
+        match expr {
+            Expr::Sum(terms) => {
+                self.exprs(terms)?;
+                self.emit(
+                    Instruction::Sum {
+                        count: terms.len(),
+                        source,
+                    },
+                    terms.len(),
+                );
+            }
+
+            Expr::Product(factors) => {
+                self.exprs(factors)?;
+                self.emit(
+                    Instruction::Product {
+                        count: factors.len(),
+                        source,
+                    },
+                    factors.len(),
+                );
+            }
+
+            Expr::Pow(base, exponent) => {
+                self.expr(base)?;
+                self.expr(exponent)?;
+                self.emit(Instruction::Pow { source }, 2);
+            }
+
+            Expr::Var(var) => {
+                let slot = self.slot(var)?;
+                self.emit(Instruction::Load(slot), 0);
+            }
+
+            Expr::Const(Num::Undefined) => {
+                self.emit(Instruction::Undefined(source), 0);
+            }
+
+            Expr::Const(num) => self.emit(Instruction::Const(num.eval_float()?), 0),
+
+            Expr::Function(def, args) => {
+                Arity::check(def.as_ref(), args.len())?;
+                self.exprs(args)?;
+
+                let globals = def
+                    .free_variables()
+                    .into_iter()
+                    .map(|var| Ok((var.clone(), self.slot(&var)?)))
+                    .collect::<Result<_, EvalError>>()?;
+
+                self.emit(
+                    Instruction::Call {
+                        def: def.clone(),
+                        count: args.len(),
+                        globals,
+                    },
+                    args.len(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exprs(&mut self, exprs: &[Expr]) -> Result<(), EvalError> {
+        for expr in exprs {
+            self.expr(expr)?;
+        }
+        Ok(())
+    }
+
+    /// Adds an instruction which pops `popped` values and pushes its result.
+    fn emit(&mut self, instruction: Instruction, popped: usize) {
+        self.instructions.push(instruction);
+        self.depth = self.depth - popped + 1;
+        self.stack_size = self.stack_size.max(self.depth);
+    }
+
+    fn slot(&self, var: &Var) -> Result<usize, EvalError> {
+        self.slots
+            .get(var)
+            .copied()
+            .ok_or_else(|| EvalError::VarMissing {
+                name: var.get_name(),
+            })
+    }
+}
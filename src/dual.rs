@@ -0,0 +1,656 @@
+//! Forward-mode automatic differentiation with dual and hyper-dual numbers.
+
+use std::sync::Arc;
+
+use num_complex::Complex64;
+
+use crate::{
+    expr::{self, EvalError, Expr},
+    function::{forward_to_complex, Arity, FuncDef, Function},
+    var::{Var, VarMap},
+};
+
+/// A value along with its partial derivatives, see [`Expr::eval_dual`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dual {
+    value: Complex64,
+    /// Empty for constants.
+    gradient: Vec<Complex64>,
+}
+
+impl Dual {
+    /// A value that does not depend on any variable.
+    pub fn constant(value: Complex64) -> Self {
+        Self {
+            value,
+            gradient: vec![],
+        }
+    }
+
+    /// The `index`-th of `count` variables.
+    pub fn variable(value: Complex64, index: usize, count: usize) -> Self {
+        let mut gradient = vec![Complex64::new(0.0, 0.0); count];
+        gradient[index] = Complex64::new(1.0, 0.0);
+        Self { value, gradient }
+    }
+
+    pub fn value(&self) -> Complex64 {
+        self.value
+    }
+
+    /// The partial derivative with respect to the `index`-th variable.
+    pub fn partial(&self, index: usize) -> Complex64 {
+        partial(&self.gradient, index)
+    }
+
+    /// The partial derivatives with respect to each variable.
+    pub fn gradient(&self) -> &[Complex64] {
+        &self.gradient
+    }
+}
+
+/// A value along with its first and second partial derivatives, see
+/// [`Expr::eval_hyper_dual`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HyperDual {
+    value: Complex64,
+    /// Empty for constants.
+    gradient: Vec<Complex64>,
+    /// The second partial derivatives in row-major order, empty for constants.
+    hessian: Vec<Complex64>,
+}
+
+impl HyperDual {
+    /// A value that does not depend on any variable.
+    pub fn constant(value: Complex64) -> Self {
+        Self {
+            value,
+            gradient: vec![],
+            hessian: vec![],
+        }
+    }
+
+    /// The `index`-th of `count` variables.
+    pub fn variable(value: Complex64, index: usize, count: usize) -> Self {
+        let mut gradient = vec![Complex64::new(0.0, 0.0); count];
+        gradient[index] = Complex64::new(1.0, 0.0);
+        Self {
+            value,
+            gradient,
+            hessian: vec![Complex64::new(0.0, 0.0); count * count],
+        }
+    }
+
+    pub fn value(&self) -> Complex64 {
+        self.value
+    }
+
+    /// The partial derivative with respect to the `index`-th variable.
+    pub fn partial(&self, index: usize) -> Complex64 {
+        partial(&self.gradient, index)
+    }
+
+    /// The partial derivatives with respect to each variable.
+    pub fn gradient(&self) -> &[Complex64] {
+        &self.gradient
+    }
+
+    /// The second partial derivative with respect to the `i`-th and `j`-th variables.
+    pub fn second_partial(&self, i: usize, j: usize) -> Complex64 {
+        partial(&self.hessian, i * self.gradient.len() + j)
+    }
+}
+
+fn partial(derivatives: &[Complex64], index: usize) -> Complex64 {
+    derivatives
+        .get(index)
+        .copied()
+        .unwrap_or(Complex64::new(0.0, 0.0))
+}
+
+/// A number carrying derivatives along, which functions are applied to with the chain rule.
+pub(crate) trait Jet: Clone {
+    /// Whether second derivatives are carried along.
+    const SECOND_ORDER: bool;
+
+    fn constant(value: Complex64) -> Self;
+    fn value(&self) -> Complex64;
+    fn is_constant(&self) -> bool;
+
+    /// Applies a function to `args` given its value and its first and second partial
+    /// derivatives at their values. An empty `second` means they are all zero.
+    fn chain(
+        args: &[&Self],
+        value: Complex64,
+        first: &[Complex64],
+        second: &[Vec<Complex64>],
+    ) -> Self;
+
+    /// The version of `def` for this kind of number.
+    fn function(def: &dyn FuncDef) -> Option<&dyn FuncDef<Self>>;
+}
+
+impl Jet for Dual {
+    const SECOND_ORDER: bool = false;
+
+    fn constant(value: Complex64) -> Self {
+        Dual::constant(value)
+    }
+
+    fn value(&self) -> Complex64 {
+        self.value
+    }
+
+    fn is_constant(&self) -> bool {
+        self.gradient.is_empty()
+    }
+
+    fn chain(args: &[&Self], value: Complex64, first: &[Complex64], _: &[Vec<Complex64>]) -> Self {
+        let count = args.iter().map(|arg| arg.gradient.len()).max().unwrap_or(0);
+        let mut gradient = vec![Complex64::new(0.0, 0.0); count];
+
+        // Constant arguments are skipped, so that their derivatives never meet an infinite
+        // partial derivative.
+        for (arg, first) in args.iter().zip(first).filter(|(arg, _)| !arg.is_constant()) {
+            for (result, derivative) in gradient.iter_mut().zip(&arg.gradient) {
+                *result += first * derivative;
+            }
+        }
+
+        Dual { value, gradient }
+    }
+
+    fn function(def: &dyn FuncDef) -> Option<&dyn FuncDef<Self>> {
+        def.as_dual()
+    }
+}
+
+impl Jet for HyperDual {
+    const SECOND_ORDER: bool = true;
+
+    fn constant(value: Complex64) -> Self {
+        HyperDual::constant(value)
+    }
+
+    fn value(&self) -> Complex64 {
+        self.value
+    }
+
+    fn is_constant(&self) -> bool {
+        self.gradient.is_empty()
+    }
+
+    fn chain(
+        args: &[&Self],
+        value: Complex64,
+        first: &[Complex64],
+        second: &[Vec<Complex64>],
+    ) -> Self {
+        let count = args.iter().map(|arg| arg.gradient.len()).max().unwrap_or(0);
+        let mut gradient = vec![Complex64::new(0.0, 0.0); count];
+        let mut hessian = vec![Complex64::new(0.0, 0.0); count * count];
+
+        let varying: Vec<usize> = (0..args.len())
+            .filter(|&i| !args[i].is_constant())
+            .collect();
+
+        for &i in &varying {
+            let arg = args[i];
+            for (result, derivative) in gradient.iter_mut().zip(&arg.gradient) {
+                *result += first[i] * derivative;
+            }
+            for (result, derivative) in hessian.iter_mut().zip(&arg.hessian) {
+                *result += first[i] * derivative;
+            }
+
+            if second.is_empty() {
+                continue;
+            }
+
+            // d²f/dx_j dx_k also gets f_il (d arg_i/dx_j) (d arg_l/dx_k).
+            for &l in &varying {
+                let other = args[l];
+                for (j, a) in arg.gradient.iter().enumerate() {
+                    for (k, b) in other.gradient.iter().enumerate() {
+                        hessian[j * count + k] += second[i][l] * a * b;
+                    }
+                }
+            }
+        }
+
+        HyperDual {
+            value,
+            gradient,
+            hessian,
+        }
+    }
+
+    fn function(def: &dyn FuncDef) -> Option<&dyn FuncDef<Self>> {
+        def.as_hyper_dual()
+    }
+}
+
+impl Expr {
+    /// Evaluates the expression along with its partial derivatives with respect to `wrt`, in a
+    /// single pass. The `i`-th partial derivative of the result is with respect to `wrt[i]`.
+    pub fn eval_dual(
+        &self,
+        var_values: &VarMap<Complex64>,
+        wrt: &[&Var],
+    ) -> Result<Dual, EvalError> {
+        let values = seed(var_values, wrt, Dual::variable);
+        let mut result: Dual = self.eval_jet(&values)?;
+
+        // Constants carry no derivatives along, so they are filled in here.
+        result.gradient.resize(wrt.len(), Complex64::new(0.0, 0.0));
+        Ok(result)
+    }
+
+    /// Evaluates the expression along with its first and second partial derivatives with
+    /// respect to `wrt`, in a single pass.
+    pub fn eval_hyper_dual(
+        &self,
+        var_values: &VarMap<Complex64>,
+        wrt: &[&Var],
+    ) -> Result<HyperDual, EvalError> {
+        let values = seed(var_values, wrt, HyperDual::variable);
+        let mut result: HyperDual = self.eval_jet(&values)?;
+
+        result.gradient.resize(wrt.len(), Complex64::new(0.0, 0.0));
+        result
+            .hessian
+            .resize(wrt.len() * wrt.len(), Complex64::new(0.0, 0.0));
+        Ok(result)
+    }
+
+    pub(crate) fn eval_jet<J: Jet>(&self, var_values: &VarMap<J>) -> Result<J, EvalError> {
+        let defined = |value: J| {
+            if value.value().is_nan() {
+                Err(EvalError::Undefined { expr: self.clone() })
+            } else {
+                Ok(value)
+            }
+        };
+
+        match self {
+            Expr::Sum(terms) => {
+                let terms = terms
+                    .iter()
+                    .map(|term| term.eval_jet(var_values))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let value = terms.iter().map(J::value).sum();
+                let ones = vec![Complex64::new(1.0, 0.0); terms.len()];
+
+                defined(J::chain(
+                    &terms.iter().collect::<Vec<_>>(),
+                    value,
+                    &ones,
+                    &[],
+                ))
+            }
+
+            Expr::Product(terms) => {
+                let mut product = J::constant(Complex64::new(1.0, 0.0));
+
+                for term in terms {
+                    let term = term.eval_jet(var_values)?;
+                    let (a, b) = (product.value(), term.value());
+                    let (zero, one) = (Complex64::new(0.0, 0.0), Complex64::new(1.0, 0.0));
+
+                    product = J::chain(
+                        &[&product, &term],
+                        a * b,
+                        &[b, a],
+                        &[vec![zero, one], vec![one, zero]],
+                    );
+                }
+
+                defined(product)
+            }
+
+            Expr::Pow(base_expr, exponent) => {
+                let base = base_expr.eval_jet(var_values)?;
+                let exponent = exponent.eval_jet(var_values)?;
+                let (u, v) = (base.value(), exponent.value());
+
+                if u == Complex64::new(0.0, 0.0) && v.re < 0.0 {
+                    return Err(EvalError::DivisionByZero {
+                        divisor: base_expr.as_ref().clone(),
+                    });
+                }
+
+                let one = Complex64::new(1.0, 0.0);
+                let value = expr::pow(u, v);
+                let ln = u.ln();
+                // Written so that e.g. x^1 has a second derivative of 0 at x = 0.
+                let scaled_pow = |factor: Complex64, exponent: Complex64| {
+                    if factor == Complex64::new(0.0, 0.0) {
+                        factor
+                    } else {
+                        factor * expr::pow(u, exponent)
+                    }
+                };
+
+                // Powers of 0 times logarithms of it are 0 in the limit, e.g. ∂(x^y)/∂y = 0 at x = 0.
+                let times_ln = |factor: Complex64| {
+                    if factor == Complex64::new(0.0, 0.0) {
+                        factor
+                    } else {
+                        factor * ln
+                    }
+                };
+
+                let first = [scaled_pow(v, v - one), times_ln(value)];
+                let lower = scaled_pow(one, v - one);
+                let mixed = lower + v * times_ln(lower);
+                let second = [
+                    vec![scaled_pow(v * (v - one), v - one - one), mixed],
+                    vec![mixed, times_ln(times_ln(value))],
+                ];
+
+                defined(J::chain(&[&base, &exponent], value, &first, &second))
+            }
+
+            Expr::Var(var) => var_values
+                .get(var.as_ref())
+                .cloned()
+                .ok_or(EvalError::VarMissing {
+                    name: var.get_name(),
+                }),
+
+            Expr::Const(num) => Ok(J::constant(num.eval_float()?)),
+
+            Expr::Function(def, args) => {
+                Arity::check(def.as_ref(), args.len())?;
+
+                let args = args
+                    .iter()
+                    .map(|arg| arg.eval_jet(var_values))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                match J::function(def.as_ref()) {
+                    Some(def) => def.eval(args, var_values),
+                    None => symbolic_chain(def, &args, var_values),
+                }
+            }
+        }
+    }
+}
+
+/// Gives the variables in `wrt` their own direction, and every other variable none.
+fn seed<'v, J: Jet>(
+    var_values: &VarMap<'v, Complex64>,
+    wrt: &[&Var],
+    variable: fn(Complex64, usize, usize) -> J,
+) -> VarMap<'v, J> {
+    var_values
+        .iter()
+        .map(|(var, value)| {
+            let jet = match wrt.iter().position(|wrt| wrt == var) {
+                Some(index) => variable(*value, index, wrt.len()),
+                None => J::constant(*value),
+            };
+            (*var, jet)
+        })
+        .collect()
+}
+
+/// Applies a function without a version for `J` using its symbolic partial derivatives.
+fn symbolic_chain<J: Jet>(
+    def: &Arc<dyn FuncDef>,
+    args: &[J],
+    var_values: &VarMap<J>,
+) -> Result<J, EvalError> {
+    let values: VarMap<Complex64> = var_values
+        .iter()
+        .map(|(var, value)| (*var, value.value()))
+        .collect();
+    // Constant arguments are skipped, as `chain` does.
+    let varying: Vec<bool> = args.iter().map(|arg| !arg.is_constant()).collect();
+    let arg_values: Vec<Complex64> = args.iter().map(J::value).collect();
+
+    let partials = symbolic_partials(
+        def.as_ref(),
+        &arg_values,
+        &varying,
+        &values,
+        J::SECOND_ORDER,
+    )?;
+
+    Ok(J::chain(
+        &args.iter().collect::<Vec<_>>(),
+        partials.value,
+        &partials.first,
+        &partials.second,
+    ))
+}
+
+/// The value of a function along with its partial derivatives, from [`symbolic_partials`].
+pub(crate) struct SymbolicPartials {
+    pub value: Complex64,
+    pub first: Vec<Complex64>,
+    pub second: Vec<Vec<Complex64>>,
+}
+
+/// Evaluates a function at `args` along with its partial derivatives from
+/// [`FuncDef::partial_derivative`], for functions without a version for the kind of number at
+/// hand. Only the partial derivatives with respect to arguments where `varying` is true are
+/// found and the others are zero, so that functions without known partial derivatives can
+/// still be applied to constants. The second partial derivatives are empty unless
+/// `second_order` is set.
+pub(crate) fn symbolic_partials(
+    def: &dyn FuncDef,
+    args: &[Complex64],
+    varying: &[bool],
+    var_values: &VarMap<Complex64>,
+    second_order: bool,
+) -> Result<SymbolicPartials, EvalError> {
+    let params: Vec<Var> = (0..args.len())
+        .map(|i| Var::new_owned(&format!("#{i}")))
+        .collect();
+    let param_exprs: Vec<Expr> = params
+        .iter()
+        .map(|param| Expr::Var(Arc::new(param.clone())))
+        .collect();
+
+    let mut values: VarMap<Complex64> = var_values
+        .iter()
+        .map(|(var, value)| (*var, *value))
+        .collect();
+    values.extend(params.iter().zip(args.iter().copied()));
+
+    let value = def.eval(args.to_vec(), &values)?;
+
+    let zero = Complex64::new(0.0, 0.0);
+    let mut first = vec![zero; args.len()];
+    let mut second = vec![];
+    if second_order {
+        second = vec![vec![zero; args.len()]; args.len()];
+    }
+    for i in (0..args.len()).filter(|&i| varying[i]) {
+        let partial = def.partial_derivative(&param_exprs, i);
+        first[i] = partial.eval(&values)?;
+
+        if second_order {
+            for j in (0..args.len()).filter(|&j| varying[j]) {
+                second[i][j] = partial.derivative(&params[j]).eval(&values)?;
+            }
+        }
+    }
+
+    Ok(SymbolicPartials {
+        value,
+        first,
+        second,
+    })
+}
+
+/// Applies a built-in function, whose partial derivatives are given by `partials`.
+fn eval_function<J: Jet>(
+    function: &Function,
+    args: Vec<J>,
+    global_vars: &VarMap<J>,
+) -> Result<J, EvalError> {
+    Arity::check::<Complex64>(function, args.len())?;
+
+    if let Function::F(_, body, params) = function {
+        let mut map: VarMap<J> = global_vars.clone();
+
+        for (param, arg) in params.iter().zip(args) {
+            map.insert(param, arg);
+        }
+
+        return body.eval_jet(&map);
+    }
+
+    // The value comes from the complex evaluation, which also checks the domain.
+    let values: Vec<Complex64> = args.iter().map(J::value).collect();
+    let value = FuncDef::<Complex64>::eval(function, values.clone(), &VarMap::new())?;
+    let (first, second) = partials(function, &values, value);
+
+    Ok(J::chain(
+        &args.iter().collect::<Vec<_>>(),
+        value,
+        &first,
+        &second,
+    ))
+}
+
+/// The first and second partial derivatives of a built-in function at `args`, where it has
+/// the value `value`.
+fn partials(
+    function: &Function,
+    args: &[Complex64],
+    value: Complex64,
+) -> (Vec<Complex64>, Vec<Vec<Complex64>>) {
+    let zero = Complex64::new(0.0, 0.0);
+    let one = Complex64::new(1.0, 0.0);
+    let unary = |first: Complex64, second: Complex64| (vec![first], vec![vec![second]]);
+    let u = args[0];
+
+    match function {
+        Function::F(..) => unreachable!(),
+        Function::Abs => {
+            let sgn = if u == zero { zero } else { u / u.norm() };
+            unary(sgn, zero)
+        }
+        Function::Sgn => unary(zero, zero),
+        Function::Exp => unary(value, value),
+        Function::Ln => unary(u.inv(), -u.powi(-2)),
+        Function::Sin => unary(u.cos(), -value),
+        Function::Cos => unary(-u.sin(), -value),
+        Function::Tan => {
+            let sec2 = one + value * value;
+            unary(sec2, 2.0 * value * sec2)
+        }
+        Function::Arcsin => {
+            let root = (one - u * u).sqrt();
+            unary(root.inv(), u / root.powi(3))
+        }
+        Function::Arccos => {
+            let root = (one - u * u).sqrt();
+            unary(-root.inv(), -u / root.powi(3))
+        }
+        Function::Arctan => {
+            let denominator = one + u * u;
+            unary(denominator.inv(), -2.0 * u / (denominator * denominator))
+        }
+        Function::Sinh => unary(u.cosh(), value),
+        Function::Cosh => unary(u.sinh(), value),
+        Function::Tanh => {
+            let sech2 = one - value * value;
+            unary(sech2, -2.0 * value * sech2)
+        }
+        Function::Arcsinh => {
+            let root = (u * u + one).sqrt();
+            unary(root.inv(), -u / root.powi(3))
+        }
+        Function::Arccosh => {
+            // As a product of two roots, so that it also holds off the real line.
+            let root = (u - one).sqrt() * (u + one).sqrt();
+            unary(root.inv(), -u / root.powi(3))
+        }
+        Function::Arctanh => {
+            let denominator = one - u * u;
+            unary(denominator.inv(), 2.0 * u / (denominator * denominator))
+        }
+        Function::Atan2 => {
+            let (y, x) = (args[0], args[1]);
+            let r2 = x * x + y * y;
+            let r4 = r2 * r2;
+            (
+                vec![x / r2, -y / r2],
+                vec![
+                    vec![-2.0 * x * y / r4, (y * y - x * x) / r4],
+                    vec![(y * y - x * x) / r4, 2.0 * x * y / r4],
+                ],
+            )
+        }
+        Function::Log => {
+            let (b, x) = (args[0], args[1]);
+            let (ln_b, ln_x) = (b.ln(), x.ln());
+            let mixed = -(x * b * ln_b * ln_b).inv();
+            (
+                vec![-ln_x / (b * ln_b * ln_b), (x * ln_b).inv()],
+                vec![
+                    vec![ln_x * (ln_b + 2.0) / (b * b * ln_b.powi(3)), mixed],
+                    vec![mixed, -(x * x * ln_b).inv()],
+                ],
+            )
+        }
+        Function::Min | Function::Max => {
+            // The derivative is that of the argument which is the extremum, shared equally among
+            // tied arguments like the symbolic partial derivatives.
+            let tied = args.iter().filter(|arg| **arg == value).count();
+            let share = one / tied as f64;
+            let first = args
+                .iter()
+                .map(|arg| if *arg == value { share } else { zero })
+                .collect();
+            (first, vec![])
+        }
+        Function::Hypot => {
+            let (x, y) = (args[0], args[1]);
+            let h3 = value.powi(3);
+            (
+                vec![x / value, y / value],
+                vec![vec![y * y / h3, -x * y / h3], vec![-x * y / h3, x * x / h3]],
+            )
+        }
+        Function::Root => {
+            let (n, x) = (args[0], args[1]);
+            let ln_x = x.ln();
+            let mixed = -value * (ln_x + n) / (n.powi(3) * x);
+            (
+                vec![-value * ln_x / (n * n), value / (n * x)],
+                vec![
+                    vec![value * ln_x * (ln_x + 2.0 * n) / n.powi(4), mixed],
+                    vec![mixed, value * (one - n) / (n * n * x * x)],
+                ],
+            )
+        }
+    }
+}
+
+/// Forward-mode differentiation of the built-in functions, see [`Expr::eval_dual`].
+impl FuncDef<Dual> for Function {
+    fn eval(&self, args: Vec<Dual>, global_vars: &VarMap<Dual>) -> Result<Dual, EvalError> {
+        eval_function(self, args, global_vars)
+    }
+
+    forward_to_complex!();
+}
+
+/// Second-order forward-mode differentiation of the built-in functions, see
+/// [`Expr::eval_hyper_dual`].
+impl FuncDef<HyperDual> for Function {
+    fn eval(
+        &self,
+        args: Vec<HyperDual>,
+        global_vars: &VarMap<HyperDual>,
+    ) -> Result<HyperDual, EvalError> {
+        eval_function(self, args, global_vars)
+    }
+
+    forward_to_complex!();
+}
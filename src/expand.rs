@@ -0,0 +1,217 @@
+//! Algebraic expansion, distributing products and integer powers over sums.
+
+use num_bigint::BigInt;
+use num_traits::One;
+use std::fmt::{self, Display, Formatter};
+
+use crate::{expr::Expr, num::Num};
+
+/// Options for [`Expr::expand_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpandOptions {
+    /// Whether the arguments of function calls are expanded as well, so that `sin((x + 1)^2)`
+    /// becomes `sin(2x + x^2 + 1)`.
+    pub functions: bool,
+    /// The most terms a sum may have at any point during the expansion, before like terms are
+    /// collected.
+    pub max_terms: usize,
+}
+
+impl Default for ExpandOptions {
+    fn default() -> Self {
+        Self {
+            functions: false,
+            max_terms: 10_000,
+        }
+    }
+}
+
+/// The reason an expression could not be expanded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpandError {
+    /// Expanding would produce more terms than [`ExpandOptions::max_terms`].
+    TooManyTerms { limit: usize },
+}
+
+impl Display for ExpandError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyTerms { limit } => {
+                write!(f, "The expansion has more than {limit} terms.")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExpandError {}
+
+impl Expr {
+    /// Expands the expression with the default [`ExpandOptions`], see [`Expr::expand_with`].
+    pub fn expand(&self) -> Result<Expr, ExpandError> {
+        self.expand_with(ExpandOptions::default())
+    }
+
+    /// Distributes products over sums and expands positive integer powers of sums with the
+    /// multinomial theorem, so that `(x + 1)(x + 2)` and `x^2 + 3x + 2` expand to the same
+    /// expression. Like terms are collected and the result is simplified with
+    /// [`Expr::simplify`].
+    ///
+    /// Bases of negative integer powers are expanded as well, so `(x + 1)^-2` becomes
+    /// `1/(2x + x^2 + 1)`. Other powers, such as `(x + 1)^(1/2)`, are kept as they are apart
+    /// from expanding their base and exponent.
+    pub fn expand_with(&self, options: ExpandOptions) -> Result<Expr, ExpandError> {
+        Ok(Expr::Sum(self.expand_terms(&options)?).simplify())
+    }
+
+    /// The terms of the expanded expression, none of which is a sum.
+    fn expand_terms(&self, options: &ExpandOptions) -> Result<Vec<Expr>, ExpandError> {
+        match self {
+            Expr::Sum(terms) => {
+                let mut expanded = vec![];
+                for term in terms {
+                    expanded.extend(term.expand_terms(options)?);
+                    check_limit(expanded.len(), options)?;
+                }
+                Ok(collect(expanded))
+            }
+
+            Expr::Product(factors) => {
+                let mut expanded = vec![Expr::Const(Num::One)];
+                for factor in factors {
+                    let terms = factor.expand_terms(options)?;
+                    check_limit(expanded.len().saturating_mul(terms.len()), options)?;
+
+                    let products = expanded
+                        .iter()
+                        .flat_map(|a| {
+                            terms
+                                .iter()
+                                .map(move |b| Expr::Product(vec![a.clone(), b.clone()]))
+                        })
+                        .collect();
+                    expanded = collect(products);
+                }
+                Ok(expanded)
+            }
+
+            Expr::Pow(base, exponent) => {
+                let n = match exponent.as_ref() {
+                    Expr::Const(n) => n.as_integer(),
+                    _ => None,
+                };
+                match n {
+                    Some(n) if n > 0 => {
+                        multinomial(&base.expand_terms(options)?, n as u32, options)
+                    }
+                    Some(n) if n < 0 => {
+                        let expanded =
+                            multinomial(&base.expand_terms(options)?, n.unsigned_abs(), options)?;
+                        Ok(vec![Expr::Sum(expanded)
+                            .simplify()
+                            .pow(Expr::Const(Num::from(-1)))])
+                    }
+                    _ => Ok(vec![base
+                        .expand_with(*options)?
+                        .pow(exponent.expand_with(*options)?)]),
+                }
+            }
+
+            Expr::Function(def, args) if options.functions => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.expand_with(*options))
+                    .collect::<Result<_, _>>()?;
+                Ok(vec![Expr::Function(def.clone(), args)])
+            }
+
+            Expr::Function(..) | Expr::Var(_) | Expr::Const(_) => Ok(vec![self.clone()]),
+        }
+    }
+}
+
+/// The terms of `(terms[0] + terms[1] + ...)^n`, by the multinomial theorem.
+fn multinomial(terms: &[Expr], n: u32, options: &ExpandOptions) -> Result<Vec<Expr>, ExpandError> {
+    if terms.is_empty() {
+        return Ok(vec![]);
+    }
+    if let [term] = terms {
+        return Ok(vec![term.clone().pow(Expr::Const(Num::from(n as i64)))]);
+    }
+
+    // There are binomial(n + m - 1, m - 1) ways to split the exponent among m terms.
+    let m = terms.len();
+    let mut count: usize = 1;
+    for i in 1..m {
+        count = count
+            .checked_mul(n as usize + i)
+            .map(|count| count / i)
+            .filter(|count| *count <= options.max_terms)
+            .ok_or(ExpandError::TooManyTerms {
+                limit: options.max_terms,
+            })?;
+    }
+
+    let mut expanded = Vec::with_capacity(count);
+    let mut exponents = vec![0; m];
+    split_exponent(terms, 0, n, &mut exponents, &BigInt::one(), &mut expanded);
+    Ok(collect(expanded))
+}
+
+/// Pushes the terms of the multinomial expansion for every way of splitting `remaining` among
+/// the terms from `index` on, where `coefficient` is the product of the binomial coefficients
+/// of the exponents chosen so far.
+fn split_exponent(
+    terms: &[Expr],
+    index: usize,
+    remaining: u32,
+    exponents: &mut [u32],
+    coefficient: &BigInt,
+    expanded: &mut Vec<Expr>,
+) {
+    if index == terms.len() - 1 {
+        exponents[index] = remaining;
+
+        let mut factors = vec![Expr::Const(Num::from(coefficient.clone()))];
+        for (term, exponent) in terms.iter().zip(exponents.iter()) {
+            if *exponent > 0 {
+                factors.push(term.clone().pow(Expr::Const(Num::from(*exponent as i64))));
+            }
+        }
+        expanded.push(Expr::Product(factors));
+        return;
+    }
+
+    // binomial(remaining, k), updated as k increases.
+    let mut binomial = BigInt::one();
+    for k in 0..=remaining {
+        exponents[index] = k;
+        split_exponent(
+            terms,
+            index + 1,
+            remaining - k,
+            exponents,
+            &(coefficient * &binomial),
+            expanded,
+        );
+        binomial = binomial * (remaining - k) / (k + 1);
+    }
+}
+
+/// Errors if a sum of `count` terms would exceed the limit.
+fn check_limit(count: usize, options: &ExpandOptions) -> Result<(), ExpandError> {
+    if count > options.max_terms {
+        return Err(ExpandError::TooManyTerms {
+            limit: options.max_terms,
+        });
+    }
+    Ok(())
+}
+
+/// Collects like terms, returning the terms of the simplified sum.
+fn collect(terms: Vec<Expr>) -> Vec<Expr> {
+    match Expr::Sum(terms).simplify() {
+        Expr::Sum(terms) => terms,
+        Expr::Const(num) if num.is_zero() => vec![],
+        term => vec![term],
+    }
+}
@@ -1,27 +1,61 @@
 use num_complex::Complex64;
+use num_integer::Integer;
 use std::collections::HashMap;
 use std::fmt::{self, Display, Formatter};
-use std::ops::{Add, Mul, Sub, Div};
+use std::ops::{Add, Div, Mul, Sub};
 use std::sync::Arc;
 
-use crate::{function::FuncDef, num::Num, var::Var};
+use crate::{
+    function::{Arity, FuncDef, Function},
+    num::Num,
+    print::PrintOptions,
+    var::{Var, VarMap},
+};
 
 /// A type represnting a possible error during expression evaluation
 #[derive(Debug)]
 pub enum EvalError {
     VarMissing { name: String },
-    FnArgCountMismatch {},
+    FnArgCountMismatch {
+        name: String,
+        expected: Arity,
+        found: usize,
+    },
+    /// The expression has no value, e.g. it is `Num::Undefined` or an indeterminate form like
+    /// `∞ - ∞`.
+    Undefined { expr: Expr },
+    /// A function was called with an argument outside of its domain, e.g. `ln(0)`.
+    DomainError { function: String, arg: Complex64 },
+    /// The divisor of a division (a negative power) evaluated to zero.
+    DivisionByZero { divisor: Expr },
+    /// The expression has no real value, e.g. `i` or `(-1)^(1/2)`, see [`Expr::eval_real`].
+    NotReal { expr: Expr },
 }
 
 impl Display for EvalError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Self::VarMissing { name } => write!(f, "Missing variable '{name}' in eval."),
-            Self::FnArgCountMismatch {} => write!(f, "Incorrect number of function arguments."),
+            Self::FnArgCountMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Function '{name}' takes {expected} argument(s) but {found} were given."
+            ),
+            Self::Undefined { expr } => write!(f, "The value of {expr} is undefined."),
+            Self::DomainError { function, arg } => {
+                write!(f, "{arg} is outside of the domain of '{function}'.")
+            }
+            Self::DivisionByZero { divisor } => write!(f, "Division by zero, as {divisor} is 0."),
+            Self::NotReal { expr } => write!(f, "The value of {expr} is not real."),
         }
     }
 }
 
+impl std::error::Error for EvalError {}
+
 pub type EvalResult = Result<Complex64, EvalError>;
 
 /// A type representing a mathematical expression.
@@ -29,6 +63,8 @@ pub type EvalResult = Result<Complex64, EvalError>;
 pub enum Expr {
     Sum(Vec<Expr>),
     Product(Vec<Expr>),
+    /// `base ^ exponent`
+    Pow(Box<Expr>, Box<Expr>),
     Var(Arc<Var>),
     Const(Num),
     Function(Arc<dyn FuncDef>, Vec<Expr>),
@@ -45,7 +81,7 @@ impl Expr {
                     sum += term.eval(var_values)?;
                 }
 
-                Ok(sum)
+                self.defined(sum)
             }
 
             Self::Product(terms) => {
@@ -55,19 +91,33 @@ impl Expr {
                     product *= term.eval(var_values)?
                 }
 
-                Ok(product)
+                self.defined(product)
             }
 
-            Self::Var(var) => Ok(var_values
+            Self::Pow(base_expr, exponent) => {
+                let base = base_expr.eval(var_values)?;
+                let exponent = exponent.eval(var_values)?;
+
+                if base == Complex64::new(0.0, 0.0) && exponent.re < 0.0 {
+                    return Err(EvalError::DivisionByZero {
+                        divisor: base_expr.as_ref().clone(),
+                    });
+                }
+
+                self.defined(pow(base, exponent))
+            }
+
+            Self::Var(var) => Ok(*var_values
                 .get(var.as_ref())
                 .ok_or(EvalError::VarMissing {
                     name: var.get_name(),
-                })?
-                .clone()),
+                })?),
 
-            Self::Const(num) => Ok(num.eval_float()),
+            Self::Const(num) => num.eval_float(),
 
             Self::Function(def, args) => {
+                Arity::check(def.as_ref(), args.len())?;
+
                 let mut evaluated_args = Vec::with_capacity(args.len());
 
                 for arg in args {
@@ -79,6 +129,127 @@ impl Expr {
         }
     }
 
+    /// Evaluates an expression over the real numbers, failing with [`EvalError::NotReal`] or
+    /// [`EvalError::DomainError`] where the value is not real, e.g. for `i`, `(-1)^(1/2)` or
+    /// `ln(-1)`.
+    pub fn eval_real(&self, var_values: &VarMap<f64>) -> Result<f64, EvalError> {
+        let defined = |value: f64| {
+            if value.is_nan() {
+                Err(EvalError::Undefined { expr: self.clone() })
+            } else {
+                Ok(value)
+            }
+        };
+
+        match self {
+            Self::Sum(terms) => {
+                let mut sum = 0.0;
+
+                for term in terms {
+                    sum += term.eval_real(var_values)?;
+                }
+
+                defined(sum)
+            }
+
+            Self::Product(terms) => {
+                let mut product = 1.0;
+
+                for term in terms {
+                    product *= term.eval_real(var_values)?;
+                }
+
+                defined(product)
+            }
+
+            Self::Pow(base_expr, exponent_expr) => {
+                let base = base_expr.eval_real(var_values)?;
+                let exponent = exponent_expr.eval_real(var_values)?;
+
+                if base == 0.0 && exponent < 0.0 {
+                    return Err(EvalError::DivisionByZero {
+                        divisor: base_expr.as_ref().clone(),
+                    });
+                }
+
+                if exponent.fract() == 0.0 && exponent.abs() <= i32::MAX as f64 {
+                    return defined(base.powi(exponent as i32));
+                }
+                if base >= 0.0 {
+                    return defined(base.powf(exponent));
+                }
+
+                // A negative number only has a real power if the exponent is a fraction with an
+                // odd denominator, e.g. (-8)^(2/3) = 4.
+                let fraction = match exponent_expr.as_ref() {
+                    Expr::Const(num) => num.as_fraction(),
+                    _ => None,
+                };
+
+                match fraction {
+                    Some((num, den)) if den.is_odd() => {
+                        let magnitude = (-base).powf(exponent);
+                        defined(if num.is_odd() { -magnitude } else { magnitude })
+                    }
+                    _ => Err(EvalError::NotReal { expr: self.clone() }),
+                }
+            }
+
+            Self::Var(var) => Ok(*var_values
+                .get(var.as_ref())
+                .ok_or(EvalError::VarMissing {
+                    name: var.get_name(),
+                })?),
+
+            Self::Const(num) => {
+                let value = num.eval_float()?;
+
+                if value.im == 0.0 {
+                    Ok(value.re)
+                } else {
+                    Err(EvalError::NotReal { expr: self.clone() })
+                }
+            }
+
+            Self::Function(def, args) => {
+                Arity::check(def.as_ref(), args.len())?;
+
+                let mut evaluated_args = Vec::with_capacity(args.len());
+
+                for arg in args {
+                    evaluated_args.push(arg.eval_real(var_values)?);
+                }
+
+                if let Some(def) = def.as_real() {
+                    return def.eval(evaluated_args, var_values);
+                }
+
+                // Functions without a real version are evaluated over the complex numbers.
+                let complex_vars = var_values
+                    .iter()
+                    .map(|(var, value)| (*var, Complex64::from(*value)))
+                    .collect();
+                let complex_args = evaluated_args.into_iter().map(Complex64::from).collect();
+                let value = def.eval(complex_args, &complex_vars)?;
+
+                if value.im == 0.0 {
+                    Ok(value.re)
+                } else {
+                    Err(EvalError::NotReal { expr: self.clone() })
+                }
+            }
+        }
+    }
+
+    /// Returns `value` unless it is NaN, which only comes out of indeterminate forms.
+    pub(crate) fn defined(&self, value: Complex64) -> EvalResult {
+        if value.is_nan() {
+            Err(EvalError::Undefined { expr: self.clone() })
+        } else {
+            Ok(value)
+        }
+    }
+
     /// Determines whether an expression is variant on the given variable.
     pub fn is_variant_on(&self, var: &Var) -> bool {
         match self {
@@ -92,6 +263,8 @@ impl Expr {
                 is_variant
             }
 
+            Self::Pow(base, exponent) => base.is_variant_on(var) || exponent.is_variant_on(var),
+
             Self::Var(checking_var) => var == checking_var.as_ref(),
 
             Self::Const(..) => false,
@@ -110,19 +283,80 @@ impl Expr {
     }
 
     /// Checks if all of the operands are constants
-    pub fn constant_operands(terms: &Vec<Expr>) -> bool {
-        let mut const_operands = true;
+    pub fn constant_operands(terms: &[Expr]) -> bool {
+        terms.iter().all(|term| matches!(term, Expr::Const(_)))
+    }
 
-        for term in terms.iter() {
-            if let Expr::Const(_) = term {
-                ()
-            } else {
-                const_operands = false;
-                break;
+    /// Raises the expression to the power of `exponent`.
+    pub fn pow(self, exponent: Expr) -> Expr {
+        Expr::Pow(Box::new(self), Box::new(exponent))
+    }
+
+    /// Returns `exponent - 1`, folding it when the exponent is an integer constant.
+    fn decrement(exponent: &Expr) -> Expr {
+        if let Expr::Const(n) = exponent {
+            if let Some(n) = n.as_integer().and_then(|n| n.checked_sub(1)) {
+                return Expr::Const(Num::from(n));
             }
         }
 
-        const_operands
+        Expr::Sum(vec![exponent.clone(), Expr::Const(Num::from(-1))])
+    }
+
+    /// Combines non-constant factors with the same base into a single power.
+    fn merge_powers(factors: Vec<Expr>) -> Vec<Expr> {
+        let mut merged: Vec<(Expr, Vec<Expr>)> = vec![];
+
+        for factor in factors {
+            let (base, exponent) = match factor {
+                Expr::Pow(base, exponent) => (*base, *exponent),
+                Expr::Const(_) => {
+                    merged.push((factor, vec![]));
+                    continue;
+                }
+                _ => (factor, Expr::Const(Num::One)),
+            };
+
+            match merged
+                .iter_mut()
+                .find(|(other, exponents)| {
+                    !exponents.is_empty() && Expr::exact_match(&base, other)
+                })
+            {
+                Some((_, exponents)) => exponents.push(exponent),
+                None => merged.push((base, vec![exponent])),
+            }
+        }
+
+        merged
+            .into_iter()
+            .filter_map(|(base, exponents)| {
+                if exponents.is_empty() {
+                    return Some(base);
+                }
+
+                let exponent = if exponents.len() == 1 {
+                    exponents.into_iter().next().unwrap()
+                } else {
+                    let integer_sum = exponents
+                        .iter()
+                        .try_fold(0i32, |sum, exponent| match exponent {
+                            Expr::Const(n) => sum.checked_add(n.as_integer()?),
+                            _ => None,
+                        });
+
+                    match integer_sum {
+                        Some(sum) => Expr::Const(Num::from(sum)),
+                        None => Expr::Sum(exponents),
+                    }
+                };
+
+                match base.pow(exponent).simplify_trivial_single_layer() {
+                    Expr::Const(n) if n.is_one() => None,
+                    factor => Some(factor),
+                }
+            })
+            .collect()
     }
 
     /// Takes partial derivative of the expression with respect to `var`. 
@@ -133,12 +367,12 @@ impl Expr {
                     Expr::Const(Num::Zero)
                 } else {
                     // (a + b + c)' = a' + b' + c'
-                    Expr::Sum(terms.into_iter().map(|v| v.derivative(var)).collect())
+                    Expr::Sum(terms.iter().map(|v| v.derivative(var)).collect())
                 }
             }
 
             Expr::Product(terms) => {
-                if Self::constant_operands(&terms) {
+                if Self::constant_operands(terms) {
                     return Expr::Const(Num::Zero);
                 }
 
@@ -146,21 +380,49 @@ impl Expr {
 
                 let mut new_terms = vec![terms.clone(); terms.len()];
 
-                for i in 0..terms.len() {
-                    new_terms[i][i] = new_terms[i][i].derivative(var);
+                for (i, new_term) in new_terms.iter_mut().enumerate() {
+                    new_term[i] = new_term[i].derivative(var);
                 }
 
-                dbg!(&new_terms);
-
                 Expr::Sum(
                     new_terms
                         .into_iter()
-                        .map(|v| Expr::Product(v))
+                        .map(Expr::Product)
                         .collect(),
                 )
 
             }
 
+            Expr::Pow(base, exponent) => {
+                if !exponent.is_variant_on(var) {
+                    // (a^n)' = n * a^(n - 1) * a'
+                    Expr::Product(vec![
+                        exponent.as_ref().clone(),
+                        base.as_ref().clone().pow(Self::decrement(exponent)),
+                        base.derivative(var),
+                    ])
+                } else {
+                    // (a^b)' = a^b * (b' * ln(a) + b * a' / a)
+                    Expr::Product(vec![
+                        self.clone(),
+                        Expr::Sum(vec![
+                            Expr::Product(vec![
+                                exponent.derivative(var),
+                                Expr::Function(
+                                    Arc::new(Function::Ln),
+                                    vec![base.as_ref().clone()],
+                                ),
+                            ]),
+                            Expr::Product(vec![
+                                exponent.as_ref().clone(),
+                                base.derivative(var),
+                                base.as_ref().clone().pow(Expr::Const(Num::from(-1))),
+                            ]),
+                        ]),
+                    ])
+                }
+            }
+
             Expr::Var(expr_var) => {
                 // dx/dx = 1, dy/dx = 0, assuming x and y are both independent vars
                 if expr_var.as_ref() == var {
@@ -175,7 +437,26 @@ impl Expr {
                 Expr::Const(Num::Zero)
             }
 
-            Expr::Function(_, _) => todo!(),
+            Expr::Function(def, args) => {
+                // f(u, v)' = f_u(u, v) * u' + f_v(u, v) * v', plus the derivative of the
+                // definition itself when it reads `var` as a global
+                let mut terms: Vec<Expr> = args
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, arg)| arg.is_variant_on(var))
+                    .map(|(i, arg)| {
+                        Expr::Product(vec![
+                            def.partial_derivative(args, i),
+                            arg.derivative(var),
+                        ])
+                    })
+                    .collect();
+                if def.is_variant_on_global(var) {
+                    terms.push(def.global_derivative(args, var));
+                }
+
+                Expr::Sum(terms)
+            }
         }
             // .simplify_trivial_single_layer()
     }
@@ -184,15 +465,16 @@ impl Expr {
     pub fn simplify_trivial(&self) -> Expr {
         match self {
             Expr::Sum(terms) => {
-                Expr::Sum(terms.into_iter().map(|v| v.simplify_trivial()).collect())
+                Expr::Sum(terms.iter().map(|v| v.simplify_trivial()).collect())
             }
             Expr::Product(terms) => {
-                Expr::Product(terms.into_iter().map(|v| v.simplify_trivial()).collect())
+                Expr::Product(terms.iter().map(|v| v.simplify_trivial()).collect())
             }
+            Expr::Pow(base, exponent) => base.simplify_trivial().pow(exponent.simplify_trivial()),
             Expr::Function(def, arg_terms) => Expr::Function(
                 def.clone(),
                 arg_terms
-                    .into_iter()
+                    .iter()
                     .map(|v| v.simplify_trivial())
                     .collect(),
             ),
@@ -236,7 +518,7 @@ impl Expr {
                 //     })
                 //     .collect();
 
-                if result.len() == 0 {
+                if result.is_empty() {
                     Expr::Const(Num::Zero)
                 } else if result.len() == 1 {
                     result[0].clone()
@@ -275,6 +557,9 @@ impl Expr {
                     })
                     .collect();
 
+                // Merges repeated factors into powers, e.g. x * x^a -> x^(1 + a).
+                let result = Self::merge_powers(result);
+
                 // Checks if there are any zeros.
                 let any_zeros = result
                     .iter()
@@ -289,13 +574,45 @@ impl Expr {
                     Expr::Const(Num::Zero)
                 } else if result.len() == 1 {
                     result[0].clone()
-                } else if result.len() == 0 {
-                    Expr::Const(Num::Zero)
+                } else if result.is_empty() {
+                    Expr::Const(Num::One)
                 } else {
                     Expr::Product(result)
                 }
             }
 
+            Expr::Pow(base, exponent) => match (base.as_ref(), exponent.as_ref()) {
+                // x^0 = 1, 1^x = 1
+                (_, Expr::Const(n)) if n.is_zero() => Expr::Const(Num::One),
+                (Expr::Const(n), _) if n.is_one() => Expr::Const(Num::One),
+
+                // x^1 = x
+                (_, Expr::Const(n)) if n.is_one() => base.as_ref().clone(),
+
+                // (x^a)^n = x^(a*n), which only holds in general for integer n.
+                (Expr::Pow(inner_base, inner_exponent), Expr::Const(n))
+                    if n.as_integer().is_some() =>
+                {
+                    let folded = match inner_exponent.as_ref() {
+                        Expr::Const(m) => m
+                            .as_integer()
+                            .and_then(|m| m.checked_mul(n.as_integer().unwrap())),
+                        _ => None,
+                    };
+                    let exponent = match folded {
+                        Some(product) => Expr::Const(Num::from(product)),
+                        None => Expr::Product(vec![
+                            inner_exponent.as_ref().clone(),
+                            exponent.as_ref().clone(),
+                        ]),
+                    };
+
+                    inner_base.as_ref().clone().pow(exponent).simplify_trivial_single_layer()
+                }
+
+                _ => self.clone(),
+            },
+
             Expr::Var(expr_var) => Expr::Var(expr_var.clone()),
             
             Expr::Const(num) => {
@@ -317,13 +634,9 @@ impl Expr {
                     }
                     let mut terms_to_match = terms_other.clone();
                     for term in terms_self {
-                        let mut found = None;
-                        for i in 0..terms_to_match.len() {
-                            if Expr::exact_match(term, &terms_to_match[i]) {
-                                found = Some(i);
-                                break;
-                            }
-                        }
+                        let found = terms_to_match
+                            .iter()
+                            .position(|other_term| Expr::exact_match(term, other_term));
                         if let Some(i) = found {
                             terms_to_match.drain(i..i + 1);
                         } else {
@@ -342,13 +655,9 @@ impl Expr {
                     }
                     let mut terms_to_match = terms_other.clone();
                     for term in terms_self {
-                        let mut found = None;
-                        for i in 0..terms_to_match.len() {
-                            if Expr::exact_match(term, &terms_to_match[i]) {
-                                found = Some(i);
-                                break;
-                            }
-                        }
+                        let found = terms_to_match
+                            .iter()
+                            .position(|other_term| Expr::exact_match(term, other_term));
                         if let Some(i) = found {
                             terms_to_match.drain(i..i + 1);
                         } else {
@@ -360,6 +669,14 @@ impl Expr {
                 _ => false,
             }
 
+            Expr::Pow(base_self, exponent_self) => match other {
+                Expr::Pow(base_other, exponent_other) => {
+                    Expr::exact_match(base_self, base_other)
+                        && Expr::exact_match(exponent_self, exponent_other)
+                }
+                _ => false,
+            }
+
             Expr::Var(var_self) => match other {
                 Expr::Var(var_other) => var_self == var_other,
                 _ => false,
@@ -370,11 +687,31 @@ impl Expr {
                 _ => false,
             }
 
-            Expr::Function(_, _) => todo!(),
+            Expr::Function(def_self, args_self) => match other {
+                Expr::Function(def_other, args_other) => {
+                    def_self.name() == def_other.name()
+                        && args_self.len() == args_other.len()
+                        && args_self
+                            .iter()
+                            .zip(args_other)
+                            .all(|(a, b)| Expr::exact_match(a, b))
+                }
+                _ => false,
+            }
         }
     }
 }
 
+/// Raises `base` to `exponent`. Integer powers are computed by repeated multiplication, which is
+/// exact for small integers and avoids the branch cut of `powc`.
+pub(crate) fn pow(base: Complex64, exponent: Complex64) -> Complex64 {
+    if exponent.im == 0.0 && exponent.re.fract() == 0.0 && exponent.re.abs() <= i32::MAX as f64 {
+        base.powi(exponent.re as i32)
+    } else {
+        base.powc(exponent)
+    }
+}
+
 impl Add for Expr {
     type Output = Expr;
 
@@ -388,7 +725,7 @@ impl Add<Vec<Expr>> for Expr {
 
     fn add(self, rhs: Vec<Expr>) -> Self::Output {
         let mut args = vec![self];
-        args.extend(rhs.into_iter());
+        args.extend(rhs);
 
         Expr::Sum(args)
     }
@@ -432,37 +769,24 @@ impl Mul<Vec<Expr>> for Expr {
 
     fn mul(self, rhs: Vec<Expr>) -> Self::Output {
         let mut args = vec![self];
-        args.extend(rhs.into_iter());
+        args.extend(rhs);
 
         Self::Product(args)
     }
 }
 
-impl fmt::Display for Expr {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Expr::Sum(terms) => {
-                let mut k: Vec<String> = vec![];
-                for term in terms {
-                    k.push(format!("{}", term));
-                }
-                write!(f, "({})", k.join(" + "))
-            },
-
-            Expr::Product(terms) => {
-                let mut k: Vec<String> = vec![];
-                for term in terms {
-                    k.push(format!("{}", term));
-                }
-                write!(f, "({})", k.join(" * "))
-            },
-
-            Expr::Var(var) => write!(f, "\u{001b}[95m{}", var.get_name()), // Remove colors before production
+impl Div for Expr {
+    type Output = Expr;
 
-            Expr::Const(num) => write!(f, "\u{001b}[94m{}", num),
+    fn div(self, rhs: Self) -> Self::Output {
+        Self::Product(vec![self, rhs.pow(Self::Const(Num::from(-1)))])
+    }
+}
 
-            Expr::Function(_, _) => todo!(),
-        }?;
-        write!(f, "\u{001b}[0m")
+impl fmt::Display for Expr {
+    /// Displays the expression as plain ASCII text, see [`Expr::display_with`] for other styles
+    /// and [`Expr::colored`] for syntax highlighting.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display_with(PrintOptions::default()))
     }
 }
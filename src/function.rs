@@ -1,16 +1,173 @@
 use num_complex::{Complex64};
-use std::collections::HashMap;
-use std::fmt::Debug;
+use std::collections::HashSet;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::sync::Arc;
 
+use crate::dual::{Dual, HyperDual};
 use crate::expr::{EvalError, EvalResult, Expr};
+use crate::interval::Interval;
+use crate::num::Num;
 use crate::var::{Var, VarMap};
 
-pub trait FuncDef<T = Complex64> : Debug {
-    fn eval(&self, args: Vec<T>, global_vars: &VarMap<Complex64>) -> EvalResult;
+pub trait FuncDef<T = Complex64> : Debug + Send + Sync {
+    fn eval(&self, args: Vec<T>, global_vars: &VarMap<T>) -> Result<T, EvalError>;
     fn is_variant_on_global(&self, global_vars: &Var) -> bool;
+    fn name(&self) -> String;
+
+    /// The number of arguments the function accepts, any number by default.
+    fn arity(&self) -> Arity {
+        Arity::AtLeast(0)
+    }
+
+    /// Returns the partial derivative of the function with respect to its `index`-th argument,
+    /// applied to `args`. By default it is unknown, so the derivative evaluates to
+    /// [`EvalError::Undefined`].
+    fn partial_derivative(&self, _args: &[Expr], _index: usize) -> Expr {
+        Expr::Const(Num::Undefined)
+    }
+
+    /// Returns the partial derivative of the function with respect to a global variable its
+    /// definition reads (see [`FuncDef::is_variant_on_global`]), applied to `args`.
+    fn global_derivative(&self, _args: &[Expr], _var: &Var) -> Expr {
+        Expr::Const(Num::Zero)
+    }
+
+    /// Returns the function with the free variables of its definition replaced, or `None` if
+    /// there is nothing to replace.
+    fn substitute(&self, _replacements: &VarMap<Expr>) -> Option<Arc<dyn FuncDef<T>>> {
+        None
+    }
+
+    /// The variables the definition depends on besides its arguments.
+    fn free_variables(&self) -> Vec<Var> {
+        vec![]
+    }
+
+    /// The real-valued version of the function, used by [`Expr::eval_real`]. Without one, the
+    /// function is evaluated over the complex numbers and must give a real result.
+    fn as_real(&self) -> Option<&dyn FuncDef<f64>> {
+        None
+    }
+
+    /// The interval version of the function, used by [`Expr::eval_interval`].
+    fn as_interval(&self) -> Option<&dyn FuncDef<Interval>> {
+        None
+    }
+
+    /// The dual number version of the function, used by [`Expr::eval_dual`]. Without one, the
+    /// function is differentiated with [`FuncDef::partial_derivative`].
+    fn as_dual(&self) -> Option<&dyn FuncDef<Dual>> {
+        None
+    }
+
+    /// The hyper-dual number version of the function, used by [`Expr::eval_hyper_dual`].
+    fn as_hyper_dual(&self) -> Option<&dyn FuncDef<HyperDual>> {
+        None
+    }
+}
+
+/// Implements every method of [`FuncDef`] but `eval` and `substitute` for another kind of number
+/// by forwarding to the `Complex64` implementation, which describes the function.
+macro_rules! forward_to_complex {
+    () => {
+        fn is_variant_on_global(&self, var: &$crate::var::Var) -> bool {
+            <Self as $crate::function::FuncDef>::is_variant_on_global(self, var)
+        }
+
+        fn name(&self) -> String {
+            <Self as $crate::function::FuncDef>::name(self)
+        }
+
+        fn arity(&self) -> $crate::function::Arity {
+            <Self as $crate::function::FuncDef>::arity(self)
+        }
+
+        fn partial_derivative(
+            &self,
+            args: &[$crate::expr::Expr],
+            index: usize,
+        ) -> $crate::expr::Expr {
+            <Self as $crate::function::FuncDef>::partial_derivative(self, args, index)
+        }
+
+        fn global_derivative(
+            &self,
+            args: &[$crate::expr::Expr],
+            var: &$crate::var::Var,
+        ) -> $crate::expr::Expr {
+            <Self as $crate::function::FuncDef>::global_derivative(self, args, var)
+        }
+
+        fn free_variables(&self) -> Vec<$crate::var::Var> {
+            <Self as $crate::function::FuncDef>::free_variables(self)
+        }
+
+        fn as_real(&self) -> Option<&dyn $crate::function::FuncDef<f64>> {
+            <Self as $crate::function::FuncDef>::as_real(self)
+        }
+
+        fn as_interval(
+            &self,
+        ) -> Option<&dyn $crate::function::FuncDef<$crate::interval::Interval>> {
+            <Self as $crate::function::FuncDef>::as_interval(self)
+        }
+
+        fn as_dual(&self) -> Option<&dyn $crate::function::FuncDef<$crate::dual::Dual>> {
+            <Self as $crate::function::FuncDef>::as_dual(self)
+        }
+
+        fn as_hyper_dual(
+            &self,
+        ) -> Option<&dyn $crate::function::FuncDef<$crate::dual::HyperDual>> {
+            <Self as $crate::function::FuncDef>::as_hyper_dual(self)
+        }
+    };
+}
+
+pub(crate) use forward_to_complex;
+
+/// The number of arguments a function accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exactly(usize),
+    /// A variadic function taking at least this many arguments.
+    AtLeast(usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Arity::Exactly(n) => count == n,
+            Arity::AtLeast(n) => count >= n,
+        }
+    }
+
+    /// Checks that `def` can be called with `count` arguments.
+    pub fn check<T>(def: &(impl FuncDef<T> + ?Sized), count: usize) -> Result<(), EvalError> {
+        let expected = def.arity();
+
+        if expected.accepts(count) {
+            Ok(())
+        } else {
+            Err(EvalError::FnArgCountMismatch {
+                name: def.name(),
+                expected,
+                found: count,
+            })
+        }
+    }
 }
 
-#[derive(Debug)]
+impl Display for Arity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Arity::Exactly(n) => write!(f, "{n}"),
+            Arity::AtLeast(n) => write!(f, "at least {n}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum Function {
     F(String, Expr, Vec<Var>),
     Abs,
@@ -29,29 +186,71 @@ pub enum Function {
     Arcsinh,
     Arccosh,
     Arctanh,
+    /// `atan2(y, x)`, the angle of the point `(x, y)`.
+    Atan2,
+    /// `log(base, x)`
+    Log,
+    Min,
+    Max,
+    /// `hypot(x, y) = sqrt(x^2 + y^2)`
+    Hypot,
+    /// `root(n, x)`, the `n`-th root of `x`.
+    Root,
+}
+
+impl Function {
+    /// Applies the function to `args`, producing an expression.
+    pub fn apply(self, args: Vec<Expr>) -> Expr {
+        Expr::Function(Arc::new(self), args)
+    }
+
+    /// Looks up a built-in function by the name it is written with, e.g. `"sin"` or `"arctan"`.
+    pub fn from_name(name: &str) -> Option<Function> {
+        Some(match name {
+            "abs" => Function::Abs,
+            "sgn" | "sign" => Function::Sgn,
+            "exp" => Function::Exp,
+            "ln" => Function::Ln,
+            "sin" => Function::Sin,
+            "cos" => Function::Cos,
+            "tan" => Function::Tan,
+            "arcsin" | "asin" => Function::Arcsin,
+            "arccos" | "acos" => Function::Arccos,
+            "arctan" | "atan" => Function::Arctan,
+            "sinh" => Function::Sinh,
+            "cosh" => Function::Cosh,
+            "tanh" => Function::Tanh,
+            "arcsinh" | "asinh" => Function::Arcsinh,
+            "arccosh" | "acosh" => Function::Arccosh,
+            "arctanh" | "atanh" => Function::Arctanh,
+            "atan2" => Function::Atan2,
+            "log" => Function::Log,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            "hypot" => Function::Hypot,
+            "root" => Function::Root,
+            _ => return None,
+        })
+    }
 }
 
 impl FuncDef<Complex64> for Function {
-    fn eval(&self, args: Vec<Complex64>, _global_vars: &VarMap<Complex64>) -> EvalResult {
-        if args.len()
-            != match self {
-                _ => 1,
-            }
-        {
-            return Err(EvalError::FnArgCountMismatch {});
-        }
-        
-        Ok(match self {
-            Function::F(name, body, vars) => {
-                let mut map: VarMap<Complex64> = HashMap::new();
-            
-                for (i, item) in vars.into_iter().enumerate() {
+    fn eval(&self, args: Vec<Complex64>, global_vars: &VarMap<Complex64>) -> EvalResult {
+        Arity::check::<Complex64>(self, args.len())?;
+
+        let value = match self {
+            Function::F(_, body, vars) => {
+                // Free variables of the body take their global values, parameters shadow them.
+                let mut map: VarMap<Complex64> = global_vars.clone();
+
+                for (i, item) in vars.iter().enumerate() {
                     map.insert(item, args[i]);
                 }
 
-                body.eval(&map)?
+                return body.eval(&map);
             },
             Function::Abs => Complex64::from(args[0].norm()),
+            Function::Sgn if args[0] == Complex64::new(0.0, 0.0) => args[0],
             Function::Sgn => args[0] / args[0].norm(),
             Function::Exp => args[0].exp(),
             Function::Ln => args[0].ln(),
@@ -67,10 +266,415 @@ impl FuncDef<Complex64> for Function {
             Function::Arcsinh => args[0].asinh(),
             Function::Arccosh => args[0].acosh(),
             Function::Arctanh => args[0].atanh(),
-        })
+            Function::Atan2 => {
+                let (y, x) = (args[0], args[1]);
+
+                if y.im == 0.0 && x.im == 0.0 {
+                    Complex64::from(y.re.atan2(x.re))
+                } else {
+                    // atan2(y, x) = -i ln((x + iy) / sqrt(x^2 + y^2))
+                    let i = Complex64::i();
+                    -i * ((x + i * y) / (x * x + y * y).sqrt()).ln()
+                }
+            }
+            Function::Log => {
+                let base = args[0];
+
+                if base == Complex64::new(0.0, 0.0) || base == Complex64::new(1.0, 0.0) {
+                    return Err(EvalError::DomainError {
+                        function: <Self as FuncDef>::name(self),
+                        arg: base,
+                    });
+                }
+
+                args[1].ln() / base.ln()
+            }
+            Function::Min | Function::Max => {
+                // Only real numbers are ordered.
+                if let Some(arg) = args.iter().find(|arg| arg.im != 0.0) {
+                    return Err(EvalError::DomainError {
+                        function: <Self as FuncDef>::name(self),
+                        arg: *arg,
+                    });
+                }
+
+                let values = args.iter().map(|arg| arg.re);
+                Complex64::from(match self {
+                    Function::Min => values.fold(f64::INFINITY, f64::min),
+                    _ => values.fold(f64::NEG_INFINITY, f64::max),
+                })
+            }
+            Function::Hypot => {
+                let (x, y) = (args[0], args[1]);
+
+                if x.im == 0.0 && y.im == 0.0 {
+                    Complex64::from(x.re.hypot(y.re))
+                } else {
+                    (x * x + y * y).sqrt()
+                }
+            }
+            Function::Root => {
+                let (n, x) = (args[0], args[1]);
+
+                if n == Complex64::new(0.0, 0.0) {
+                    return Err(EvalError::DomainError {
+                        function: <Self as FuncDef>::name(self),
+                        arg: n,
+                    });
+                }
+
+                // Odd roots of negative reals are taken to be real, e.g. root(3, -8) = -2.
+                let odd = n.im == 0.0 && n.re.fract() == 0.0 && n.re.rem_euclid(2.0) == 1.0;
+                if odd && x.im == 0.0 && x.re < 0.0 {
+                    Complex64::from(-(-x.re).powf(1.0 / n.re))
+                } else {
+                    x.powc(n.inv())
+                }
+            }
+        };
+
+        // A finite argument only gives a non-finite value at a pole or branch point,
+        // e.g. ln(0) or arctanh(1). The argument reported is the one the function is taken of.
+        let arg = args[args.len() - 1];
+        if !value.is_finite() && args.iter().all(|arg| arg.is_finite()) {
+            return Err(EvalError::DomainError {
+                function: <Self as FuncDef>::name(self),
+                arg,
+            });
+        }
+
+        Ok(value)
+    }
+
+    fn is_variant_on_global(&self, var: &Var) -> bool {
+        match self {
+            Function::F(_, body, params) => !params.contains(var) && body.is_variant_on(var),
+            _ => false,
+        }
     }
 
-    fn is_variant_on_global(&self, _global_vars: &Var) -> bool {
-        false
+    fn name(&self) -> String {
+        match self {
+            Function::F(name, ..) => name,
+            Function::Abs => "abs",
+            Function::Sgn => "sgn",
+            Function::Exp => "exp",
+            Function::Ln => "ln",
+            Function::Sin => "sin",
+            Function::Cos => "cos",
+            Function::Tan => "tan",
+            Function::Arcsin => "arcsin",
+            Function::Arccos => "arccos",
+            Function::Arctan => "arctan",
+            Function::Sinh => "sinh",
+            Function::Cosh => "cosh",
+            Function::Tanh => "tanh",
+            Function::Arcsinh => "arcsinh",
+            Function::Arccosh => "arccosh",
+            Function::Arctanh => "arctanh",
+            Function::Atan2 => "atan2",
+            Function::Log => "log",
+            Function::Min => "min",
+            Function::Max => "max",
+            Function::Hypot => "hypot",
+            Function::Root => "root",
+        }
+        .to_string()
+    }
+
+    fn arity(&self) -> Arity {
+        match self {
+            Function::F(_, _, params) => Arity::Exactly(params.len()),
+            Function::Atan2 | Function::Log | Function::Hypot | Function::Root => {
+                Arity::Exactly(2)
+            }
+            Function::Min | Function::Max => Arity::AtLeast(1),
+            _ => Arity::Exactly(1),
+        }
+    }
+
+    fn partial_derivative(&self, args: &[Expr], index: usize) -> Expr {
+        let neg = |expr: Expr| Expr::Product(vec![Expr::Const(Num::from(-1)), expr]);
+        let pow = |expr: Expr, num: i32, den: u32| expr.pow(Expr::Const(Num::rational(num, den)));
+        let square = |expr: Expr| pow(expr, 2, 1);
+        let one = || Expr::Const(Num::One);
+
+        if let Function::F(name, body, params) = self {
+            let param = &params[index];
+            let derivative = Function::F(
+                format!("d{name}/d{}", param.get_name()),
+                body.derivative(param),
+                params.clone(),
+            );
+
+            return derivative.apply(args.to_vec());
+        }
+
+        match self {
+            Function::Atan2 => {
+                let (y, x) = (args[0].clone(), args[1].clone());
+                let norm_squared = square(x.clone()) + square(y.clone());
+
+                return match index {
+                    // d/dy atan2(y, x) = x / (x^2 + y^2)
+                    0 => x * pow(norm_squared, -1, 1),
+                    // d/dx atan2(y, x) = -y / (x^2 + y^2)
+                    _ => neg(y * pow(norm_squared, -1, 1)),
+                };
+            }
+            Function::Hypot => {
+                // d/dx hypot(x, y) = x / hypot(x, y)
+                let hypot = Function::Hypot.apply(args.to_vec());
+                return args[index].clone() * pow(hypot, -1, 1);
+            }
+            Function::Log => {
+                let (base, x) = (args[0].clone(), args[1].clone());
+                let ln = |expr: Expr| Function::Ln.apply(vec![expr]);
+
+                return match index {
+                    // d/db log(b, x) = -ln(x) / (b ln(b)^2)
+                    0 => neg(ln(x) * pow(base.clone(), -1, 1) * pow(ln(base), -2, 1)),
+                    // d/dx log(b, x) = 1 / (x ln(b))
+                    _ => pow(x * ln(base), -1, 1),
+                };
+            }
+            Function::Root => {
+                let (n, x) = (args[0].clone(), args[1].clone());
+                let root = Function::Root.apply(args.to_vec());
+
+                return match index {
+                    // d/dn root(n, x) = -root(n, x) ln(x) / n^2
+                    0 => neg(root * Function::Ln.apply(vec![x]) * pow(n, -2, 1)),
+                    // d/dx root(n, x) = root(n, x) / (n x)
+                    _ => root * pow(n * x, -1, 1),
+                };
+            }
+            Function::Min | Function::Max => {
+                // The derivative with respect to an argument is 1 where it is the only extremum.
+                // Arguments tied for the extremum share it equally, so that it is 1/n for each of
+                // n tied arguments.
+                let sgn = |expr: Expr| Function::Sgn.apply(vec![expr]);
+                let arg = &args[index];
+                let (mut is_extremum, mut tied) = (vec![], vec![one()]);
+                for (_, other) in args.iter().enumerate().filter(|(i, _)| *i != index) {
+                    let difference = match self {
+                        Function::Min => other.clone() - arg.clone(),
+                        _ => arg.clone() - other.clone(),
+                    };
+                    // sgn(sgn(d) + 1) is 1 for d >= 0 and 0 otherwise, and 1 - sgn(d)^2 is 1
+                    // for d = 0 and 0 otherwise.
+                    is_extremum.push(sgn(sgn(difference.clone()) + one()));
+                    tied.push(one() - square(sgn(difference)));
+                }
+
+                return match is_extremum.len() {
+                    0 => one(),
+                    _ => Expr::Product(is_extremum) * pow(Expr::Sum(tied), -1, 1),
+                };
+            }
+            _ => {}
+        }
+
+        let u = args[0].clone();
+
+        match self {
+            Function::F(..) => unreachable!(),
+            Function::Abs => Function::Sgn.apply(vec![u]),
+            Function::Sgn => Expr::Const(Num::Zero),
+            Function::Exp => Function::Exp.apply(vec![u]),
+            Function::Ln => pow(u, -1, 1),
+            Function::Sin => Function::Cos.apply(vec![u]),
+            Function::Cos => neg(Function::Sin.apply(vec![u])),
+            Function::Tan => pow(Function::Cos.apply(vec![u]), -2, 1),
+            Function::Arcsin => pow(one() - square(u), -1, 2),
+            Function::Arccos => neg(pow(one() - square(u), -1, 2)),
+            Function::Arctan => pow(one() + square(u), -1, 1),
+            Function::Sinh => Function::Cosh.apply(vec![u]),
+            Function::Cosh => Function::Sinh.apply(vec![u]),
+            Function::Tanh => pow(Function::Cosh.apply(vec![u]), -2, 1),
+            Function::Arcsinh => pow(square(u) + one(), -1, 2),
+            // Written as a product of two roots so that it also holds off the real line.
+            Function::Arccosh => Expr::Product(vec![
+                pow(u.clone() - one(), -1, 2),
+                pow(u + one(), -1, 2),
+            ]),
+            Function::Arctanh => pow(one() - square(u), -1, 1),
+            Function::Atan2
+            | Function::Log
+            | Function::Min
+            | Function::Max
+            | Function::Hypot
+            | Function::Root => unreachable!(),
+        }
+    }
+
+    fn global_derivative(&self, args: &[Expr], var: &Var) -> Expr {
+        match self {
+            Function::F(name, body, params) if !params.contains(var) => Function::F(
+                format!("d{name}/d{}", var.get_name()),
+                body.derivative(var),
+                params.clone(),
+            )
+            .apply(args.to_vec()),
+            _ => Expr::Const(Num::Zero),
+        }
+    }
+
+    fn substitute(&self, replacements: &VarMap<Expr>) -> Option<Arc<dyn FuncDef>> {
+        let Function::F(name, body, params) = self else {
+            return None;
+        };
+
+        // Parameters shadow the variables being replaced.
+        let mut inner: VarMap<Expr> = replacements
+            .iter()
+            .filter(|(var, _)| !params.contains(var))
+            .map(|(var, replacement)| (*var, replacement.clone()))
+            .collect();
+        if inner.is_empty() {
+            return None;
+        }
+
+        // Parameters that appear in a replacement are renamed so that they don't capture it.
+        let captured: HashSet<Var> = inner.values().flat_map(Expr::variables).collect();
+        let mut taken = body.variables();
+        taken.extend(captured.iter().cloned());
+        taken.extend(params.iter().cloned());
+
+        let renamed: Vec<Var> = params
+            .iter()
+            .map(|param| {
+                if !captured.contains(param) {
+                    return param.clone();
+                }
+
+                let mut fresh = format!("{}'", param.get_name());
+                while taken.contains(&Var::new_owned(&fresh)) {
+                    fresh.push('\'');
+                }
+                let fresh = Var::new_owned(&fresh);
+                taken.insert(fresh.clone());
+                fresh
+            })
+            .collect();
+
+        for (param, new) in params.iter().zip(&renamed) {
+            if param != new {
+                inner.insert(param, Expr::Var(Arc::new(new.clone())));
+            }
+        }
+
+        Some(Arc::new(Function::F(name.clone(), body.substitute_all(&inner), renamed)))
+    }
+
+    fn free_variables(&self) -> Vec<Var> {
+        match self {
+            Function::F(_, body, params) => body
+                .variables()
+                .into_iter()
+                .filter(|var| !params.contains(var))
+                .collect(),
+            _ => vec![],
+        }
     }
+
+    fn as_real(&self) -> Option<&dyn FuncDef<f64>> {
+        Some(self)
+    }
+
+    fn as_interval(&self) -> Option<&dyn FuncDef<Interval>> {
+        Some(self)
+    }
+
+    fn as_dual(&self) -> Option<&dyn FuncDef<Dual>> {
+        Some(self)
+    }
+
+    fn as_hyper_dual(&self) -> Option<&dyn FuncDef<HyperDual>> {
+        Some(self)
+    }
+}
+
+/// Evaluation over the real numbers, which fails where the complex version would leave the real
+/// line, e.g. `ln(-1)` or `arcsin(2)`.
+impl FuncDef<f64> for Function {
+    fn eval(&self, args: Vec<f64>, global_vars: &VarMap<f64>) -> Result<f64, EvalError> {
+        Arity::check::<f64>(self, args.len())?;
+
+        let domain_error = |arg: f64| EvalError::DomainError {
+            function: <Self as FuncDef<f64>>::name(self),
+            arg: Complex64::from(arg),
+        };
+
+        let value = match self {
+            Function::F(_, body, vars) => {
+                let mut map: VarMap<f64> = global_vars.clone();
+
+                for (i, item) in vars.iter().enumerate() {
+                    map.insert(item, args[i]);
+                }
+
+                return body.eval_real(&map);
+            }
+            Function::Abs => args[0].abs(),
+            Function::Sgn if args[0] == 0.0 => 0.0,
+            Function::Sgn => args[0].signum(),
+            Function::Exp => args[0].exp(),
+            Function::Ln => args[0].ln(),
+            Function::Sin => args[0].sin(),
+            Function::Cos => args[0].cos(),
+            Function::Tan => args[0].tan(),
+            Function::Arcsin => args[0].asin(),
+            Function::Arccos => args[0].acos(),
+            Function::Arctan => args[0].atan(),
+            Function::Sinh => args[0].sinh(),
+            Function::Cosh => args[0].cosh(),
+            Function::Tanh => args[0].tanh(),
+            Function::Arcsinh => args[0].asinh(),
+            Function::Arccosh => args[0].acosh(),
+            Function::Arctanh => args[0].atanh(),
+            Function::Atan2 => args[0].atan2(args[1]),
+            Function::Log => {
+                if args[0] == 0.0 || args[0] == 1.0 {
+                    return Err(domain_error(args[0]));
+                }
+
+                args[1].ln() / args[0].ln()
+            }
+            Function::Min => args.iter().copied().fold(f64::INFINITY, f64::min),
+            Function::Max => args.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Function::Hypot => args[0].hypot(args[1]),
+            Function::Root => {
+                let (n, x) = (args[0], args[1]);
+
+                if n == 0.0 {
+                    return Err(domain_error(n));
+                }
+
+                // Odd roots of negative numbers are real, e.g. root(3, -8) = -2.
+                if x < 0.0 && n.fract() == 0.0 && n.rem_euclid(2.0) == 1.0 {
+                    -(-x).powf(n.recip())
+                } else {
+                    x.powf(n.recip())
+                }
+            }
+        };
+
+        // Outside of the domain the functions above give NaN. Infinities are only errors at
+        // poles, as they may also be values too large for an `f64`, e.g. exp(1000).
+        let x = args[args.len() - 1];
+        let pole = match self {
+            Function::Ln | Function::Log => x == 0.0,
+            Function::Arctanh => x.abs() == 1.0,
+            Function::Root => x == 0.0 && args[0] < 0.0,
+            _ => false,
+        };
+        if pole || (value.is_nan() && !args.iter().any(|arg| arg.is_nan())) {
+            return Err(domain_error(x));
+        }
+
+        Ok(value)
+    }
+
+    forward_to_complex!();
 }
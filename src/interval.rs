@@ -0,0 +1,627 @@
+//! Interval arithmetic, for enclosing the values of an expression over a box of inputs.
+
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+use std::fmt::{self, Display, Formatter};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use num_complex::Complex64;
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive};
+
+use crate::{
+    expr::{EvalError, Expr},
+    function::{forward_to_complex, Arity, FuncDef, Function},
+    num::Num,
+    var::VarMap,
+};
+
+/// How many units in the last place the results of library functions like `sin` may be off by.
+/// Basic arithmetic and square roots are correctly rounded, so they are only widened by one.
+const LIBM_ULPS: u32 = 2;
+
+/// Above this magnitude periodic functions are taken to cover their whole range, as the
+/// position within the period is lost to rounding.
+const MAX_PERIODIC_ARG: f64 = 1e15;
+
+/// A closed interval of real numbers `[lo, hi]`, possibly unbounded.
+///
+/// Operations round outwards, so that the result of an operation on intervals contains the
+/// results of the operation on all numbers in them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    lo: f64,
+    hi: f64,
+}
+
+impl Interval {
+    /// Creates the interval `[lo, hi]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lo > hi` or either bound is NaN.
+    pub fn new(lo: f64, hi: f64) -> Self {
+        assert!(lo <= hi, "invalid interval [{lo}, {hi}]");
+
+        // Keep the bounds finite on the inner side, so that sums never see `∞ - ∞`.
+        Self {
+            lo: lo.min(f64::MAX),
+            hi: hi.max(f64::MIN),
+        }
+    }
+
+    /// The interval containing only `value`.
+    pub fn point(value: f64) -> Self {
+        Self::new(value, value)
+    }
+
+    /// The interval of all real numbers.
+    pub fn entire() -> Self {
+        Self::new(f64::NEG_INFINITY, f64::INFINITY)
+    }
+
+    pub fn lo(&self) -> f64 {
+        self.lo
+    }
+
+    pub fn hi(&self) -> f64 {
+        self.hi
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        self.lo <= value && value <= self.hi
+    }
+
+    pub fn width(&self) -> f64 {
+        self.hi - self.lo
+    }
+
+    /// The middle of the interval. For an unbounded interval this is its finite bound, or 0 if
+    /// it is entire.
+    pub fn midpoint(&self) -> f64 {
+        match (self.lo.is_finite(), self.hi.is_finite()) {
+            (true, true) => self.lo / 2.0 + self.hi / 2.0,
+            (true, false) => self.lo,
+            (false, true) => self.hi,
+            (false, false) => 0.0,
+        }
+    }
+
+    /// The smallest interval containing both intervals.
+    pub fn hull(self, other: Interval) -> Interval {
+        Interval::new(self.lo.min(other.lo), self.hi.max(other.hi))
+    }
+
+    /// The numbers in both intervals, if there are any.
+    pub fn intersect(self, other: Interval) -> Option<Interval> {
+        let (lo, hi) = (self.lo.max(other.lo), self.hi.min(other.hi));
+        (lo <= hi).then(|| Interval::new(lo, hi))
+    }
+
+    pub fn abs(self) -> Interval {
+        if self.lo >= 0.0 {
+            self
+        } else if self.hi <= 0.0 {
+            -self
+        } else {
+            Interval::new(0.0, self.hi.max(-self.lo))
+        }
+    }
+
+    /// Raises the interval to an integer power.
+    pub fn powi(self, n: i32) -> Interval {
+        if n < 0 {
+            Interval::point(1.0) / self.powu(n.unsigned_abs())
+        } else {
+            self.powu(n.unsigned_abs())
+        }
+    }
+
+    /// Raises the interval to a natural power. This takes a `u32` so that the magnitude of any
+    /// `i32` exponent, including `i32::MIN`, fits.
+    fn powu(self, n: u32) -> Interval {
+        if n == 0 {
+            return Interval::point(1.0);
+        }
+
+        // `powi` multiplies repeatedly, so its error grows with the exponent.
+        let ulps = n.saturating_add(1);
+        let pow = |x: f64| match i32::try_from(n) {
+            Ok(n) => x.powi(n),
+            Err(_) => x.powf(f64::from(n)),
+        };
+        if n.is_multiple_of(2) {
+            let base = self.abs();
+            outward(pow(base.lo), pow(base.hi), ulps).max_lo(0.0)
+        } else {
+            outward(pow(self.lo), pow(self.hi), ulps)
+        }
+    }
+
+    /// Raises the interval to an interval power, as `exp(exponent ln(self))`. Negative numbers
+    /// are left out of the base, so this is `None` if the base is entirely negative.
+    pub fn pow(self, exponent: Interval) -> Option<Interval> {
+        Some((exponent * self.ln()?).exp())
+    }
+
+    /// The real `n`-th root. For even `n` negative numbers are left out, so this is `None` if
+    /// the interval is entirely negative.
+    pub fn root(self, n: u32) -> Option<Interval> {
+        // The exponent 1/n is not exact, so general roots go through an enclosure of it.
+        let inverse = Interval::point(1.0) / Interval::point(f64::from(n));
+        let positive_root = |x: f64| (Interval::point(x.abs()).ln().unwrap() * inverse).exp();
+
+        match n {
+            0 => None,
+            1 => Some(self),
+            2 => self.sqrt(),
+            3 => Some(outward(self.lo.cbrt(), self.hi.cbrt(), LIBM_ULPS)),
+            // Odd roots are increasing and odd, so negative bounds mirror positive ones.
+            _ if n % 2 == 1 => {
+                let lo = if self.lo >= 0.0 {
+                    positive_root(self.lo).lo
+                } else {
+                    -positive_root(self.lo).hi
+                };
+                let hi = if self.hi >= 0.0 {
+                    positive_root(self.hi).hi
+                } else {
+                    -positive_root(self.hi).lo
+                };
+                Some(Interval::new(lo, hi))
+            }
+            _ => {
+                let x = self.intersect(Interval::new(0.0, f64::INFINITY))?;
+                Some(x.pow(inverse)?.max_lo(0.0))
+            }
+        }
+    }
+
+    pub fn sqrt(self) -> Option<Interval> {
+        let x = self.intersect(Interval::new(0.0, f64::INFINITY))?;
+        Some(outward(x.lo.sqrt(), x.hi.sqrt(), 1).max_lo(0.0))
+    }
+
+    pub fn exp(self) -> Interval {
+        outward(self.lo.exp(), self.hi.exp(), LIBM_ULPS).max_lo(0.0)
+    }
+
+    /// The natural logarithm of the positive part of the interval, or `None` if it has none.
+    pub fn ln(self) -> Option<Interval> {
+        let x = self.intersect(Interval::new(0.0, f64::INFINITY))?;
+        Some(outward(x.lo.ln(), x.hi.ln(), LIBM_ULPS))
+    }
+
+    pub fn sin(self) -> Interval {
+        // The maxima are at π/2 + 2kπ and the minima at -π/2 + 2kπ.
+        self.periodic(f64::sin, FRAC_PI_2, -FRAC_PI_2)
+    }
+
+    pub fn cos(self) -> Interval {
+        // The maxima are at 2kπ and the minima at π + 2kπ.
+        self.periodic(f64::cos, 0.0, PI)
+    }
+
+    /// Encloses a function with period 2π and range [-1, 1], given where it is extremal.
+    fn periodic(self, f: fn(f64) -> f64, max_at: f64, min_at: f64) -> Interval {
+        let unit = Interval::new(-1.0, 1.0);
+
+        if self.hi - self.lo >= TAU || self.lo.abs().max(self.hi.abs()) > MAX_PERIODIC_ARG {
+            return unit;
+        }
+
+        let (a, b) = (f(self.lo), f(self.hi));
+        let mut result = outward(a.min(b), a.max(b), LIBM_ULPS);
+
+        if self.may_contain(max_at, TAU) {
+            result.hi = 1.0;
+        }
+        if self.may_contain(min_at, TAU) {
+            result.lo = -1.0;
+        }
+
+        result.intersect(unit).unwrap_or(unit)
+    }
+
+    /// Whether the interval contains `offset + k period` for some integer `k`, erring on the
+    /// side of yes where rounding makes it unclear.
+    fn may_contain(self, offset: f64, period: f64) -> bool {
+        let lo = (self.lo - offset) / period;
+        let hi = (self.hi - offset) / period;
+        let margin = (lo.abs().max(hi.abs()) + 1.0) * 16.0 * f64::EPSILON;
+
+        (lo - margin).ceil() <= (hi + margin).floor()
+    }
+
+    /// The tangent, which is unbounded if the interval may contain a pole.
+    pub fn tan(self) -> Interval {
+        if self.hi - self.lo >= PI
+            || self.lo.abs().max(self.hi.abs()) > MAX_PERIODIC_ARG
+            || self.may_contain(FRAC_PI_2, PI)
+        {
+            return Interval::entire();
+        }
+
+        outward(self.lo.tan(), self.hi.tan(), LIBM_ULPS)
+    }
+
+    /// The arcsine of the part of the interval in [-1, 1], if there is one.
+    pub fn asin(self) -> Option<Interval> {
+        let x = self.intersect(Interval::new(-1.0, 1.0))?;
+        let range = Interval::new(-FRAC_PI_2.next_up(), FRAC_PI_2.next_up());
+        outward(x.lo.asin(), x.hi.asin(), LIBM_ULPS).intersect(range)
+    }
+
+    /// The arccosine of the part of the interval in [-1, 1], if there is one.
+    pub fn acos(self) -> Option<Interval> {
+        let x = self.intersect(Interval::new(-1.0, 1.0))?;
+        let range = Interval::new(0.0, PI.next_up());
+        outward(x.hi.acos(), x.lo.acos(), LIBM_ULPS).intersect(range)
+    }
+
+    pub fn atan(self) -> Interval {
+        outward(self.lo.atan(), self.hi.atan(), LIBM_ULPS)
+    }
+
+    pub fn sinh(self) -> Interval {
+        outward(self.lo.sinh(), self.hi.sinh(), LIBM_ULPS)
+    }
+
+    pub fn cosh(self) -> Interval {
+        let x = self.abs();
+        outward(x.lo.cosh(), x.hi.cosh(), LIBM_ULPS).max_lo(1.0)
+    }
+
+    pub fn tanh(self) -> Interval {
+        let unit = Interval::new(-1.0, 1.0);
+        let result = outward(self.lo.tanh(), self.hi.tanh(), LIBM_ULPS);
+        result.intersect(unit).unwrap_or(unit)
+    }
+
+    pub fn asinh(self) -> Interval {
+        outward(self.lo.asinh(), self.hi.asinh(), LIBM_ULPS)
+    }
+
+    /// The inverse hyperbolic cosine of the part of the interval above 1, if there is one.
+    pub fn acosh(self) -> Option<Interval> {
+        let x = self.intersect(Interval::new(1.0, f64::INFINITY))?;
+        Some(outward(x.lo.acosh(), x.hi.acosh(), LIBM_ULPS).max_lo(0.0))
+    }
+
+    /// The inverse hyperbolic tangent of the part of the interval in [-1, 1], if there is one.
+    pub fn atanh(self) -> Option<Interval> {
+        let x = self.intersect(Interval::new(-1.0, 1.0))?;
+        Some(outward(x.lo.atanh(), x.hi.atanh(), LIBM_ULPS))
+    }
+
+    /// The angle of the points `(x, y)` with `x` in `x` and `y` in `self`.
+    pub fn atan2(self, x: Interval) -> Interval {
+        let y = self;
+
+        // Across the negative x-axis the angle jumps from π to -π.
+        if x.lo <= 0.0 && y.contains(0.0) {
+            return Interval::new(-PI.next_up(), PI.next_up());
+        }
+
+        // Otherwise the box lies in a half-plane without the cut, where the extreme angles are
+        // taken at its corners.
+        let corners = [
+            y.lo.atan2(x.lo),
+            y.lo.atan2(x.hi),
+            y.hi.atan2(x.lo),
+            y.hi.atan2(x.hi),
+        ];
+        let lo = corners.into_iter().fold(f64::INFINITY, f64::min);
+        let hi = corners.into_iter().fold(f64::NEG_INFINITY, f64::max);
+        outward(lo, hi, LIBM_ULPS)
+    }
+
+    pub fn min(self, other: Interval) -> Interval {
+        Interval::new(self.lo.min(other.lo), self.hi.min(other.hi))
+    }
+
+    pub fn max(self, other: Interval) -> Interval {
+        Interval::new(self.lo.max(other.lo), self.hi.max(other.hi))
+    }
+
+    /// Raises the lower bound to `bound`, which the function being enclosed never goes below.
+    fn max_lo(self, bound: f64) -> Interval {
+        Interval::new(self.lo.max(bound), self.hi.max(bound))
+    }
+}
+
+/// Widens `[lo, hi]` by `ulps` units in the last place on each side.
+fn outward(lo: f64, hi: f64, ulps: u32) -> Interval {
+    let (mut lo, mut hi) = (lo, hi);
+
+    if ulps <= 4 {
+        for _ in 0..ulps {
+            lo = lo.next_down();
+            hi = hi.next_up();
+        }
+    } else {
+        // Overflowed endpoints are only moved by the rounding step, as `∞ - ∞` is NaN.
+        let step = f64::from(ulps) * f64::EPSILON;
+        let widen = |x: f64| if x.is_finite() { x.abs() * step } else { 0.0 };
+        lo = (lo - widen(lo)).next_down();
+        hi = (hi + widen(hi)).next_up();
+    }
+
+    Interval::new(lo, hi)
+}
+
+/// The product of two bounds, where zero times infinity is zero as the infinite bound only
+/// means the interval is unbounded.
+fn mul_bounds(a: f64, b: f64) -> f64 {
+    if a == 0.0 || b == 0.0 {
+        0.0
+    } else {
+        a * b
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}, {}]", self.lo, self.hi)
+    }
+}
+
+impl Neg for Interval {
+    type Output = Interval;
+
+    fn neg(self) -> Interval {
+        Interval::new(-self.hi, -self.lo)
+    }
+}
+
+impl Add for Interval {
+    type Output = Interval;
+
+    fn add(self, other: Interval) -> Interval {
+        outward(self.lo + other.lo, self.hi + other.hi, 1)
+    }
+}
+
+impl Sub for Interval {
+    type Output = Interval;
+
+    fn sub(self, other: Interval) -> Interval {
+        self + -other
+    }
+}
+
+impl Mul for Interval {
+    type Output = Interval;
+
+    fn mul(self, other: Interval) -> Interval {
+        let products = [
+            mul_bounds(self.lo, other.lo),
+            mul_bounds(self.lo, other.hi),
+            mul_bounds(self.hi, other.lo),
+            mul_bounds(self.hi, other.hi),
+        ];
+        let lo = products.into_iter().fold(f64::INFINITY, f64::min);
+        let hi = products.into_iter().fold(f64::NEG_INFINITY, f64::max);
+        outward(lo, hi, 1)
+    }
+}
+
+impl Div for Interval {
+    type Output = Interval;
+
+    /// Divides the intervals. A divisor containing zero gives the entire real line.
+    fn div(self, other: Interval) -> Interval {
+        if other.contains(0.0) {
+            return Interval::entire();
+        }
+
+        // Infinite bounds give NaN for `∞/∞`, which `min` and `max` skip.
+        let quotients = [
+            self.lo / other.lo,
+            self.lo / other.hi,
+            self.hi / other.lo,
+            self.hi / other.hi,
+        ];
+        let lo = quotients.into_iter().fold(f64::INFINITY, f64::min);
+        let hi = quotients.into_iter().fold(f64::NEG_INFINITY, f64::max);
+        outward(lo, hi, 1)
+    }
+}
+
+impl Expr {
+    /// Evaluates an expression over intervals, giving an interval that contains the value of
+    /// the expression for all values of the variables in their intervals.
+    ///
+    /// Parts of an interval outside of the domain of a function are left out, e.g. `ln([-1, 1])`
+    /// is `[-∞, 0]`, and it is a [`EvalError::DomainError`] if nothing is left. Poles give
+    /// unbounded intervals. Functions without an interval version enclose to the entire real
+    /// line, and so do powers of bases which may be negative, unless the exponent is a single
+    /// integer or a constant fraction.
+    pub fn eval_interval(&self, var_values: &VarMap<Interval>) -> Result<Interval, EvalError> {
+        match self {
+            Expr::Sum(terms) => {
+                let mut sum = Interval::point(0.0);
+
+                for term in terms {
+                    sum = sum + term.eval_interval(var_values)?;
+                }
+
+                Ok(sum)
+            }
+
+            Expr::Product(terms) => {
+                let mut product = Interval::point(1.0);
+
+                for term in terms {
+                    product = product * term.eval_interval(var_values)?;
+                }
+
+                Ok(product)
+            }
+
+            Expr::Pow(base_expr, exponent_expr) => {
+                let base = base_expr.eval_interval(var_values)?;
+                let exponent = exponent_expr.eval_interval(var_values)?;
+
+                if base == Interval::point(0.0) && exponent.hi < 0.0 {
+                    return Err(EvalError::DivisionByZero {
+                        divisor: base_expr.as_ref().clone(),
+                    });
+                }
+
+                let fraction = match exponent_expr.as_ref() {
+                    Expr::Const(num) => num.as_fraction(),
+                    _ => None,
+                };
+                let fraction = fraction.and_then(|(num, den)| Some((num.to_i32()?, den.to_u32()?)));
+
+                let integer = (exponent.lo == exponent.hi && exponent.lo.fract() == 0.0)
+                    .then(|| exponent.lo.to_i32())
+                    .flatten();
+
+                let result = match fraction {
+                    Some((num, 1)) => Some(base.powi(num)),
+                    // Odd roots of negative numbers are real, e.g. (-8)^(2/3) = 4.
+                    Some((num, den)) if den.is_odd() => base.root(den).map(|root| root.powi(num)),
+                    // Even roots leave out negative numbers, like `ln` and `sqrt`.
+                    Some(_) => base.pow(exponent),
+                    None => match integer {
+                        Some(n) => Some(base.powi(n)),
+                        // Powers of negative numbers may be real for some exponents in the
+                        // interval, with values that `pow` does not enclose.
+                        None if base.lo < 0.0 => Some(Interval::entire()),
+                        None => base.pow(exponent),
+                    },
+                };
+
+                result.ok_or_else(|| EvalError::NotReal { expr: self.clone() })
+            }
+
+            Expr::Var(var) => var_values
+                .get(var.as_ref())
+                .copied()
+                .ok_or(EvalError::VarMissing {
+                    name: var.get_name(),
+                }),
+
+            Expr::Const(num) => {
+                let value = num.eval_float()?;
+
+                Ok(match num {
+                    Num::I => return Err(EvalError::NotReal { expr: self.clone() }),
+                    Num::Infinity => Interval::new(f64::INFINITY, f64::INFINITY),
+                    Num::Zero | Num::One => Interval::point(value.re),
+                    // Integers are exact as long as they fit in the mantissa.
+                    Num::Rational { den, .. }
+                        if den.is_one() && value.re.abs() <= 2f64.powi(53) =>
+                    {
+                        Interval::point(value.re)
+                    }
+                    Num::Radical { .. } => outward(value.re, value.re, LIBM_ULPS),
+                    _ => outward(value.re, value.re, 1),
+                })
+            }
+
+            Expr::Function(def, args) => {
+                Arity::check(def.as_ref(), args.len())?;
+
+                let mut evaluated_args = Vec::with_capacity(args.len());
+
+                for arg in args {
+                    evaluated_args.push(arg.eval_interval(var_values)?);
+                }
+
+                match def.as_interval() {
+                    Some(def) => def.eval(evaluated_args, var_values),
+                    None => Ok(Interval::entire()),
+                }
+            }
+        }
+    }
+}
+
+/// Enclosures of the built-in functions, see [`Expr::eval_interval`].
+impl FuncDef<Interval> for Function {
+    fn eval(
+        &self,
+        args: Vec<Interval>,
+        global_vars: &VarMap<Interval>,
+    ) -> Result<Interval, EvalError> {
+        Arity::check::<Interval>(self, args.len())?;
+
+        let x = args[args.len() - 1];
+        let domain_error = |arg: Interval| EvalError::DomainError {
+            function: <Self as FuncDef>::name(self),
+            arg: Complex64::from(arg.midpoint()),
+        };
+
+        let value = match self {
+            Function::F(_, body, vars) => {
+                let mut map: VarMap<Interval> = global_vars.clone();
+
+                for (i, item) in vars.iter().enumerate() {
+                    map.insert(item, args[i]);
+                }
+
+                return body.eval_interval(&map);
+            }
+            Function::Abs => Some(x.abs()),
+            Function::Sgn => {
+                let sgn = |value: f64| if value == 0.0 { 0.0 } else { value.signum() };
+                Some(Interval::new(sgn(x.lo), sgn(x.hi)))
+            }
+            Function::Exp => Some(x.exp()),
+            Function::Ln => x.ln(),
+            Function::Sin => Some(x.sin()),
+            Function::Cos => Some(x.cos()),
+            Function::Tan => Some(x.tan()),
+            Function::Arcsin => x.asin(),
+            Function::Arccos => x.acos(),
+            Function::Arctan => Some(x.atan()),
+            Function::Sinh => Some(x.sinh()),
+            Function::Cosh => Some(x.cosh()),
+            Function::Tanh => Some(x.tanh()),
+            Function::Arcsinh => Some(x.asinh()),
+            Function::Arccosh => x.acosh(),
+            Function::Arctanh => x.atanh(),
+            Function::Atan2 => Some(args[0].atan2(args[1])),
+            Function::Log => {
+                let base = args[0];
+
+                if base == Interval::point(0.0) || base == Interval::point(1.0) {
+                    return Err(domain_error(base));
+                }
+
+                let ln_base = base.ln().ok_or_else(|| domain_error(base))?;
+                x.ln().map(|ln_x| ln_x / ln_base)
+            }
+            Function::Min => Some(args.iter().copied().reduce(Interval::min).unwrap()),
+            Function::Max => Some(args.iter().copied().reduce(Interval::max).unwrap()),
+            Function::Hypot => (args[0].powi(2) + args[1].powi(2)).sqrt(),
+            Function::Root => {
+                let n = args[0];
+
+                if n == Interval::point(0.0) {
+                    return Err(domain_error(n));
+                }
+
+                let index = (n.lo == n.hi && n.lo.fract() == 0.0)
+                    .then(|| n.lo.abs().to_u32())
+                    .flatten();
+
+                match index {
+                    Some(index) if n.lo > 0.0 => x.root(index),
+                    // root(-n, x) = 1/root(n, x)
+                    Some(index) => x.root(index).map(|root| Interval::point(1.0) / root),
+                    // Other roots of negative numbers may be real with values that `pow` does
+                    // not enclose.
+                    None if x.lo < 0.0 => Some(Interval::entire()),
+                    None => x.pow(Interval::point(1.0) / n),
+                }
+            }
+        };
+
+        value.ok_or_else(|| domain_error(x))
+    }
+
+    forward_to_complex!();
+}
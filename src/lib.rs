@@ -1,7 +1,18 @@
+pub mod compile;
+pub mod dual;
+pub mod expand;
 pub mod expr;
 pub mod function;
+pub mod interval;
 pub mod num;
+pub mod parse;
+pub mod poly;
+pub mod print;
 pub mod var;
 mod algo;
+mod batch;
+mod simplify;
+mod substitute;
+mod tape;
 
 mod testing;
\ No newline at end of file
@@ -19,10 +19,10 @@ fn test_2() {
     let f = x.clone() * Expr::Const(Num::rational(5, 1)) + x.clone() - y.clone() * Expr::Const(Num::rational(2, 1));
 
     println!("f = {},\nf simp = {},\ndf/dx = {},\ndf/dx simp = {}", 
-        &f, 
-        f.simplify_trivial_single_layer(), 
-        f.derivative(x_var.as_ref()),
-        f.derivative(x_var.as_ref()).simplify_trivial_single_layer()
+        f.colored(), 
+        f.simplify_trivial_single_layer().colored(), 
+        f.derivative(x_var.as_ref()).colored(),
+        f.derivative(x_var.as_ref()).simplify_trivial_single_layer().colored()
     );
 
     println!("---");
@@ -32,14 +32,15 @@ fn test_2() {
         // * y.clone();
 
     println!("g = {},\ng simp = {},\ndg/dy = {},\ndg/dy simp = {}", 
-        &g, 
-        g.simplify_trivial_single_layer(), 
-        g.derivative(y_var.as_ref()),
-        g.derivative(y_var.as_ref()).simplify_trivial_single_layer()
+        g.colored(), 
+        g.simplify_trivial_single_layer().colored(), 
+        g.derivative(y_var.as_ref()).colored(),
+        g.derivative(y_var.as_ref()).simplify_trivial_single_layer().colored()
     );
 
 }
 
+#[allow(dead_code)]
 fn test_1() {
     let x_var = Var::new("x");
     let y_var = Var::new("y");
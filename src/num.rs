@@ -1,20 +1,31 @@
+use num_bigint::{BigInt, BigUint};
 use num_complex::Complex64;
+use num_integer::Integer;
+use num_traits::{One, Pow, Signed, ToPrimitive, Zero};
 use std::{
+    cmp::Ordering,
     fmt,
-    num::{NonZeroI32, NonZeroU32},
+    ops::{Add, Div, Mul, Neg, Sub},
 };
 
 use crate::algo;
+use crate::expr::{EvalError, EvalResult, Expr};
 
-#[derive(Debug, Clone, Copy)]
+/// Results of exact arithmetic larger than this many bits are left symbolic instead.
+const MAX_EXACT_BITS: u64 = 1 << 20;
+
+#[derive(Debug, Clone)]
 pub enum Num {
     Rational {
-        num: NonZeroI32,
-        den: NonZeroU32,
+        num: BigInt,
+        den: BigUint,
     },
+    /// `num/den` times the `index`-th root of `radicand`.
     Radical {
-        radicand: NonZeroU32,
-        index: NonZeroU32,
+        num: BigInt,
+        den: BigUint,
+        radicand: BigUint,
+        index: u32,
     },
     Pi,
     E,
@@ -26,13 +37,14 @@ pub enum Num {
 }
 
 impl Num {
-    pub fn eval_float(&self) -> Complex64 {
-        match self {
-            Num::Rational { num, den } => {
-                Complex64::new((num.get() as f64) / (den.get() as f64), 0.0)
-            }
-            Num::Radical { radicand, index } => Complex64::new(
-                (radicand.get() as f64).powf(1.0 / (index.get() as f64)),
+    /// Evaluates the number as a complex float. Fails only on `Undefined`.
+    pub fn eval_float(&self) -> EvalResult {
+        Ok(match self {
+            Num::Rational { num, den } => Complex64::new(ratio_to_f64(num, den), 0.0),
+            Num::Radical { num, den, radicand, index } => Complex64::new(
+                ratio_to_f64(num, den)
+                    * ratio_to_f64(&BigInt::from(radicand.clone()), &BigUint::one())
+                        .powf(1.0 / (*index as f64)),
                 0.0,
             ),
             Num::Pi => Complex64::new(std::f64::consts::PI, 0.0),
@@ -41,89 +53,523 @@ impl Num {
             Num::Zero => Complex64::new(0.0, 0.0),
             Num::One => Complex64::new(1.0, 0.0),
             Num::Infinity => Complex64::new(f64::INFINITY, 0.0),
-            Num::Undefined => todo!("Num::Undefined is, well, undefined (unimplemented)"),
-        }
+            Num::Undefined => {
+                return Err(EvalError::Undefined {
+                    expr: Expr::Const(self.clone()),
+                })
+            }
+        })
     }
 
     pub fn reduce(&self) -> Num {
         match self {
-            Num::Rational { num, den } => {
-                let gcd = algo::euclid_gcd(num.get().abs() as u32, den.get());
-                Num::Rational {
-                    num: NonZeroI32::new(num.get() / gcd as i32).unwrap(),
-                    den: NonZeroU32::new(den.get() / gcd).unwrap(),
-                }
+            Num::Rational { num, den } => Num::rational(num.clone(), den.clone()),
+            Num::Radical { num, den, radicand, index } => {
+                Num::scaled_radical(num.clone(), den.clone().into(), radicand.clone(), *index)
             }
-            Num::Radical { .. } => todo!(),
-            _ => *self,
+            _ => self.clone(),
         }
     }
 
-    pub fn int(num: i32) -> Self {
-        Self::from(num)
+    pub fn int(num: impl Into<BigInt>) -> Self {
+        Self::rational(num, 1)
     }
 
-    pub fn rational(num: i32, den: u32) -> Self {
-        match (num, den) {
-            (_, 0) => Num::Undefined,
-            (_, 1) => Num::from(num),
-            (0, _) => Num::Zero,
-            (1, _) => Num::One,
-            _ => Num::Rational {
-                num: NonZeroI32::new(num).unwrap(),
-                den: NonZeroU32::new(den).unwrap(),
-            },
+    /// Creates the rational `num/den` in lowest terms. Either argument may be negative.
+    pub fn rational(num: impl Into<BigInt>, den: impl Into<BigInt>) -> Self {
+        let (num, den): (BigInt, BigInt) = (num.into(), den.into());
+
+        if den.is_zero() {
+            return Num::Undefined;
+        }
+        if num.is_zero() {
+            return Num::Zero;
+        }
+
+        let gcd = algo::euclid_gcd(num.abs(), den.abs());
+        let (num, den) = (num / &gcd, den / gcd);
+        let (num, den) = if den.is_negative() {
+            (-num, -den)
+        } else {
+            (num, den)
+        };
+
+        if num.is_one() && den.is_one() {
+            Num::One
+        } else {
+            Num::Rational {
+                num,
+                den: den.into_parts().1,
+            }
         }
     }
 
-    pub fn radical(radicand: u32, index: u32) -> Self {
-        match (radicand, index) {
-            (_, 0) => Num::Undefined,
-            (_, 1) => Num::from(radicand as i32),
-            (0, _) => Num::Zero,
-            (1, _) => Num::One,
-            _ => Num::Radical {
-                radicand: NonZeroU32::new(radicand).unwrap(),
-                index: NonZeroU32::new(index).unwrap(),
-            },
+    /// Creates the `index`-th root of `radicand` in canonical form.
+    pub fn radical(radicand: impl Into<BigUint>, index: u32) -> Self {
+        Self::scaled_radical(BigInt::one(), BigInt::one(), radicand.into(), index)
+    }
+
+    /// Creates `num/den` times the `index`-th root of `radicand` in canonical form.
+    ///
+    /// Perfect powers are moved out of the radicand (`\sqrt{8} = 2\sqrt{2}`) and the index is
+    /// lowered as far as the radicand allows (`\sqrt[4]{4} = \sqrt{2}`). Exact roots become
+    /// rationals. Radicands are only factored as far as [`algo::factorize`] can, so repeated
+    /// prime factors above its trial division bound may stay inside unless the whole radicand
+    /// is a perfect power.
+    fn scaled_radical(num: BigInt, den: BigInt, radicand: BigUint, index: u32) -> Self {
+        if index == 0 || den.is_zero() {
+            return Num::Undefined;
+        }
+        if num.is_zero() || radicand.is_zero() {
+            return Num::Zero;
+        }
+
+        let mut outside = BigUint::one();
+        let mut inside = vec![];
+        for (factor, multiplicity) in algo::factorize(&radicand) {
+            outside *= Pow::pow(&factor, multiplicity / index);
+            if multiplicity % index != 0 {
+                inside.push((factor, multiplicity % index));
+            }
+        }
+
+        let common = inside
+            .iter()
+            .fold(index, |gcd, (_, multiplicity)| algo::euclid_gcd(gcd, *multiplicity));
+        let radicand: BigUint = inside
+            .into_iter()
+            .map(|(factor, multiplicity)| factor.pow(multiplicity / common))
+            .product();
+
+        let coefficient = Num::rational(num * BigInt::from(outside), den);
+        if radicand.is_one() {
+            return coefficient;
+        }
+
+        let (num, den) = coefficient.as_fraction().unwrap();
+        Num::Radical {
+            num,
+            den: den.into_parts().1,
+            radicand,
+            index: index / common,
         }
     }
 
     pub fn is_zero(&self) -> bool {
-        match self {
-            Num::Zero => true,
-            _ => false
-        }
+        matches!(self, Num::Zero)
     }
 
     pub fn is_one(&self) -> bool {
         match self {
-            Num::Rational { num, .. } => num.get() == 1,
-            Num::Radical { radicand, .. } => radicand.get() == 1,
+            Num::Rational { num, den } => num.magnitude() == den && num.is_positive(),
+            // A canonical radical always has a radicand above 1.
+            Num::Radical { .. } => false,
             Num::One => true,
             _ => false,
         }
     }
 
+    /// Returns the value as an integer, if it is one that fits in an `i32`.
+    pub fn as_integer(&self) -> Option<i32> {
+        let (num, den) = self.as_fraction()?;
+
+        if den.is_one() {
+            num.to_i32()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the numerator and (positive) denominator, if the number is rational.
+    pub fn as_fraction(&self) -> Option<(BigInt, BigInt)> {
+        match self {
+            Num::Zero => Some((BigInt::zero(), BigInt::one())),
+            Num::One => Some((BigInt::one(), BigInt::one())),
+            Num::Rational { num, den } => Some((num.clone(), BigInt::from(den.clone()))),
+            _ => None,
+        }
+    }
+
     pub fn is_undefined(&self) -> bool {
+        matches!(self, Num::Undefined)
+    }
+
+    /// Splits a real algebraic number into a rational coefficient and the `(radicand, index)` of
+    /// its radical part. Rationals are their own coefficient times the radical `\sqrt[1]{1}`.
+    fn as_scaled_radical(&self) -> Option<((BigInt, BigInt), BigUint, u32)> {
         match self {
-            Num::Undefined => true,
+            Num::Radical { num, den, radicand, index } => {
+                Some(((num.clone(), BigInt::from(den.clone())), radicand.clone(), *index))
+            }
+            _ => Some((self.as_fraction()?, BigUint::one(), 1)),
+        }
+    }
+}
+
+/// Converts `num/den` to the nearest float, even when both are too large for an `f64`.
+fn ratio_to_f64(num: &BigInt, den: &BigUint) -> f64 {
+    let shift = num.bits().max(den.bits()).saturating_sub(1000);
+    let num = (num >> shift).to_f64().unwrap_or(f64::NAN);
+    let den = (den >> shift).to_f64().unwrap_or(f64::NAN);
+
+    num / den
+}
+
+/// Exact arithmetic.
+///
+/// The `checked_*` methods return `None` when the result cannot be represented as a `Num`
+/// (e.g. `1 + π`), and `Undefined` for indeterminate forms such as `0/0` or `∞ - ∞`.
+impl Num {
+    /// Whether the number is finite and real.
+    fn is_finite_real(&self) -> bool {
+        !matches!(self, Num::I | Num::Infinity | Num::Undefined)
+    }
+
+    /// The sign of a real number.
+    pub(crate) fn signum(&self) -> Option<i32> {
+        match self {
+            Num::Zero => Some(0),
+            Num::Rational { num, .. } | Num::Radical { num, .. } => {
+                Some(if num.is_negative() { -1 } else { 1 })
+            }
+            Num::I | Num::Undefined => None,
+            _ => Some(1),
+        }
+    }
+
+    /// Whether both numbers are the same irrational or imaginary constant.
+    fn is_same_constant(&self, other: &Num) -> bool {
+        match (self, other) {
+            (Num::Pi, Num::Pi) | (Num::E, Num::E) | (Num::I, Num::I) => true,
+            (Num::Radical { .. }, Num::Radical { .. }) => same_radical(self, other),
             _ => false,
         }
     }
+
+    pub fn checked_neg(&self) -> Option<Num> {
+        match self {
+            Num::Undefined => Some(Num::Undefined),
+            Num::Radical { num, den, radicand, index } => Some(Num::Radical {
+                num: -num,
+                den: den.clone(),
+                radicand: radicand.clone(),
+                index: *index,
+            }),
+            _ => {
+                let (num, den) = self.as_fraction()?;
+                Some(Num::rational(-num, den))
+            }
+        }
+    }
+
+    pub fn checked_add(&self, rhs: &Num) -> Option<Num> {
+        match (self, rhs) {
+            (Num::Undefined, _) | (_, Num::Undefined) => Some(Num::Undefined),
+            (Num::Zero, other) | (other, Num::Zero) => Some(other.clone()),
+            (Num::Infinity, other) | (other, Num::Infinity) => {
+                (other.is_finite_real() || matches!(other, Num::Infinity)).then_some(Num::Infinity)
+            }
+            _ => {
+                // Only multiples of the same radical can be added, e.g. 2\sqrt{2} + \sqrt{2}.
+                let ((num_a, den_a), radicand, index) = self.as_scaled_radical()?;
+                let ((num_b, den_b), radicand_b, index_b) = rhs.as_scaled_radical()?;
+                let (radicand_a, radicand_b, common) =
+                    common_index(radicand.clone(), index, radicand_b, index_b)?;
+                let (ratio_num, ratio_den) = radical_ratio(&radicand_a, &radicand_b, common)?;
+
+                // a \sqrt[n]{r} + b t \sqrt[n]{r}, where t is the rational ratio of the radicals.
+                Some(Num::scaled_radical(
+                    num_a * &den_b * &ratio_den + num_b * ratio_num * &den_a,
+                    den_a * den_b * ratio_den,
+                    radicand,
+                    index,
+                ))
+            }
+        }
+    }
+
+    pub fn checked_sub(&self, rhs: &Num) -> Option<Num> {
+        match (self, rhs) {
+            (Num::Undefined, _) | (_, Num::Undefined) => Some(Num::Undefined),
+            (Num::Infinity, Num::Infinity) => Some(Num::Undefined),
+            // There is no negative infinity to represent the result with.
+            (_, Num::Infinity) => None,
+            (Num::Infinity, other) => other.is_finite_real().then_some(Num::Infinity),
+            (a, b) if a.is_same_constant(b) => Some(Num::Zero),
+            _ => self.checked_add(&rhs.checked_neg()?),
+        }
+    }
+
+    pub fn checked_mul(&self, rhs: &Num) -> Option<Num> {
+        match (self, rhs) {
+            (Num::Undefined, _) | (_, Num::Undefined) => Some(Num::Undefined),
+            (Num::Zero, Num::Infinity) | (Num::Infinity, Num::Zero) => Some(Num::Undefined),
+            (Num::Zero, _) | (_, Num::Zero) => Some(Num::Zero),
+            (Num::One, other) | (other, Num::One) => Some(other.clone()),
+            (Num::Infinity, other) | (other, Num::Infinity) => {
+                (other.signum() == Some(1)).then_some(Num::Infinity)
+            }
+            (Num::I, Num::I) => Some(Num::from(-1)),
+            _ => {
+                let ((num_a, den_a), radicand_a, index_a) = self.as_scaled_radical()?;
+                let ((num_b, den_b), radicand_b, index_b) = rhs.as_scaled_radical()?;
+                let (radicand_a, radicand_b, index) =
+                    common_index(radicand_a, index_a, radicand_b, index_b)?;
+
+                Some(Num::scaled_radical(
+                    num_a * num_b,
+                    den_a * den_b,
+                    radicand_a * radicand_b,
+                    index,
+                ))
+            }
+        }
+    }
+
+    pub fn checked_div(&self, rhs: &Num) -> Option<Num> {
+        match (self, rhs) {
+            (Num::Undefined, _) | (_, Num::Undefined) => Some(Num::Undefined),
+            (_, Num::Zero) | (Num::Infinity, Num::Infinity) => Some(Num::Undefined),
+            (Num::Zero, _) => Some(Num::Zero),
+            (_, Num::Infinity) => Some(Num::Zero),
+            (Num::Infinity, other) => (other.signum() == Some(1)).then_some(Num::Infinity),
+            (other, Num::One) => Some(other.clone()),
+            (a, b) if a.is_same_constant(b) => Some(Num::One),
+            _ => {
+                let ((num_a, den_a), radicand_a, index_a) = self.as_scaled_radical()?;
+                let ((num_b, den_b), radicand_b, index_b) = rhs.as_scaled_radical()?;
+                let (radicand_a, radicand_b, index) =
+                    common_index(radicand_a, index_a, radicand_b, index_b)?;
+                if !pow_fits(radicand_b.bits(), index) {
+                    return None;
+                }
+
+                // The denominator is rationalised: 1/\sqrt[n]{r} = \sqrt[n]{r^(n-1)} / r.
+                Some(Num::scaled_radical(
+                    num_a * den_b,
+                    den_a * num_b * BigInt::from(radicand_b.clone()),
+                    radicand_a * radicand_b.pow(index - 1),
+                    index,
+                ))
+            }
+        }
+    }
+
+    pub fn checked_pow(&self, exponent: &Num) -> Option<Num> {
+        match (self, exponent) {
+            (Num::Undefined, _) | (_, Num::Undefined) => Some(Num::Undefined),
+            (Num::Zero | Num::Infinity, Num::Zero) | (Num::One, Num::Infinity) => {
+                Some(Num::Undefined)
+            }
+            (_, Num::Zero) | (Num::One, _) => Some(Num::One),
+            (base, Num::One) => Some(base.clone()),
+            (Num::Zero, exponent) => match exponent.signum()? {
+                1 => Some(Num::Zero),
+                _ => Some(Num::Undefined),
+            },
+            (Num::Infinity, exponent) => match exponent.signum()? {
+                1 => Some(Num::Infinity),
+                _ => Some(Num::Zero),
+            },
+            (base, Num::Infinity) => {
+                let (num, den) = base.as_fraction()?;
+                match (num.is_positive(), num.magnitude().cmp(den.magnitude())) {
+                    (true, Ordering::Greater) => Some(Num::Infinity),
+                    (_, Ordering::Less) => Some(Num::Zero),
+                    _ => None,
+                }
+            }
+            (Num::I, exponent) => match exponent.as_integer()?.rem_euclid(4) {
+                0 => Some(Num::One),
+                1 => Some(Num::I),
+                2 => Some(Num::from(-1)),
+                _ => None,
+            },
+            (base, exponent) => {
+                let ((num, den), radicand, index) = base.as_scaled_radical()?;
+                let (p, q) = exponent.as_fraction()?;
+                let p = p.to_i32()?;
+                let q = q.to_u32()?;
+
+                // Roots are only taken of positive numbers, as the principal root of a negative
+                // one is not real, e.g. (-8)^(2/3) = -2 + 2\sqrt{3} i rather than 4.
+                if q != 1 && num.is_negative() {
+                    return None;
+                }
+
+                let bits = num.bits().max(den.bits()).max(radicand.bits());
+                let k = p.unsigned_abs();
+                if !pow_fits(bits, k.saturating_mul(q).saturating_mul(index)) {
+                    return None;
+                }
+
+                // (c \sqrt[n]{r})^k = c^k \sqrt[n]{r^k}
+                let (num, den, radicand) = (num.pow(k), den.pow(k), radicand.pow(k));
+
+                let power = if q == 1 {
+                    Num::scaled_radical(num, den, radicand, index)
+                } else {
+                    // \sqrt[q]{a/b} \sqrt[nq]{r} = \sqrt[nq]{(a b^(q-1))^n r} / b
+                    let inner = (num.magnitude() * den.magnitude().pow(q - 1)).pow(index);
+                    Num::scaled_radical(BigInt::one(), den, inner * radicand, index.checked_mul(q)?)
+                };
+
+                if p < 0 {
+                    Num::One.checked_div(&power)
+                } else {
+                    Some(power)
+                }
+            }
+        }
+    }
+
+    /// Raises the number to the power of `exponent`, falling back to a symbolic power when the
+    /// result is not representable.
+    pub fn pow(self, exponent: Num) -> Expr {
+        match self.checked_pow(&exponent) {
+            Some(result) => Expr::Const(result),
+            None => Expr::Const(self).pow(Expr::Const(exponent)),
+        }
+    }
+}
+
+/// Rewrites two radicals over their least common index, so that they can be multiplied.
+fn common_index(
+    radicand_a: BigUint,
+    index_a: u32,
+    radicand_b: BigUint,
+    index_b: u32,
+) -> Option<(BigUint, BigUint, u32)> {
+    let index = index_a.lcm(&index_b);
+    let (power_a, power_b) = (index / index_a, index / index_b);
+
+    if !pow_fits(radicand_a.bits(), power_a) || !pow_fits(radicand_b.bits(), power_b) {
+        return None;
+    }
+
+    Some((radicand_a.pow(power_a), radicand_b.pow(power_b), index))
+}
+
+/// The rational `t` with `\sqrt[n]{radicand_b} = t \sqrt[n]{radicand_a}`, if there is one, as
+/// a numerator and denominator.
+fn radical_ratio(radicand_a: &BigUint, radicand_b: &BigUint, n: u32) -> Option<(BigInt, BigInt)> {
+    let gcd = algo::euclid_gcd(radicand_a.clone(), radicand_b.clone());
+    let num = algo::exact_root(&(radicand_b / &gcd), n)?;
+    let den = algo::exact_root(&(radicand_a / &gcd), n)?;
+    Some((num.into(), den.into()))
+}
+
+/// Whether two radicals have the same value. Their fields may differ when a prime factor of a
+/// radicand was too large to move out, e.g. `\sqrt{p^2 q}` and `p\sqrt{q}`, so this compares
+/// `(num/den)^n radicand` over their common index `n` instead.
+fn same_radical(a: &Num, b: &Num) -> bool {
+    let Some(((num_a, den_a), radicand_a, index_a)) = a.as_scaled_radical() else {
+        return false;
+    };
+    let Some(((num_b, den_b), radicand_b, index_b)) = b.as_scaled_radical() else {
+        return false;
+    };
+    if num_a.sign() != num_b.sign() {
+        return false;
+    }
+    if (&num_a, &den_a, &radicand_a, index_a) == (&num_b, &den_b, &radicand_b, index_b) {
+        return true;
+    }
+
+    let Some((radicand_a, radicand_b, n)) = common_index(radicand_a, index_a, radicand_b, index_b)
+    else {
+        return false;
+    };
+    let bits = num_a.bits() + den_a.bits() + num_b.bits() + den_b.bits();
+    if !pow_fits(bits, n) {
+        return false;
+    }
+
+    // num_a^n radicand_a den_b^n = num_b^n radicand_b den_a^n
+    let side = |num: &BigInt, radicand: BigUint, den: &BigInt| {
+        num.magnitude().pow(n) * radicand * den.magnitude().pow(n)
+    };
+    side(&num_a, radicand_a, &den_b) == side(&num_b, radicand_b, &den_a)
+}
+
+/// Whether raising a number of `bits` bits to `exponent` stays within [`MAX_EXACT_BITS`].
+fn pow_fits(bits: u64, exponent: u32) -> bool {
+    bits.saturating_mul(exponent as u64) <= MAX_EXACT_BITS
+}
+
+impl Add for Num {
+    type Output = Expr;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        match self.checked_add(&rhs) {
+            Some(result) => Expr::Const(result),
+            None => Expr::Const(self) + Expr::Const(rhs),
+        }
+    }
+}
+
+impl Sub for Num {
+    type Output = Expr;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        match self.checked_sub(&rhs) {
+            Some(result) => Expr::Const(result),
+            None => Expr::Const(self) - Expr::Const(rhs),
+        }
+    }
+}
+
+impl Mul for Num {
+    type Output = Expr;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        match self.checked_mul(&rhs) {
+            Some(result) => Expr::Const(result),
+            None => Expr::Const(self) * Expr::Const(rhs),
+        }
+    }
+}
+
+impl Div for Num {
+    type Output = Expr;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        match self.checked_div(&rhs) {
+            Some(result) => Expr::Const(result),
+            None => Expr::Const(self) / Expr::Const(rhs),
+        }
+    }
+}
+
+impl Neg for Num {
+    type Output = Expr;
+
+    fn neg(self) -> Self::Output {
+        match self.checked_neg() {
+            Some(result) => Expr::Const(result),
+            None => Expr::Product(vec![Expr::Const(Num::from(-1)), Expr::Const(self)]),
+        }
+    }
 }
 
 impl fmt::Display for Num {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Num::Rational { num, den } => {
-                if den.get() == 1 {
+                if den.is_one() {
                     write!(f, "{}", num)
                 } else {
                     write!(f, "{}/{}", num, den)
                 }
             }
-            Num::Radical { radicand, index } => write!(f, "\\sqrt[{}]{{{}}}", index, radicand),
+            Num::Radical { num, den, radicand, index } => {
+                match Num::rational(num.clone(), den.clone()) {
+                    Num::One => {}
+                    coefficient if coefficient.as_integer() == Some(-1) => write!(f, "-")?,
+                    coefficient => write!(f, "{}", coefficient)?,
+                }
+                write!(f, "\\sqrt[{}]{{{}}}", index, radicand)
+            }
             Num::Pi => write!(f, "\\pi"),
             Num::E => write!(f, "e"),
             Num::I => write!(f, "i"),
@@ -135,9 +581,12 @@ impl fmt::Display for Num {
     }
 }
 
+/// Rationals are compared field by field, which relies on the lowest terms the constructors and
+/// arithmetic produce, so numbers built by hand from the variants should be [`Num::reduce`]d
+/// first. Radicals are compared by value, see [`Num::radical`].
 impl PartialEq for Num {
     fn eq(&self, other: &Self) -> bool {
-        match (self.reduce(), other.reduce()) {
+        match (self, other) {
             (Num::E, Num::E) => true,
             (Num::I, Num::I) => true,
             (Num::Pi, Num::Pi) => true,
@@ -155,9 +604,7 @@ impl PartialEq for Num {
                     den: den_b,
                 },
             ) => num_a == num_b && den_a == den_b,
-            (Num::Radical { .. }, Num::Radical { .. }) => {
-                todo!()
-            }
+            (Num::Radical { .. }, Num::Radical { .. }) => same_radical(self, other),
             _ => false,
         }
     }
@@ -165,19 +612,30 @@ impl PartialEq for Num {
 
 impl PartialEq<Complex64> for Num {
     fn eq(&self, other: &Complex64) -> bool {
-        todo!()
+        self.eval_float().is_ok_and(|value| value == *other)
     }
 }
 
 impl From<i32> for Num {
     fn from(value: i32) -> Self {
-        match value {
-            0 => Num::Zero,
-            1 => Num::One,
-            _ => Num::Rational {
-                num: NonZeroI32::new(value).unwrap(),
-                den: NonZeroU32::new(1).unwrap(),
-            },
-        }
+        Num::int(value)
+    }
+}
+
+impl From<i64> for Num {
+    fn from(value: i64) -> Self {
+        Num::int(value)
+    }
+}
+
+impl From<BigInt> for Num {
+    fn from(value: BigInt) -> Self {
+        Num::int(value)
+    }
+}
+
+impl From<BigUint> for Num {
+    fn from(value: BigUint) -> Self {
+        Num::int(value)
     }
 }
@@ -0,0 +1,507 @@
+use num_bigint::BigInt;
+use num_traits::Pow;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::{
+    expr::Expr,
+    function::{Arity, FuncDef, Function},
+    num::Num,
+    var::Var,
+};
+
+/// A byte range into the parsed input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
+/// The reason a string could not be parsed into an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    UnexpectedChar(char),
+    UnexpectedToken {
+        found: String,
+    },
+    UnexpectedEnd,
+    UnclosedParen,
+    UnknownFunction {
+        name: String,
+    },
+    InvalidNumber {
+        literal: String,
+    },
+    FnArgCountMismatch {
+        name: String,
+        expected: Arity,
+        found: usize,
+    },
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedChar(c) => write!(f, "Unexpected character '{c}'."),
+            Self::UnexpectedToken { found } => write!(f, "Unexpected '{found}'."),
+            Self::UnexpectedEnd => write!(f, "Unexpected end of input."),
+            Self::UnclosedParen => write!(f, "Unclosed parenthesis."),
+            Self::UnknownFunction { name } => write!(f, "Unknown function '{name}'."),
+            Self::InvalidNumber { literal } => write!(f, "Invalid number '{literal}'."),
+            Self::FnArgCountMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Function '{name}' takes {expected} argument(s) but {found} were given."
+            ),
+        }
+    }
+}
+
+/// An error produced while parsing, along with the span of input it refers to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (at {}..{})",
+            self.kind, self.span.start, self.span.end
+        )
+    }
+}
+
+pub type ParseResult<T> = Result<T, ParseError>;
+
+/// Parses a string such as `"x*5 + sin(y)^2"` into an expression.
+///
+/// Supports `+ - * / ^` with the usual precedence (`^` is right associative and binds tighter
+/// than unary minus), parentheses, implicit multiplication (`2x`, `3(x + 1)`), the built-in
+/// functions and `sqrt`, the constants `pi`, `e`, `i` and `inf`, and integer or decimal literals, which
+/// are kept as exact rationals.
+pub fn parse(input: &str) -> ParseResult<Expr> {
+    let tokens = lex(input)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end: input.len(),
+    };
+
+    let expr = parser.sum()?;
+
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) if token.kind == TokenKind::RParen => Err(ParseError {
+            kind: ParseErrorKind::UnexpectedToken {
+                found: ")".to_string(),
+            },
+            span: token.span,
+        }),
+        Some(token) => Err(token.unexpected()),
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Number(String),
+    Ident(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    LParen,
+    RParen,
+    Comma,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    span: Span,
+}
+
+impl Token {
+    fn unexpected(&self) -> ParseError {
+        let found = match &self.kind {
+            TokenKind::Number(literal) | TokenKind::Ident(literal) => literal.clone(),
+            TokenKind::Plus => "+".to_string(),
+            TokenKind::Minus => "-".to_string(),
+            TokenKind::Star => "*".to_string(),
+            TokenKind::Slash => "/".to_string(),
+            TokenKind::Caret => "^".to_string(),
+            TokenKind::LParen => "(".to_string(),
+            TokenKind::RParen => ")".to_string(),
+            TokenKind::Comma => ",".to_string(),
+        };
+
+        ParseError {
+            kind: ParseErrorKind::UnexpectedToken { found },
+            span: self.span,
+        }
+    }
+
+    /// Whether this token can begin an operand of an implicit multiplication.
+    fn starts_operand(&self) -> bool {
+        matches!(
+            self.kind,
+            TokenKind::Number(_) | TokenKind::Ident(_) | TokenKind::LParen
+        )
+    }
+}
+
+fn lex(input: &str) -> ParseResult<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let single = |kind| Token {
+            kind,
+            span: Span {
+                start,
+                end: start + c.len_utf8(),
+            },
+        };
+
+        match c {
+            c if c.is_whitespace() => continue,
+            '+' => tokens.push(single(TokenKind::Plus)),
+            '-' | '−' => tokens.push(single(TokenKind::Minus)),
+            '*' | '·' | '×' => tokens.push(single(TokenKind::Star)),
+            '/' => tokens.push(single(TokenKind::Slash)),
+            '^' => tokens.push(single(TokenKind::Caret)),
+            '(' => tokens.push(single(TokenKind::LParen)),
+            ')' => tokens.push(single(TokenKind::RParen)),
+            ',' => tokens.push(single(TokenKind::Comma)),
+            '∞' => tokens.push(single(TokenKind::Ident(c.to_string()))),
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, next)) = chars.peek() {
+                    if next.is_ascii_digit() || next == '.' {
+                        end = i + next.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Number(input[start..end].to_string()),
+                    span: Span { start, end },
+                });
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, next)) = chars.peek() {
+                    if next.is_alphanumeric() || next == '_' {
+                        end = i + next.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Ident(input[start..end].to_string()),
+                    span: Span { start, end },
+                });
+            }
+            _ => {
+                return Err(ParseError {
+                    kind: ParseErrorKind::UnexpectedChar(c),
+                    span: Span {
+                        start,
+                        end: start + c.len_utf8(),
+                    },
+                })
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> ParseResult<Token> {
+        let token = self.tokens.get(self.pos).cloned().ok_or(ParseError {
+            kind: ParseErrorKind::UnexpectedEnd,
+            span: Span {
+                start: self.end,
+                end: self.end,
+            },
+        })?;
+        self.pos += 1;
+
+        Ok(token)
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if self.peek().is_some_and(|token| &token.kind == kind) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn last_span(&self) -> Span {
+        self.tokens[self.pos - 1].span
+    }
+
+    /// sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> ParseResult<Expr> {
+        let mut terms = vec![self.product()?];
+
+        loop {
+            if self.eat(&TokenKind::Plus) {
+                terms.push(self.product()?);
+            } else if self.eat(&TokenKind::Minus) {
+                let term = self.product()?;
+                terms.push(negate(term));
+            } else {
+                break;
+            }
+        }
+
+        Ok(if terms.len() == 1 {
+            terms.pop().unwrap()
+        } else {
+            Expr::Sum(terms)
+        })
+    }
+
+    /// product := unary (('*' | '/') unary | power)*
+    fn product(&mut self) -> ParseResult<Expr> {
+        let mut factors = vec![self.unary()?];
+
+        loop {
+            if self.eat(&TokenKind::Star) {
+                factors.push(self.unary()?);
+            } else if self.eat(&TokenKind::Slash) {
+                let divisor = self.unary()?;
+                let dividend = factors.pop().unwrap();
+                factors.push(divide(dividend, divisor));
+            } else if self.peek().is_some_and(Token::starts_operand) {
+                factors.push(self.power()?);
+            } else {
+                break;
+            }
+        }
+
+        Ok(if factors.len() == 1 {
+            factors.pop().unwrap()
+        } else {
+            Expr::Product(factors)
+        })
+    }
+
+    /// unary := '-' unary | '+' unary | power
+    fn unary(&mut self) -> ParseResult<Expr> {
+        if self.eat(&TokenKind::Minus) {
+            Ok(negate(self.unary()?))
+        } else if self.eat(&TokenKind::Plus) {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    /// power := primary ('^' unary)?
+    fn power(&mut self) -> ParseResult<Expr> {
+        let base = self.primary()?;
+
+        if self.eat(&TokenKind::Caret) {
+            Ok(base.pow(self.unary()?))
+        } else {
+            Ok(base)
+        }
+    }
+
+    /// primary := number | constant | variable | function '(' args ')' | '(' sum ')'
+    fn primary(&mut self) -> ParseResult<Expr> {
+        let token = self.next()?;
+
+        match &token.kind {
+            TokenKind::Number(literal) => parse_number(literal, token.span),
+
+            TokenKind::Ident(name) => {
+                if let Some(num) = constant(name) {
+                    return Ok(Expr::Const(num));
+                }
+
+                let is_call = self
+                    .peek()
+                    .is_some_and(|next| next.kind == TokenKind::LParen);
+
+                if name == "sqrt" || Function::from_name(name).is_some() {
+                    if !is_call {
+                        // Surfaces either the offending token or the end of input.
+                        return Err(self.next().map_or_else(|err| err, |next| next.unexpected()));
+                    }
+                    self.call(name, token.span)
+                } else if is_call && name.chars().count() > 1 {
+                    Err(ParseError {
+                        kind: ParseErrorKind::UnknownFunction { name: name.clone() },
+                        span: token.span,
+                    })
+                } else {
+                    Ok(Expr::Var(Var::new(name)))
+                }
+            }
+
+            TokenKind::LParen => {
+                let inner = self.sum()?;
+                if self.eat(&TokenKind::RParen) {
+                    Ok(inner)
+                } else {
+                    Err(ParseError {
+                        kind: ParseErrorKind::UnclosedParen,
+                        span: token.span,
+                    })
+                }
+            }
+
+            _ => Err(token.unexpected()),
+        }
+    }
+
+    fn call(&mut self, name: &str, name_span: Span) -> ParseResult<Expr> {
+        let open = self.next()?;
+        let mut args = vec![];
+
+        if !self.eat(&TokenKind::RParen) {
+            loop {
+                args.push(self.sum()?);
+
+                if self.eat(&TokenKind::Comma) {
+                    continue;
+                } else if self.eat(&TokenKind::RParen) {
+                    break;
+                } else {
+                    return Err(ParseError {
+                        kind: ParseErrorKind::UnclosedParen,
+                        span: open.span,
+                    });
+                }
+            }
+        }
+
+        let function = Function::from_name(name);
+        let expected = function.as_ref().map_or(Arity::Exactly(1), <Function as FuncDef>::arity);
+
+        if !expected.accepts(args.len()) {
+            return Err(ParseError {
+                kind: ParseErrorKind::FnArgCountMismatch {
+                    name: name.to_string(),
+                    expected,
+                    found: args.len(),
+                },
+                span: name_span.to(self.last_span()),
+            });
+        }
+
+        Ok(match function {
+            Some(function) => Expr::Function(Arc::new(function), args),
+            // sqrt(x) = x^(1/2)
+            None => args.pop().unwrap().pow(Expr::Const(Num::rational(1, 2))),
+        })
+    }
+}
+
+fn constant(name: &str) -> Option<Num> {
+    match name {
+        "pi" | "π" => Some(Num::Pi),
+        "e" => Some(Num::E),
+        "i" => Some(Num::I),
+        "inf" | "∞" => Some(Num::Infinity),
+        _ => None,
+    }
+}
+
+/// Parses an integer or decimal literal into an exact rational.
+fn parse_number(literal: &str, span: Span) -> ParseResult<Expr> {
+    let invalid = || ParseError {
+        kind: ParseErrorKind::InvalidNumber {
+            literal: literal.to_string(),
+        },
+        span,
+    };
+
+    let (whole, frac) = match literal.split_once('.') {
+        Some((whole, frac)) => (whole, frac),
+        None => (literal, ""),
+    };
+    if (whole.is_empty() && frac.is_empty()) || frac.contains('.') {
+        return Err(invalid());
+    }
+
+    let num: BigInt = format!("{whole}{frac}").parse().map_err(|_| invalid())?;
+    let den = BigInt::from(10).pow(frac.len() as u32);
+
+    Ok(Expr::Const(Num::rational(num, den)))
+}
+
+fn negate(expr: Expr) -> Expr {
+    if let Expr::Const(num) = &expr {
+        if let Some(negated) = num.checked_neg() {
+            return Expr::Const(negated);
+        }
+    }
+
+    Expr::Product(vec![Expr::Const(Num::from(-1)), expr])
+}
+
+fn divide(dividend: Expr, divisor: Expr) -> Expr {
+    if let Expr::Const(divisor) = &divisor {
+        if divisor.as_fraction().is_some() {
+            // Keeps rational literals such as `3/4` as a single constant.
+            if let Expr::Const(dividend) = &dividend {
+                if dividend.as_fraction().is_some() {
+                    if let Some(quotient) = dividend.checked_div(divisor) {
+                        return Expr::Const(quotient);
+                    }
+                }
+            }
+
+            match Num::One.checked_div(divisor) {
+                Some(Num::Undefined) => return Expr::Const(Num::Undefined),
+                Some(reciprocal) => return dividend * Expr::Const(reciprocal),
+                None => (),
+            }
+        }
+    }
+
+    dividend / divisor
+}
@@ -0,0 +1,423 @@
+//! Division, pseudo-remainders and greatest common divisors of polynomials.
+
+use num_bigint::BigInt;
+use num_traits::{One, Signed};
+
+use crate::{algo, num::Num, var::Var};
+
+use super::{add, assert_same_var, mul, Polynomial, UnivariatePolynomial};
+
+impl UnivariatePolynomial {
+    /// Divides by `divisor`, returning the quotient and the remainder, whose degree is lower
+    /// than that of `divisor`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `divisor` is zero or in a different variable.
+    pub fn div_rem(&self, divisor: &Self) -> (Self, Self) {
+        assert_same_var(self, divisor);
+        assert!(!divisor.is_zero(), "division by the zero polynomial");
+
+        let len = divisor.coefficients.len();
+        let lead = divisor.leading_coefficient();
+        let mut remainder = self.coefficients.clone();
+        let mut quotient = vec![Num::Zero; (remainder.len() + 1).saturating_sub(len)];
+
+        while remainder.len() >= len {
+            let shift = remainder.len() - len;
+            let factor = div(remainder.last().unwrap(), &lead);
+
+            for (i, coefficient) in divisor.coefficients.iter().enumerate() {
+                remainder[shift + i] = sub(&remainder[shift + i], &mul(&factor, coefficient));
+            }
+            quotient[shift] = factor;
+
+            // The leading coefficient cancels exactly.
+            remainder.pop();
+            while remainder.last().is_some_and(Num::is_zero) {
+                remainder.pop();
+            }
+        }
+
+        (
+            Self::from_coefficients(self.var.clone(), quotient),
+            Self::from_coefficients(self.var.clone(), remainder),
+        )
+    }
+
+    /// The remainder of `lc^(m - n + 1) self` divided by `divisor`, where `lc` is the leading
+    /// coefficient of `divisor` and `m` and `n` are the degrees. Unlike the remainder, it has
+    /// integer coefficients when both polynomials do.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `divisor` is zero or in a different variable.
+    pub fn pseudo_rem(&self, divisor: &Self) -> Self {
+        let (_, remainder) = self.div_rem(divisor);
+        let power = (self.coefficients.len() + 1).saturating_sub(divisor.coefficients.len());
+
+        remainder.scale(&pow(&divisor.leading_coefficient(), power))
+    }
+
+    /// The rational number whose quotient with the polynomial has coprime integer coefficients
+    /// and a positive leading coefficient. It is zero for the zero polynomial.
+    pub fn content(&self) -> Num {
+        content(self.coefficients.iter(), &self.leading_coefficient())
+    }
+
+    /// The polynomial divided by its [content](Self::content), which has coprime integer
+    /// coefficients and a positive leading coefficient.
+    pub fn primitive_part(&self) -> Self {
+        match self.content() {
+            Num::Zero => self.clone(),
+            content => self.scale(&inverse(&content)),
+        }
+    }
+
+    /// The primitive pseudo-remainder sequence of the polynomials, which starts with their
+    /// primitive parts, highest degree first, and continues with the primitive part of the
+    /// pseudo-remainder of the previous two until that is zero. Zero polynomials are left out.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the polynomials are in different variables.
+    pub fn primitive_prs(&self, other: &Self) -> Vec<Self> {
+        assert_same_var(self, other);
+
+        let (mut a, mut b) = if self.degree() >= other.degree() {
+            (self.primitive_part(), other.primitive_part())
+        } else {
+            (other.primitive_part(), self.primitive_part())
+        };
+        if a.is_zero() {
+            return vec![];
+        }
+
+        let mut sequence = vec![a.clone()];
+        while !b.is_zero() {
+            sequence.push(b.clone());
+            let remainder = a.pseudo_rem(&b).primitive_part();
+            a = b;
+            b = remainder;
+        }
+
+        sequence
+    }
+
+    /// The monic greatest common divisor, which is zero only if both polynomials are.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the polynomials are in different variables.
+    pub fn gcd(&self, other: &Self) -> Self {
+        match self.primitive_prs(other).pop() {
+            Some(gcd) => gcd.monic(),
+            None => self.clone(),
+        }
+    }
+
+    /// The polynomial divided by its leading coefficient.
+    pub fn monic(&self) -> Self {
+        match self.leading_coefficient() {
+            Num::Zero => self.clone(),
+            lead => self.scale(&inverse(&lead)),
+        }
+    }
+}
+
+impl Polynomial {
+    /// Divides by `divisor` with the multivariate division algorithm in the lexicographic
+    /// order, returning the quotient and a remainder with no term divisible by the leading
+    /// term of `divisor`. The remainder is zero whenever `divisor` divides the polynomial.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `divisor` is zero.
+    pub fn div_rem(&self, divisor: &Self) -> (Self, Self) {
+        if self.vars != divisor.vars {
+            let (dividend, divisor) = self.unify(divisor);
+            return dividend.div_rem(&divisor);
+        }
+
+        let (lead_exponents, lead) = divisor
+            .leading_term()
+            .expect("division by the zero polynomial");
+        let mut quotient = Self::zero_in(&self.vars);
+        let mut remainder = Self::zero_in(&self.vars);
+        let mut rest = self.clone();
+
+        while let Some((exponents, coefficient)) = rest.terms.pop_last() {
+            let divisible = exponents.iter().zip(lead_exponents).all(|(a, b)| a >= b);
+            if !divisible {
+                remainder.terms.insert(exponents, coefficient);
+                continue;
+            }
+
+            let shift: Vec<u32> = exponents
+                .iter()
+                .zip(lead_exponents)
+                .map(|(a, b)| a - b)
+                .collect();
+            let factor = div(&coefficient, lead);
+
+            // The leading term was already removed, and cancels with that of the product.
+            for (exponents, coefficient) in divisor.terms().rev().skip(1) {
+                let exponents = exponents
+                    .iter()
+                    .zip(&shift)
+                    .map(|(a, b)| {
+                        a.checked_add(*b)
+                            .expect("the exponents of the quotient times the divisor fit in a u32")
+                    })
+                    .collect();
+                rest.add_term(exponents, &mul(&factor, coefficient).checked_neg().unwrap());
+            }
+            quotient.add_term(shift, &factor);
+        }
+
+        (quotient, remainder)
+    }
+
+    /// The quotient by `divisor`, if it divides the polynomial.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `divisor` is zero.
+    pub fn divide_exact(&self, divisor: &Self) -> Option<Self> {
+        let (quotient, remainder) = self.div_rem(divisor);
+        remainder.is_zero().then_some(quotient)
+    }
+
+    /// The pseudo-remainder of the polynomials as univariate polynomials in `var`, whose
+    /// coefficients are polynomials in the other variables. See
+    /// [`UnivariatePolynomial::pseudo_rem`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `divisor` is zero.
+    pub fn pseudo_rem(&self, divisor: &Self, var: &Var) -> Self {
+        assert!(!divisor.is_zero(), "division by the zero polynomial");
+        let (dividend, divisor) = self.unify(divisor);
+
+        match dividend.vars.iter().position(|other| other == var) {
+            Some(index) => {
+                let remainder = pseudo_rem(&dividend.split(index), &divisor.split(index));
+                Self::join(&dividend.vars, &remainder, index)
+            }
+            // Both are constants in `var`, so the divisor divides lc * dividend exactly.
+            None => Self::zero_in(&dividend.vars),
+        }
+    }
+
+    /// The rational number whose quotient with the polynomial has coprime integer coefficients
+    /// and a positive leading coefficient. It is zero for the zero polynomial.
+    pub fn content(&self) -> Num {
+        content(self.terms.values(), &self.leading_coefficient())
+    }
+
+    /// The polynomial divided by its [content](Self::content), which has coprime integer
+    /// coefficients and a positive leading coefficient.
+    pub fn primitive_part(&self) -> Self {
+        match self.content() {
+            Num::Zero => self.clone(),
+            content => self.scale(&inverse(&content)),
+        }
+    }
+
+    /// The greatest common divisor, with a leading coefficient of one. It is zero only if both
+    /// polynomials are.
+    ///
+    /// Computed recursively, as the product of the greatest common divisor of the contents and
+    /// the last element of the primitive pseudo-remainder sequence of the primitive parts, both
+    /// as univariate polynomials in the first variable either depends on.
+    pub fn gcd(&self, other: &Self) -> Self {
+        let (a, b) = self.unify(other);
+        let gcd = gcd(&a, &b);
+
+        match gcd.leading_coefficient() {
+            Num::Zero => gcd,
+            lead => gcd.scale(&inverse(&lead)),
+        }
+    }
+
+    /// The coefficients of the polynomial as a univariate polynomial in the `index`-th
+    /// variable, from the constant term up.
+    pub(super) fn split(&self, index: usize) -> Vec<Polynomial> {
+        let degree = self.terms.keys().map(|exponents| exponents[index]).max();
+        let mut coefficients =
+            vec![Self::zero_in(&self.vars); degree.map_or(0, |d| d as usize + 1)];
+
+        for (exponents, coefficient) in &self.terms {
+            let mut rest = exponents.clone();
+            rest[index] = 0;
+            coefficients[exponents[index] as usize].add_term(rest, coefficient);
+        }
+
+        coefficients
+    }
+
+    /// The inverse of [`Polynomial::split`].
+    pub(super) fn join(vars: &[Var], coefficients: &[Polynomial], index: usize) -> Self {
+        let mut polynomial = Self::zero_in(vars);
+
+        for (power, coefficient) in coefficients.iter().enumerate() {
+            for (exponents, coefficient) in &coefficient.terms {
+                let mut exponents = exponents.clone();
+                exponents[index] = u32::try_from(power)
+                    .ok()
+                    .and_then(|power| exponents[index].checked_add(power))
+                    .expect("the exponents of the joined polynomial fit in a u32");
+                polynomial.add_term(exponents, coefficient);
+            }
+        }
+
+        polynomial
+    }
+}
+
+/// The greatest common divisor of two polynomials in the same variables, up to a constant
+/// factor.
+fn gcd(a: &Polynomial, b: &Polynomial) -> Polynomial {
+    if a.is_zero() {
+        return b.clone();
+    }
+    if b.is_zero() {
+        return a.clone();
+    }
+
+    let depends_on = |p: &Polynomial, index: usize| p.terms.keys().any(|e| e[index] > 0);
+    let Some(index) = (0..a.vars.len()).find(|i| depends_on(a, *i) || depends_on(b, *i)) else {
+        return Polynomial::constant_in(&a.vars, Num::One);
+    };
+
+    let vars = a.vars.clone();
+    let (a, b) = (a.split(index), b.split(index));
+    let (content_a, content_b) = (coefficient_gcd(&a), coefficient_gcd(&b));
+    let content = gcd(&content_a, &content_b);
+
+    let mut a = divide_coefficients(&a, &content_a);
+    let mut b = divide_coefficients(&b, &content_b);
+    if a.len() < b.len() {
+        std::mem::swap(&mut a, &mut b);
+    }
+
+    while !b.is_empty() {
+        let remainder = primitive(&pseudo_rem(&a, &b));
+        a = b;
+        b = remainder;
+    }
+
+    &Polynomial::join(&vars, &primitive(&a), index) * &content
+}
+
+/// The greatest common divisor of the coefficients of a univariate polynomial.
+pub(super) fn coefficient_gcd(coefficients: &[Polynomial]) -> Polynomial {
+    coefficients.iter().fold(
+        Polynomial::zero_in(&coefficients[0].vars),
+        |so_far, coefficient| gcd(&so_far, coefficient),
+    )
+}
+
+/// Divides every coefficient by `divisor`, which divides them all.
+fn divide_coefficients(coefficients: &[Polynomial], divisor: &Polynomial) -> Vec<Polynomial> {
+    coefficients
+        .iter()
+        .map(|coefficient| {
+            coefficient
+                .divide_exact(divisor)
+                .expect("the divisor divides every coefficient")
+        })
+        .collect()
+}
+
+/// The primitive part of a univariate polynomial with polynomial coefficients, with the
+/// rational content of its coefficients removed too, so that they do not grow.
+fn primitive(coefficients: &[Polynomial]) -> Vec<Polynomial> {
+    if coefficients.is_empty() {
+        return vec![];
+    }
+
+    let divided = divide_coefficients(coefficients, &coefficient_gcd(coefficients));
+    let rational = content(
+        divided
+            .iter()
+            .flat_map(|coefficient| coefficient.terms.values()),
+        &divided.last().unwrap().leading_coefficient(),
+    );
+    let rational = inverse(&rational);
+
+    divided
+        .iter()
+        .map(|coefficient| coefficient.scale(&rational))
+        .collect()
+}
+
+/// The pseudo-remainder of univariate polynomials with polynomial coefficients.
+fn pseudo_rem(a: &[Polynomial], b: &[Polynomial]) -> Vec<Polynomial> {
+    let lead = b.last().unwrap();
+    let mut remainder = a.to_vec();
+    let mut power = (a.len() + 1).saturating_sub(b.len());
+
+    while remainder.len() >= b.len() {
+        let shift = remainder.len() - b.len();
+        let top = remainder.last().unwrap().clone();
+
+        for coefficient in &mut remainder {
+            *coefficient = &*coefficient * lead;
+        }
+        for (i, coefficient) in b.iter().enumerate() {
+            remainder[shift + i] = &remainder[shift + i] - &(&top * coefficient);
+        }
+
+        remainder.pop();
+        while remainder.last().is_some_and(Polynomial::is_zero) {
+            remainder.pop();
+        }
+        power -= 1;
+    }
+
+    let scale = lead.pow(power as u32);
+    remainder
+        .iter()
+        .map(|coefficient| coefficient * &scale)
+        .collect()
+}
+
+/// The content of rational coefficients, with the sign of `lead`.
+fn content<'a>(coefficients: impl Iterator<Item = &'a Num>, lead: &Num) -> Num {
+    let mut numerators = vec![];
+    let mut denominators = BigInt::one();
+
+    for coefficient in coefficients {
+        let (num, den) = coefficient.as_fraction().unwrap();
+        let gcd = algo::euclid_gcd(denominators.clone(), den.clone());
+        denominators = denominators / gcd * den;
+        numerators.push(num);
+    }
+
+    let content = Num::rational(algo::content(&numerators), denominators);
+    match lead.as_fraction() {
+        Some((num, _)) if num.is_negative() => content.checked_neg().unwrap(),
+        _ => content,
+    }
+}
+
+pub(super) fn neg(a: &Num) -> Num {
+    a.checked_neg().expect("rationals can be negated exactly")
+}
+
+pub(super) fn sub(a: &Num, b: &Num) -> Num {
+    add(a, &neg(b))
+}
+
+pub(super) fn div(a: &Num, b: &Num) -> Num {
+    a.checked_div(b).expect("rationals can be divided exactly")
+}
+
+fn inverse(a: &Num) -> Num {
+    div(&Num::One, a)
+}
+
+fn pow(base: &Num, exponent: usize) -> Num {
+    (0..exponent).fold(Num::One, |power, _| mul(&power, base))
+}
@@ -0,0 +1,500 @@
+//! Factoring polynomials over the rationals.
+
+use num_bigint::BigInt;
+use num_traits::{One, Signed, Zero};
+
+use crate::{algo, expr::Expr, num::Num, var::Var};
+
+use super::{
+    division::coefficient_gcd,
+    modular::{self, Modular},
+    rational::restore_atoms,
+    Polynomial, UnivariatePolynomial,
+};
+
+/// The number of suitable primes tried in [`zassenhaus`], of which the one giving the fewest
+/// factors is used.
+const PRIMES_TRIED: usize = 5;
+
+/// Multivariate polynomials whose Kronecker substitution has a higher degree than this are
+/// only factored into square-free parts.
+const KRONECKER_MAX_DEGREE: u32 = 200;
+
+/// The most factors of a Kronecker substitution that are recombined, as every subset of them
+/// may have to be tried.
+const KRONECKER_MAX_FACTORS: usize = 16;
+
+impl Expr {
+    /// Factors the expression into irreducible polynomials with integer coefficients, after
+    /// writing it as a fraction in lowest terms with [`Expr::cancel`]. The result is a product
+    /// of a rational constant and powers of the factors, where the factors of the denominator
+    /// have negative exponents, e.g. `2x^2 - 2` becomes `2(x - 1)(x + 1)`.
+    ///
+    /// See [`Polynomial::factor`] for how far polynomials in several variables are factored.
+    pub fn factor(&self) -> Expr {
+        let Some((numerator, denominator, atoms)) = self.lowest_terms() else {
+            return self.clone();
+        };
+
+        let (numerator_constant, numerator) = numerator.factor();
+        let (denominator_constant, denominator) = denominator.factor();
+        if numerator_constant.is_zero() {
+            return Expr::Const(Num::Zero);
+        }
+
+        let constant = numerator_constant
+            .checked_div(&denominator_constant)
+            .unwrap();
+        let mut factors = vec![];
+        if !constant.is_one() {
+            factors.push(Expr::Const(constant));
+        }
+        for (factor, multiplicity, sign) in numerator
+            .into_iter()
+            .map(|(factor, multiplicity)| (factor, multiplicity, 1))
+            .chain(
+                denominator
+                    .into_iter()
+                    .map(|(factor, multiplicity)| (factor, multiplicity, -1)),
+            )
+        {
+            let factor = factor.to_expr();
+            factors.push(match multiplicity as i64 * sign {
+                1 => factor,
+                exponent => factor.pow(Expr::Const(Num::from(exponent))),
+            });
+        }
+
+        let result = match factors.len() {
+            0 => Expr::Const(Num::One),
+            1 => factors.pop().unwrap(),
+            _ => Expr::Product(factors),
+        };
+        restore_atoms(&result, &atoms)
+    }
+}
+
+impl UnivariatePolynomial {
+    /// The derivative with respect to the variable.
+    pub fn derivative(&self) -> Self {
+        let coefficients = self
+            .coefficients
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(power, coefficient)| coefficient.checked_mul(&Num::from(power as i64)).unwrap())
+            .collect();
+        Self::from_coefficients(self.var.clone(), coefficients)
+    }
+
+    /// Splits the polynomial into monic, pairwise coprime polynomials without repeated factors,
+    /// along with their multiplicities in ascending order, using Yun's algorithm. The product
+    /// of their powers is the polynomial divided by its leading coefficient.
+    pub fn square_free_decomposition(&self) -> Vec<(Self, u32)> {
+        let mut parts = vec![];
+        if self.degree().unwrap_or(0) == 0 {
+            return parts;
+        }
+
+        let f = self.monic();
+        let derivative = f.derivative();
+        let gcd = f.gcd(&derivative);
+        let mut b = f.div_rem(&gcd).0;
+        let mut c = derivative.div_rem(&gcd).0;
+        let mut multiplicity = 1;
+
+        while b.degree() > Some(0) {
+            let d = &c - &b.derivative();
+            let part = b.gcd(&d);
+            b = b.div_rem(&part).0;
+            c = d.div_rem(&part).0;
+
+            if part.degree() > Some(0) {
+                parts.push((part, multiplicity));
+            }
+            multiplicity += 1;
+        }
+
+        parts
+    }
+
+    /// Factors the polynomial into irreducible polynomials over the rationals. Returns a
+    /// rational constant and the factors with their multiplicities, where the factors have
+    /// coprime integer coefficients and positive leading coefficients. The polynomial is the
+    /// constant times the product of the powers of the factors.
+    ///
+    /// Square-free parts are factored with the algorithm of Zassenhaus: they are factored
+    /// modulo a prime, the factors are lifted to a power of it, and products of them are tried
+    /// as factors over the integers.
+    pub fn factor(&self) -> (Num, Vec<(Self, u32)>) {
+        let mut factors = vec![];
+        for (part, multiplicity) in self.square_free_decomposition() {
+            for factor in zassenhaus(&to_integers(&part.primitive_part())) {
+                factors.push((self.with_integer_coefficients(&factor), multiplicity));
+            }
+        }
+        factors.sort_by_key(|(factor, _)| factor.degree());
+
+        let constant = factors
+            .iter()
+            .fold(self.leading_coefficient(), |constant, (factor, m)| {
+                let lead = factor
+                    .leading_coefficient()
+                    .checked_pow(&Num::from(*m as i64))
+                    .unwrap();
+                constant.checked_div(&lead).unwrap()
+            });
+        (constant, factors)
+    }
+
+    fn with_integer_coefficients(&self, coefficients: &[BigInt]) -> Self {
+        let coefficients = coefficients.iter().cloned().map(Num::from).collect();
+        Self::from_coefficients(self.var.clone(), coefficients)
+    }
+}
+
+impl Polynomial {
+    /// The partial derivative with respect to `var`.
+    pub fn derivative(&self, var: &Var) -> Self {
+        let Some(index) = self.vars.iter().position(|other| other == var) else {
+            return Self::zero_in(&self.vars);
+        };
+
+        let terms = self
+            .terms
+            .iter()
+            .filter(|(exponents, _)| exponents[index] > 0)
+            .map(|(exponents, coefficient)| {
+                let mut exponents = exponents.clone();
+                let power = Num::from(exponents[index] as i64);
+                exponents[index] -= 1;
+                (exponents, coefficient.checked_mul(&power).unwrap())
+            });
+        Self::from_exponents(self.vars.clone(), terms)
+    }
+
+    /// Factors the polynomial over the rationals, like [`UnivariatePolynomial::factor`].
+    ///
+    /// Polynomials in several variables are split into their contents with respect to each
+    /// variable and square-free parts. These are factored completely with Kronecker's
+    /// substitution, which maps them to polynomials in one variable, as long as that is of
+    /// degree at most 200 and has at most 16 factors. Otherwise the square-free parts are
+    /// returned as they are.
+    pub fn factor(&self) -> (Num, Vec<(Self, u32)>) {
+        let mut factors = vec![];
+        if !self.is_zero() {
+            factor_primitive(&self.primitive_part(), 1, &mut factors);
+        }
+        factors.sort_by_key(|(factor, _)| factor.total_degree());
+
+        let constant = factors
+            .iter()
+            .fold(self.leading_coefficient(), |constant, (factor, m)| {
+                let lead = factor
+                    .leading_coefficient()
+                    .checked_pow(&Num::from(*m as i64))
+                    .unwrap();
+                constant.checked_div(&lead).unwrap()
+            });
+        (constant, factors)
+    }
+
+    /// The square-free decomposition with respect to the `index`-th variable, see
+    /// [`UnivariatePolynomial::square_free_decomposition`], of a polynomial whose content with
+    /// respect to that variable is constant.
+    fn square_free_parts(&self, index: usize) -> Vec<(Self, u32)> {
+        let var = self.vars[index].clone();
+        let derivative = self.derivative(&var);
+        let gcd = self.gcd(&derivative);
+        let mut b = self.divide_exact(&gcd).unwrap();
+        let mut c = derivative.divide_exact(&gcd).unwrap();
+        let mut parts = vec![];
+        let mut multiplicity = 1;
+
+        while b.degree(&var) > Some(0) {
+            let d = &c - &b.derivative(&var);
+            let part = b.gcd(&d);
+            b = b.divide_exact(&part).unwrap();
+            c = d.divide_exact(&part).unwrap();
+
+            if part.degree(&var) > Some(0) {
+                parts.push((part, multiplicity));
+            }
+            multiplicity += 1;
+        }
+
+        parts
+    }
+}
+
+/// Adds the factors of a primitive polynomial, raised to `multiplicity`, to `factors`.
+fn factor_primitive(f: &Polynomial, multiplicity: u32, factors: &mut Vec<(Polynomial, u32)>) {
+    let depends_on = |index: usize| f.terms.keys().any(|exponents| exponents[index] > 0);
+    let Some(index) = (0..f.vars.len()).find(|index| depends_on(*index)) else {
+        return;
+    };
+
+    if let Some(univariate) = f.to_univariate() {
+        let (_, univariate_factors) = univariate.factor();
+        for (factor, m) in univariate_factors {
+            let factor = Polynomial::from(factor).in_vars(&f.vars);
+            factors.push((factor, m * multiplicity));
+        }
+        return;
+    }
+
+    // The content with respect to the first variable is a polynomial in the others.
+    let content = coefficient_gcd(&f.split(index)).primitive_part();
+    factor_primitive(&content, multiplicity, factors);
+    let f = f.divide_exact(&content).unwrap();
+
+    for (part, m) in f.square_free_parts(index) {
+        for factor in kronecker(&part.primitive_part()) {
+            factors.push((factor.primitive_part(), m * multiplicity));
+        }
+    }
+}
+
+/// Factors a primitive square-free polynomial in several variables with Kronecker's
+/// substitution, which replaces the `i`-th variable with `x^(D^i)` for a `D` greater than
+/// every degree, so that the polynomial can be recovered from the substituted one.
+fn kronecker(f: &Polynomial) -> Vec<Polynomial> {
+    let base = (0..f.vars.len())
+        .map(|index| f.terms.keys().map(|e| e[index]).max().unwrap_or(0))
+        .max()
+        .unwrap_or(0)
+        + 1;
+
+    let exponent = |exponents: &[u32]| -> Option<u32> {
+        exponents
+            .iter()
+            .rev()
+            .try_fold(0u32, |sum, e| sum.checked_mul(base)?.checked_add(*e))
+    };
+    let Some(degree) = f.terms.keys().map(|e| exponent(e)).max().flatten() else {
+        return vec![f.clone()];
+    };
+    if degree > KRONECKER_MAX_DEGREE || f.terms.keys().any(|e| exponent(e).is_none()) {
+        return vec![f.clone()];
+    }
+
+    let mut coefficients = vec![Num::Zero; degree as usize + 1];
+    for (exponents, coefficient) in &f.terms {
+        coefficients[exponent(exponents).unwrap() as usize] = coefficient.clone();
+    }
+    let image = UnivariatePolynomial::from_coefficients(f.vars[0].clone(), coefficients);
+
+    let mut pieces = vec![];
+    for (factor, multiplicity) in image.factor().1 {
+        pieces.extend(std::iter::repeat_n(factor, multiplicity as usize));
+    }
+    if pieces.len() > KRONECKER_MAX_FACTORS {
+        return vec![f.clone()];
+    }
+
+    // The inverse of the substitution, which is correct for factors of `f`.
+    let restore = |image: &UnivariatePolynomial| {
+        let terms = image
+            .coefficients
+            .iter()
+            .enumerate()
+            .map(|(power, coefficient)| {
+                let mut power = power as u32;
+                let exponents = (0..f.vars.len())
+                    .map(|_| {
+                        let digit = power % base;
+                        power /= base;
+                        digit
+                    })
+                    .collect();
+                (exponents, coefficient.clone())
+            });
+        Polynomial::from_exponents(f.vars.clone(), terms)
+    };
+
+    let mut factors = vec![];
+    let mut rest = f.clone();
+    recombine(&mut pieces, |subset| {
+        let product = subset.iter().fold(
+            UnivariatePolynomial::from_coefficients(f.vars[0].clone(), vec![Num::One]),
+            |product, piece| &product * *piece,
+        );
+        let candidate = restore(&product).primitive_part();
+        if candidate.total_degree() == Some(0) {
+            return false;
+        }
+
+        match rest.divide_exact(&candidate) {
+            Some(quotient) => {
+                factors.push(candidate);
+                rest = quotient;
+                true
+            }
+            None => false,
+        }
+    });
+    if rest.total_degree() > Some(0) {
+        factors.push(rest.primitive_part());
+    }
+
+    factors
+}
+
+/// Tries the products of subsets of `pieces` as factors, from the smallest subsets up, where
+/// `try_factor` returns whether a product is one. The pieces of each factor found are removed.
+///
+/// Once subsets of more than half of the remaining pieces would be needed, their product is
+/// irreducible, as any factor of it or its cofactor is the product of at most half of them.
+fn recombine<T>(pieces: &mut Vec<T>, mut try_factor: impl FnMut(&[&T]) -> bool) {
+    let mut size = 1;
+
+    'sizes: while 2 * size <= pieces.len() {
+        for subset in combinations(pieces.len(), size) {
+            let chosen: Vec<&T> = subset.iter().map(|i| &pieces[*i]).collect();
+            if try_factor(&chosen) {
+                for i in subset.into_iter().rev() {
+                    pieces.remove(i);
+                }
+                continue 'sizes;
+            }
+        }
+        size += 1;
+    }
+}
+
+/// The subsets of `0..n` with `k` elements, as ascending indices in lexicographic order.
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    let mut subsets = vec![];
+    let mut subset: Vec<usize> = (0..k).collect();
+    if k > n {
+        return subsets;
+    }
+
+    loop {
+        subsets.push(subset.clone());
+
+        // Advance the last index which is not yet at its highest value.
+        let Some(i) = (0..k).rev().find(|i| subset[*i] < n - k + i) else {
+            return subsets;
+        };
+        subset[i] += 1;
+        for j in i + 1..k {
+            subset[j] = subset[j - 1] + 1;
+        }
+    }
+}
+
+/// Factors a primitive square-free polynomial with integer coefficients and a positive leading
+/// coefficient into irreducible ones, with the algorithm of Zassenhaus.
+fn zassenhaus(f: &[BigInt]) -> Vec<Vec<BigInt>> {
+    if f.len() <= 2 {
+        return vec![f.to_vec()];
+    }
+
+    let lead = f.last().unwrap();
+    let mut best: Option<(Modular, Vec<modular::Poly>)> = None;
+    let mut tried = 0;
+
+    for p in (3..).step_by(2).filter(|p| modular::is_prime(*p)) {
+        let modular = Modular { p };
+        if modular.reduce(lead) == 0 {
+            continue;
+        }
+        // The factors modulo p must be distinct for lifting, so f must stay square-free.
+        let reduced = modular.reduce_poly(f);
+        if modular.gcd(&reduced, &modular.derivative(&reduced)).len() > 1 {
+            continue;
+        }
+
+        let factors = modular.factor(&modular.monic(&reduced));
+        if factors.len() == 1 {
+            return vec![f.to_vec()];
+        }
+        if best
+            .as_ref()
+            .is_none_or(|(_, best)| factors.len() < best.len())
+        {
+            best = Some((modular, factors));
+        }
+
+        tried += 1;
+        if tried == PRIMES_TRIED {
+            break;
+        }
+    }
+    let (modular, factors) = best.unwrap();
+
+    // Mignotte's bound on the coefficients of a factor, times the leading coefficient, which
+    // the lifted factors are multiplied by. The modulus must exceed twice it, so that the
+    // coefficients are told apart from their negatives.
+    let norm = f.iter().map(|c| c * c).sum::<BigInt>().sqrt() + 1;
+    let bound = lead * norm * (BigInt::one() << (f.len() - 1)) * 2;
+    let mut k = 1;
+    let mut modulus = BigInt::from(modular.p);
+    while modulus <= bound {
+        modulus *= modular.p;
+        k += 1;
+    }
+
+    let mut pieces = modular::hensel_lift(modular, f, &factors, k);
+    let mut factors = vec![];
+    let mut rest = f.to_vec();
+    recombine(&mut pieces, |subset| {
+        let product = modular::product_mod(subset, rest.last().unwrap(), &modulus);
+        let candidate = algo::primitive_part(&modular::symmetric(&product, &modulus));
+
+        match divide_integers(&rest, &candidate) {
+            Some(quotient) => {
+                factors.push(positive(candidate));
+                rest = quotient;
+                true
+            }
+            None => false,
+        }
+    });
+    factors.push(positive(rest));
+
+    factors
+}
+
+/// The quotient of polynomials with integer coefficients, if it has integer coefficients and
+/// there is no remainder.
+fn divide_integers(a: &[BigInt], b: &[BigInt]) -> Option<Vec<BigInt>> {
+    let lead = b.last()?;
+    let mut remainder = a.to_vec();
+    let mut quotient = vec![BigInt::zero(); (a.len() + 1).checked_sub(b.len())?];
+
+    while remainder.len() >= b.len() {
+        let shift = remainder.len() - b.len();
+        let top = remainder.last().unwrap();
+        if !(top % lead).is_zero() {
+            return None;
+        }
+
+        let factor = top / lead;
+        for (i, c) in b.iter().enumerate() {
+            remainder[shift + i] -= &factor * c;
+        }
+        quotient[shift] = factor;
+        modular::trim(&mut remainder);
+    }
+
+    remainder.is_empty().then_some(quotient)
+}
+
+/// The polynomial, negated if its leading coefficient is negative.
+fn positive(f: Vec<BigInt>) -> Vec<BigInt> {
+    if f.last().is_some_and(Signed::is_negative) {
+        f.into_iter().map(|c| -c).collect()
+    } else {
+        f
+    }
+}
+
+/// The coefficients of a polynomial with integer coefficients.
+fn to_integers(f: &UnivariatePolynomial) -> Vec<BigInt> {
+    f.coefficients
+        .iter()
+        .map(|coefficient| coefficient.as_fraction().unwrap().0)
+        .collect()
+}
@@ -0,0 +1,918 @@
+//! Polynomials with rational coefficients, for exact algebra on expressions that are polynomial.
+//!
+//! [`Polynomial`] is sparse and has any number of variables, while [`UnivariatePolynomial`] is
+//! dense and has a single one. Both keep their coefficients as rational [`Num`]s.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::ops::{Add, Mul, Neg, Sub};
+use std::sync::Arc;
+
+use num_complex::Complex64;
+
+use crate::{
+    expr::{EvalError, EvalResult, Expr},
+    num::Num,
+    var::{Var, VarMap},
+};
+
+mod division;
+mod factor;
+mod modular;
+mod rational;
+mod roots;
+
+/// The reason an expression could not be converted into a polynomial.
+#[derive(Debug, Clone)]
+pub enum PolynomialError {
+    /// The expression is not a polynomial, e.g. it divides by a variable, raises one to a
+    /// non-integer power or applies a function to one.
+    NotPolynomial { expr: Expr },
+    /// A coefficient is not rational, e.g. `\pi` or `\sqrt{2}`.
+    NotRational { num: Num },
+    /// A variable is not one of the variables of the polynomial.
+    UnknownVar { name: String },
+    /// An exponent of the polynomial does not fit in a `u32`, e.g. in `(x^65536)^65536`.
+    ExponentTooLarge { expr: Expr },
+}
+
+impl Display for PolynomialError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotPolynomial { expr } => write!(f, "{expr} is not a polynomial."),
+            Self::NotRational { num } => write!(f, "The coefficient {num} is not rational."),
+            Self::UnknownVar { name } => {
+                write!(f, "'{name}' is not one of the variables of the polynomial.")
+            }
+            Self::ExponentTooLarge { expr } => {
+                write!(f, "The exponents of {expr} are too large.")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolynomialError {}
+
+/// The reason the roots of a polynomial could not be found exactly.
+#[derive(Debug, Clone)]
+pub enum SolveError {
+    /// The expression to solve is not a polynomial in the variable.
+    Polynomial(PolynomialError),
+    /// The polynomial is zero, so every value is a root.
+    ZeroPolynomial,
+    /// An irreducible factor of the polynomial has a degree above 4, so its roots have no
+    /// general formula.
+    DegreeTooHigh { degree: u32 },
+    /// A root did not give zero when substituted back into the polynomial.
+    Unverified { root: Expr },
+}
+
+impl Display for SolveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Polynomial(error) => write!(f, "{error}"),
+            Self::ZeroPolynomial => write!(f, "Every value is a root of the zero polynomial."),
+            Self::DegreeTooHigh { degree } => {
+                write!(
+                    f,
+                    "There is no formula for the roots of a polynomial of degree {degree}."
+                )
+            }
+            Self::Unverified { root } => {
+                write!(f, "{root} is not a root of the polynomial.")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+impl From<PolynomialError> for SolveError {
+    fn from(error: PolynomialError) -> Self {
+        Self::Polynomial(error)
+    }
+}
+
+impl Expr {
+    /// Converts the expression into a polynomial in `vars`, whose order decides which term is
+    /// leading (see [`Polynomial::leading_term`]).
+    ///
+    /// Constants must be rational, and variables may only be raised to non-negative integer
+    /// powers. Powers of constants are evaluated exactly, so `2^-1 x` and `4^(1/2) x` work.
+    pub fn to_polynomial(&self, vars: &[&Var]) -> Result<Polynomial, PolynomialError> {
+        let vars: Vec<Var> = vars.iter().map(|var| (*var).clone()).collect();
+        self.polynomial_in(&vars)
+    }
+
+    fn polynomial_in(&self, vars: &[Var]) -> Result<Polynomial, PolynomialError> {
+        match self {
+            Expr::Sum(terms) => {
+                let mut sum = Polynomial::zero_in(vars);
+                for term in terms {
+                    sum = &sum + &term.polynomial_in(vars)?;
+                }
+                Ok(sum)
+            }
+
+            Expr::Product(factors) => {
+                let mut product = Polynomial::constant_in(vars, Num::One);
+                for factor in factors {
+                    product = product
+                        .checked_mul(&factor.polynomial_in(vars)?)
+                        .ok_or_else(|| PolynomialError::ExponentTooLarge { expr: self.clone() })?;
+                }
+                Ok(product)
+            }
+
+            Expr::Pow(base, exponent) => {
+                let base = base.polynomial_in(vars)?;
+                let not_polynomial = || PolynomialError::NotPolynomial { expr: self.clone() };
+
+                let Expr::Const(exponent) = exponent.as_ref() else {
+                    return Err(not_polynomial());
+                };
+                if let Some(n) = exponent.as_integer().filter(|n| *n >= 0) {
+                    return base
+                        .checked_pow(n as u32)
+                        .ok_or_else(|| PolynomialError::ExponentTooLarge { expr: self.clone() });
+                }
+
+                // Other powers of constants, e.g. 2^-1 or 4^(1/2), may still be rational.
+                match base
+                    .as_constant()
+                    .and_then(|base| base.checked_pow(exponent))
+                {
+                    Some(power) if is_rational(&power) => Ok(Polynomial::constant_in(vars, power)),
+                    Some(power) => Err(PolynomialError::NotRational { num: power }),
+                    None => Err(not_polynomial()),
+                }
+            }
+
+            Expr::Var(var) => {
+                let index = vars.iter().position(|other| other == var.as_ref()).ok_or(
+                    PolynomialError::UnknownVar {
+                        name: var.get_name(),
+                    },
+                )?;
+                let mut exponents = vec![0; vars.len()];
+                exponents[index] = 1;
+                Ok(Polynomial::from_exponents(
+                    vars.to_vec(),
+                    [(exponents, Num::One)],
+                ))
+            }
+
+            Expr::Const(num) if is_rational(num) => Ok(Polynomial::constant_in(vars, num.clone())),
+
+            Expr::Const(num) => Err(PolynomialError::NotRational { num: num.clone() }),
+
+            Expr::Function(..) => Err(PolynomialError::NotPolynomial { expr: self.clone() }),
+        }
+    }
+}
+
+/// A sparse polynomial in any number of variables.
+///
+/// Polynomials in different variables can be combined, and the result is in the variables of
+/// both. Likewise, equality compares the terms once both are written in the same variables.
+#[derive(Debug, Clone)]
+pub struct Polynomial {
+    vars: Vec<Var>,
+    /// The nonzero coefficients, keyed by the exponent of each variable. The map is ordered
+    /// lexicographically, so the leading term is the last one.
+    terms: BTreeMap<Vec<u32>, Num>,
+}
+
+impl Polynomial {
+    /// The zero polynomial.
+    pub fn zero(vars: &[&Var]) -> Self {
+        Self::zero_in(&owned(vars))
+    }
+
+    /// The constant polynomial `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is not rational.
+    pub fn constant(vars: &[&Var], value: Num) -> Self {
+        Self::constant_in(&owned(vars), value)
+    }
+
+    /// The polynomial with the given `(exponents, coefficient)` terms, where `exponents[i]` is
+    /// the exponent of `vars[i]`. Terms with the same exponents are added together.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a coefficient is not rational or a term has the wrong number of exponents.
+    pub fn from_terms(vars: &[&Var], terms: impl IntoIterator<Item = (Vec<u32>, Num)>) -> Self {
+        Self::from_exponents(owned(vars), terms)
+    }
+
+    fn zero_in(vars: &[Var]) -> Self {
+        Self {
+            vars: vars.to_vec(),
+            terms: BTreeMap::new(),
+        }
+    }
+
+    fn constant_in(vars: &[Var], value: Num) -> Self {
+        Self::from_exponents(vars.to_vec(), [(vec![0; vars.len()], value)])
+    }
+
+    fn from_exponents(vars: Vec<Var>, terms: impl IntoIterator<Item = (Vec<u32>, Num)>) -> Self {
+        let mut polynomial = Self {
+            vars,
+            terms: BTreeMap::new(),
+        };
+        for (exponents, coefficient) in terms {
+            assert_eq!(
+                exponents.len(),
+                polynomial.vars.len(),
+                "expected an exponent for each variable"
+            );
+            assert!(is_rational(&coefficient), "coefficients must be rational");
+            polynomial.add_term(exponents, &coefficient);
+        }
+        polynomial
+    }
+
+    /// Adds `coefficient` to the term with the given exponents, removing it if it cancels.
+    fn add_term(&mut self, exponents: Vec<u32>, coefficient: &Num) {
+        if coefficient.is_zero() {
+            return;
+        }
+
+        match self.terms.get_mut(&exponents) {
+            Some(existing) => {
+                *existing = add(existing, coefficient);
+                if existing.is_zero() {
+                    self.terms.remove(&exponents);
+                }
+            }
+            None => {
+                self.terms.insert(exponents, coefficient.clone());
+            }
+        }
+    }
+
+    pub fn vars(&self) -> &[Var] {
+        &self.vars
+    }
+
+    /// The nonzero terms as `(exponents, coefficient)`, in ascending order.
+    pub fn terms(&self) -> impl DoubleEndedIterator<Item = (&[u32], &Num)> {
+        self.terms
+            .iter()
+            .map(|(exponents, coefficient)| (exponents.as_slice(), coefficient))
+    }
+
+    /// The number of nonzero terms.
+    pub fn term_count(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// The coefficient of the term with the given exponents.
+    pub fn coefficient(&self, exponents: &[u32]) -> Num {
+        self.terms.get(exponents).cloned().unwrap_or(Num::Zero)
+    }
+
+    /// The value of the polynomial, if it does not depend on any variable.
+    pub fn as_constant(&self) -> Option<Num> {
+        match self.terms.iter().next_back() {
+            None => Some(Num::Zero),
+            Some((exponents, coefficient)) if exponents.iter().all(|e| *e == 0) => {
+                Some(coefficient.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// The highest exponent of `var`, or `None` for the zero polynomial.
+    pub fn degree(&self, var: &Var) -> Option<u32> {
+        let index = self.vars.iter().position(|other| other == var);
+        self.terms
+            .keys()
+            .map(|exponents| index.map_or(0, |index| exponents[index]))
+            .max()
+    }
+
+    /// The highest sum of the exponents of a term, or `None` for the zero polynomial.
+    pub fn total_degree(&self) -> Option<u32> {
+        self.terms
+            .keys()
+            .map(|exponents| exponents.iter().sum())
+            .max()
+    }
+
+    /// The greatest term in the lexicographic order of the exponents, where the first variable
+    /// is the most significant, or `None` for the zero polynomial.
+    pub fn leading_term(&self) -> Option<(&[u32], &Num)> {
+        self.terms().next_back()
+    }
+
+    /// The coefficient of the leading term, which is zero for the zero polynomial.
+    pub fn leading_coefficient(&self) -> Num {
+        self.leading_term()
+            .map_or(Num::Zero, |(_, coefficient)| coefficient.clone())
+    }
+
+    /// Multiplies every coefficient by `factor`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is not rational.
+    pub fn scale(&self, factor: &Num) -> Self {
+        assert!(is_rational(factor), "coefficients must be rational");
+        if factor.is_zero() {
+            return Self::zero_in(&self.vars);
+        }
+
+        Self {
+            vars: self.vars.clone(),
+            terms: self
+                .terms
+                .iter()
+                .map(|(exponents, coefficient)| (exponents.clone(), mul(coefficient, factor)))
+                .collect(),
+        }
+    }
+
+    /// Raises the polynomial to the power of `exponent`, by repeated squaring.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an exponent of the result does not fit in a `u32`, see
+    /// [`Polynomial::checked_pow`].
+    pub fn pow(&self, exponent: u32) -> Self {
+        self.checked_pow(exponent)
+            .expect("the exponents of the power fit in a u32")
+    }
+
+    /// Raises the polynomial to the power of `exponent`, or `None` if an exponent of the result
+    /// does not fit in a `u32`.
+    pub fn checked_pow(&self, exponent: u32) -> Option<Self> {
+        let mut result = Self::constant_in(&self.vars, Num::One);
+        let mut base = self.clone();
+        let mut exponent = exponent;
+
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                result = result.checked_mul(&base)?;
+            }
+            exponent /= 2;
+            if exponent > 0 {
+                base = base.checked_mul(&base)?;
+            }
+        }
+
+        Some(result)
+    }
+
+    /// Multiplies the polynomials, or `None` if an exponent of the product does not fit in a
+    /// `u32`.
+    pub fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        if self.vars != rhs.vars {
+            let (lhs, rhs) = self.unify(rhs);
+            return lhs.checked_mul(&rhs);
+        }
+
+        let mut product = Polynomial::zero_in(&self.vars);
+        for (exponents_a, coefficient_a) in &self.terms {
+            for (exponents_b, coefficient_b) in &rhs.terms {
+                let exponents = exponents_a
+                    .iter()
+                    .zip(exponents_b)
+                    .map(|(a, b)| a.checked_add(*b))
+                    .collect::<Option<_>>()?;
+                product.add_term(exponents, &mul(coefficient_a, coefficient_b));
+            }
+        }
+        Some(product)
+    }
+
+    /// Evaluates the polynomial given the values of its variables. Only the variables it
+    /// depends on need a value.
+    pub fn eval(&self, var_values: &VarMap<Complex64>) -> EvalResult {
+        let mut sum = Complex64::new(0.0, 0.0);
+
+        for (exponents, coefficient) in &self.terms {
+            let mut term = coefficient.eval_float()?;
+            for (var, exponent) in self.vars.iter().zip(exponents) {
+                if *exponent > 0 {
+                    let value = var_values.get(var).ok_or(EvalError::VarMissing {
+                        name: var.get_name(),
+                    })?;
+                    term *= value.powu(*exponent);
+                }
+            }
+            sum += term;
+        }
+
+        Ok(sum)
+    }
+
+    /// Evaluates the polynomial exactly, returning `None` if a variable it depends on has no
+    /// value or the result cannot be represented as a `Num`.
+    pub fn eval_exact(&self, var_values: &VarMap<Num>) -> Option<Num> {
+        let mut sum = Num::Zero;
+
+        for (exponents, coefficient) in &self.terms {
+            let mut term = coefficient.clone();
+            for (var, exponent) in self.vars.iter().zip(exponents) {
+                if *exponent > 0 {
+                    let power = var_values
+                        .get(var)?
+                        .checked_pow(&Num::from(*exponent as i64))?;
+                    term = term.checked_mul(&power)?;
+                }
+            }
+            sum = sum.checked_add(&term)?;
+        }
+
+        Some(sum)
+    }
+
+    /// The polynomial as a univariate one, if it depends on at most one variable. A constant
+    /// polynomial is in its only variable, and `None` if it has several.
+    pub fn to_univariate(&self) -> Option<UnivariatePolynomial> {
+        let mut used = (0..self.vars.len()).filter(|i| self.terms.keys().any(|e| e[*i] > 0));
+        let index = match (used.next(), used.next()) {
+            (Some(index), None) => index,
+            (None, None) if self.vars.len() == 1 => 0,
+            _ => return None,
+        };
+
+        let degree = self.degree(&self.vars[index]).unwrap_or(0) as usize;
+        let mut coefficients = vec![Num::Zero; degree + 1];
+        for (exponents, coefficient) in &self.terms {
+            coefficients[exponents[index] as usize] = coefficient.clone();
+        }
+
+        Some(UnivariatePolynomial::from_coefficients(
+            self.vars[index].clone(),
+            coefficients,
+        ))
+    }
+
+    /// Converts the polynomial back into an expression, with its terms in descending order.
+    pub fn to_expr(&self) -> Expr {
+        let terms: Vec<Expr> = self
+            .terms
+            .iter()
+            .rev()
+            .map(|(exponents, coefficient)| {
+                let powers = self
+                    .vars
+                    .iter()
+                    .zip(exponents)
+                    .filter(|(_, exponent)| **exponent > 0)
+                    .map(|(var, exponent)| power_expr(var, *exponent));
+                term_expr(coefficient, powers.collect())
+            })
+            .collect();
+
+        sum_expr(terms)
+    }
+
+    /// Rewrites the polynomial in `vars`, which must contain all of its variables.
+    fn in_vars(&self, vars: &[Var]) -> Self {
+        if self.vars == vars {
+            return self.clone();
+        }
+
+        let indices: Vec<usize> = self
+            .vars
+            .iter()
+            .map(|var| vars.iter().position(|other| other == var).unwrap())
+            .collect();
+        let terms = self.terms.iter().map(|(exponents, coefficient)| {
+            let mut new = vec![0; vars.len()];
+            for (exponent, index) in exponents.iter().zip(&indices) {
+                new[*index] = *exponent;
+            }
+            (new, coefficient.clone())
+        });
+
+        Self::from_exponents(vars.to_vec(), terms)
+    }
+
+    /// Rewrites both polynomials in the variables of `self` followed by any new ones of `other`.
+    fn unify(&self, other: &Self) -> (Self, Self) {
+        let mut vars = self.vars.clone();
+        for var in &other.vars {
+            if !vars.contains(var) {
+                vars.push(var.clone());
+            }
+        }
+
+        (self.in_vars(&vars), other.in_vars(&vars))
+    }
+}
+
+impl Add<&Polynomial> for &Polynomial {
+    type Output = Polynomial;
+
+    fn add(self, rhs: &Polynomial) -> Self::Output {
+        if self.vars != rhs.vars {
+            let (lhs, rhs) = self.unify(rhs);
+            return &lhs + &rhs;
+        }
+
+        let mut sum = self.clone();
+        for (exponents, coefficient) in &rhs.terms {
+            sum.add_term(exponents.clone(), coefficient);
+        }
+        sum
+    }
+}
+
+impl Sub<&Polynomial> for &Polynomial {
+    type Output = Polynomial;
+
+    fn sub(self, rhs: &Polynomial) -> Self::Output {
+        self + &-rhs
+    }
+}
+
+impl Mul<&Polynomial> for &Polynomial {
+    type Output = Polynomial;
+
+    /// Multiplies the polynomials.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an exponent of the product does not fit in a `u32`, see
+    /// [`Polynomial::checked_mul`].
+    fn mul(self, rhs: &Polynomial) -> Self::Output {
+        self.checked_mul(rhs)
+            .expect("the exponents of the product fit in a u32")
+    }
+}
+
+impl Neg for &Polynomial {
+    type Output = Polynomial;
+
+    fn neg(self) -> Self::Output {
+        self.scale(&Num::from(-1))
+    }
+}
+
+impl Add for Polynomial {
+    type Output = Polynomial;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        &self + &rhs
+    }
+}
+
+impl Sub for Polynomial {
+    type Output = Polynomial;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        &self - &rhs
+    }
+}
+
+impl Mul for Polynomial {
+    type Output = Polynomial;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        &self * &rhs
+    }
+}
+
+impl Neg for Polynomial {
+    type Output = Polynomial;
+
+    fn neg(self) -> Self::Output {
+        -&self
+    }
+}
+
+impl PartialEq for Polynomial {
+    fn eq(&self, other: &Self) -> bool {
+        if self.vars == other.vars {
+            return self.terms == other.terms;
+        }
+
+        let (a, b) = self.unify(other);
+        a.terms == b.terms
+    }
+}
+
+impl Display for Polynomial {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_expr())
+    }
+}
+
+/// A dense polynomial in a single variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnivariatePolynomial {
+    var: Var,
+    /// The coefficients from the constant term up, without trailing zeros.
+    coefficients: Vec<Num>,
+}
+
+impl UnivariatePolynomial {
+    /// The polynomial `coefficients[0] + coefficients[1] var + coefficients[2] var^2 + ...`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a coefficient is not rational.
+    pub fn new(var: &Var, coefficients: Vec<Num>) -> Self {
+        Self::from_coefficients(var.clone(), coefficients)
+    }
+
+    fn from_coefficients(var: Var, coefficients: Vec<Num>) -> Self {
+        assert!(
+            coefficients.iter().all(is_rational),
+            "coefficients must be rational"
+        );
+
+        let mut polynomial = Self { var, coefficients };
+        polynomial.trim();
+        polynomial
+    }
+
+    /// Removes the zero coefficients at the top.
+    fn trim(&mut self) {
+        while self.coefficients.last().is_some_and(Num::is_zero) {
+            self.coefficients.pop();
+        }
+    }
+
+    pub fn var(&self) -> &Var {
+        &self.var
+    }
+
+    /// The coefficients from the constant term up, which is empty for the zero polynomial.
+    pub fn coefficients(&self) -> &[Num] {
+        &self.coefficients
+    }
+
+    /// The coefficient of `var^power`.
+    pub fn coefficient(&self, power: u32) -> Num {
+        self.coefficients
+            .get(power as usize)
+            .cloned()
+            .unwrap_or(Num::Zero)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+
+    /// The highest power with a nonzero coefficient, or `None` for the zero polynomial.
+    pub fn degree(&self) -> Option<u32> {
+        (self.coefficients.len() as u32).checked_sub(1)
+    }
+
+    /// The coefficient of the highest power, which is zero for the zero polynomial.
+    pub fn leading_coefficient(&self) -> Num {
+        self.coefficients.last().cloned().unwrap_or(Num::Zero)
+    }
+
+    /// Multiplies every coefficient by `factor`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is not rational.
+    pub fn scale(&self, factor: &Num) -> Self {
+        let coefficients = self
+            .coefficients
+            .iter()
+            .map(|coefficient| mul(coefficient, factor))
+            .collect();
+        Self::from_coefficients(self.var.clone(), coefficients)
+    }
+
+    /// Raises the polynomial to the power of `exponent`, by repeated squaring.
+    pub fn pow(&self, exponent: u32) -> Self {
+        let mut result = Self::from_coefficients(self.var.clone(), vec![Num::One]);
+        let mut base = self.clone();
+        let mut exponent = exponent;
+
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                result = &result * &base;
+            }
+            exponent /= 2;
+            if exponent > 0 {
+                base = &base * &base;
+            }
+        }
+
+        result
+    }
+
+    /// Evaluates the polynomial at `x` with Horner's method.
+    pub fn eval(&self, x: Complex64) -> EvalResult {
+        let mut value = Complex64::new(0.0, 0.0);
+        for coefficient in self.coefficients.iter().rev() {
+            value = value * x + coefficient.eval_float()?;
+        }
+        Ok(value)
+    }
+
+    /// Evaluates the polynomial exactly at `x`, returning `None` if the result cannot be
+    /// represented as a `Num`.
+    pub fn eval_exact(&self, x: &Num) -> Option<Num> {
+        let mut value = Num::Zero;
+        for coefficient in self.coefficients.iter().rev() {
+            value = value.checked_mul(x)?.checked_add(coefficient)?;
+        }
+        Some(value)
+    }
+
+    /// Converts the polynomial back into an expression, from the highest power down.
+    pub fn to_expr(&self) -> Expr {
+        let terms: Vec<Expr> = self
+            .coefficients
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, coefficient)| !coefficient.is_zero())
+            .map(|(power, coefficient)| {
+                let powers = (power > 0).then(|| power_expr(&self.var, power as u32));
+                term_expr(coefficient, powers.into_iter().collect())
+            })
+            .collect();
+
+        sum_expr(terms)
+    }
+}
+
+impl Add<&UnivariatePolynomial> for &UnivariatePolynomial {
+    type Output = UnivariatePolynomial;
+
+    /// # Panics
+    ///
+    /// Panics if the polynomials are in different variables.
+    fn add(self, rhs: &UnivariatePolynomial) -> Self::Output {
+        assert_same_var(self, rhs);
+
+        let len = self.coefficients.len().max(rhs.coefficients.len());
+        let coefficients = (0..len as u32)
+            .map(|power| add(&self.coefficient(power), &rhs.coefficient(power)))
+            .collect();
+        UnivariatePolynomial::from_coefficients(self.var.clone(), coefficients)
+    }
+}
+
+impl Sub<&UnivariatePolynomial> for &UnivariatePolynomial {
+    type Output = UnivariatePolynomial;
+
+    /// # Panics
+    ///
+    /// Panics if the polynomials are in different variables.
+    fn sub(self, rhs: &UnivariatePolynomial) -> Self::Output {
+        self + &-rhs
+    }
+}
+
+impl Mul<&UnivariatePolynomial> for &UnivariatePolynomial {
+    type Output = UnivariatePolynomial;
+
+    /// # Panics
+    ///
+    /// Panics if the polynomials are in different variables.
+    fn mul(self, rhs: &UnivariatePolynomial) -> Self::Output {
+        assert_same_var(self, rhs);
+        if self.is_zero() || rhs.is_zero() {
+            return UnivariatePolynomial::from_coefficients(self.var.clone(), vec![]);
+        }
+
+        let mut coefficients =
+            vec![Num::Zero; self.coefficients.len() + rhs.coefficients.len() - 1];
+        for (i, a) in self.coefficients.iter().enumerate() {
+            if a.is_zero() {
+                continue;
+            }
+            for (j, b) in rhs.coefficients.iter().enumerate() {
+                coefficients[i + j] = add(&coefficients[i + j], &mul(a, b));
+            }
+        }
+        UnivariatePolynomial::from_coefficients(self.var.clone(), coefficients)
+    }
+}
+
+impl Neg for &UnivariatePolynomial {
+    type Output = UnivariatePolynomial;
+
+    fn neg(self) -> Self::Output {
+        self.scale(&Num::from(-1))
+    }
+}
+
+impl Add for UnivariatePolynomial {
+    type Output = UnivariatePolynomial;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        &self + &rhs
+    }
+}
+
+impl Sub for UnivariatePolynomial {
+    type Output = UnivariatePolynomial;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        &self - &rhs
+    }
+}
+
+impl Mul for UnivariatePolynomial {
+    type Output = UnivariatePolynomial;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        &self * &rhs
+    }
+}
+
+impl Neg for UnivariatePolynomial {
+    type Output = UnivariatePolynomial;
+
+    fn neg(self) -> Self::Output {
+        -&self
+    }
+}
+
+impl From<UnivariatePolynomial> for Polynomial {
+    fn from(polynomial: UnivariatePolynomial) -> Self {
+        let terms = polynomial
+            .coefficients
+            .into_iter()
+            .enumerate()
+            .map(|(power, coefficient)| (vec![power as u32], coefficient));
+        Polynomial::from_exponents(vec![polynomial.var], terms)
+    }
+}
+
+impl Display for UnivariatePolynomial {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_expr())
+    }
+}
+
+fn assert_same_var(a: &UnivariatePolynomial, b: &UnivariatePolynomial) {
+    assert_eq!(
+        a.var, b.var,
+        "polynomials must be in the same variable to be combined"
+    );
+}
+
+fn owned(vars: &[&Var]) -> Vec<Var> {
+    vars.iter().map(|var| (*var).clone()).collect()
+}
+
+fn is_rational(num: &Num) -> bool {
+    num.as_fraction().is_some()
+}
+
+/// Adds two rational coefficients, which is always exact.
+fn add(a: &Num, b: &Num) -> Num {
+    a.checked_add(b).expect("rationals can be added exactly")
+}
+
+/// Multiplies two rational coefficients, which is always exact.
+fn mul(a: &Num, b: &Num) -> Num {
+    a.checked_mul(b)
+        .expect("rationals can be multiplied exactly")
+}
+
+fn power_expr(var: &Var, exponent: u32) -> Expr {
+    let var = Expr::Var(Arc::new(var.clone()));
+    if exponent == 1 {
+        var
+    } else {
+        var.pow(Expr::Const(Num::from(exponent as i64)))
+    }
+}
+
+/// The term `coefficient * powers`, leaving out a coefficient of one.
+fn term_expr(coefficient: &Num, mut powers: Vec<Expr>) -> Expr {
+    if powers.is_empty() {
+        return Expr::Const(coefficient.clone());
+    }
+    if !coefficient.is_one() {
+        powers.insert(0, Expr::Const(coefficient.clone()));
+    }
+
+    if powers.len() == 1 {
+        powers.pop().unwrap()
+    } else {
+        Expr::Product(powers)
+    }
+}
+
+fn sum_expr(mut terms: Vec<Expr>) -> Expr {
+    match terms.len() {
+        0 => Expr::Const(Num::Zero),
+        1 => terms.pop().unwrap(),
+        _ => Expr::Sum(terms),
+    }
+}
@@ -0,0 +1,395 @@
+//! Polynomials over the integers modulo a prime, and lifting factorizations modulo a prime to
+//! factorizations modulo a power of it. Used to factor polynomials over the rationals.
+//!
+//! Polynomials are dense vectors of coefficients from the constant term up, without trailing
+//! zeros, so the zero polynomial is empty.
+
+use num_bigint::{BigInt, BigUint};
+use num_integer::Integer;
+use num_traits::{ToPrimitive, Zero};
+
+/// A polynomial modulo a prime.
+pub(super) type Poly = Vec<u64>;
+
+/// Arithmetic modulo the prime `p`, which is small enough for products of two residues to fit
+/// in a `u64`.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Modular {
+    pub p: u64,
+}
+
+impl Modular {
+    pub fn reduce(&self, n: &BigInt) -> u64 {
+        n.mod_floor(&BigInt::from(self.p)).to_u64().unwrap()
+    }
+
+    pub fn reduce_poly(&self, f: &[BigInt]) -> Poly {
+        let mut reduced = f.iter().map(|c| self.reduce(c)).collect();
+        trim(&mut reduced);
+        reduced
+    }
+
+    fn mul_mod(&self, a: u64, b: u64) -> u64 {
+        a * b % self.p
+    }
+
+    fn pow_mod(&self, base: u64, exponent: u64) -> u64 {
+        let (mut result, mut base, mut exponent) = (1, base % self.p, exponent);
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                result = self.mul_mod(result, base);
+            }
+            base = self.mul_mod(base, base);
+            exponent /= 2;
+        }
+        result
+    }
+
+    /// The inverse of a nonzero residue, by Fermat's little theorem.
+    pub fn inverse(&self, a: u64) -> u64 {
+        self.pow_mod(a, self.p - 2)
+    }
+
+    pub fn add(&self, a: &[u64], b: &[u64]) -> Poly {
+        let mut sum: Poly = (0..a.len().max(b.len()))
+            .map(|i| (a.get(i).unwrap_or(&0) + b.get(i).unwrap_or(&0)) % self.p)
+            .collect();
+        trim(&mut sum);
+        sum
+    }
+
+    pub fn sub(&self, a: &[u64], b: &[u64]) -> Poly {
+        let mut difference: Poly = (0..a.len().max(b.len()))
+            .map(|i| (a.get(i).unwrap_or(&0) + self.p - b.get(i).unwrap_or(&0)) % self.p)
+            .collect();
+        trim(&mut difference);
+        difference
+    }
+
+    pub fn mul(&self, a: &[u64], b: &[u64]) -> Poly {
+        if a.is_empty() || b.is_empty() {
+            return vec![];
+        }
+
+        let mut product = vec![0; a.len() + b.len() - 1];
+        for (i, x) in a.iter().enumerate() {
+            for (j, y) in b.iter().enumerate() {
+                product[i + j] = (product[i + j] + self.mul_mod(*x, *y)) % self.p;
+            }
+        }
+        trim(&mut product);
+        product
+    }
+
+    pub fn scale(&self, a: &[u64], factor: u64) -> Poly {
+        let mut scaled = a.iter().map(|c| self.mul_mod(*c, factor)).collect();
+        trim(&mut scaled);
+        scaled
+    }
+
+    pub fn monic(&self, a: &[u64]) -> Poly {
+        match a.last() {
+            Some(lead) => self.scale(a, self.inverse(*lead)),
+            None => vec![],
+        }
+    }
+
+    /// The quotient and remainder of `a` divided by the nonzero `b`.
+    pub fn div_rem(&self, a: &[u64], b: &[u64]) -> (Poly, Poly) {
+        let inverse = self.inverse(*b.last().unwrap());
+        let mut remainder = a.to_vec();
+        let mut quotient = vec![0; (a.len() + 1).saturating_sub(b.len())];
+
+        while remainder.len() >= b.len() {
+            let shift = remainder.len() - b.len();
+            let factor = self.mul_mod(*remainder.last().unwrap(), inverse);
+            for (i, c) in b.iter().enumerate() {
+                let product = self.mul_mod(factor, *c);
+                remainder[shift + i] = (remainder[shift + i] + self.p - product) % self.p;
+            }
+            quotient[shift] = factor;
+            trim(&mut remainder);
+        }
+
+        trim(&mut quotient);
+        (quotient, remainder)
+    }
+
+    pub fn rem(&self, a: &[u64], b: &[u64]) -> Poly {
+        self.div_rem(a, b).1
+    }
+
+    /// The monic greatest common divisor.
+    pub fn gcd(&self, a: &[u64], b: &[u64]) -> Poly {
+        let (mut a, mut b) = (a.to_vec(), b.to_vec());
+        while !b.is_empty() {
+            let remainder = self.rem(&a, &b);
+            a = b;
+            b = remainder;
+        }
+        self.monic(&a)
+    }
+
+    /// Returns `(s, t)` with `s a + t b = 1`, for coprime `a` and `b`.
+    pub fn bezout(&self, a: &[u64], b: &[u64]) -> (Poly, Poly) {
+        // Invariant: r0 = s0 a + t0 b and r1 = s1 a + t1 b.
+        let (mut r0, mut r1) = (a.to_vec(), b.to_vec());
+        let (mut s0, mut s1): (Poly, Poly) = (vec![1], vec![]);
+        let (mut t0, mut t1): (Poly, Poly) = (vec![], vec![1]);
+
+        while !r1.is_empty() {
+            let (quotient, remainder) = self.div_rem(&r0, &r1);
+            let s2 = self.sub(&s0, &self.mul(&quotient, &s1));
+            let t2 = self.sub(&t0, &self.mul(&quotient, &t1));
+            (r0, r1) = (r1, remainder);
+            (s0, s1) = (s1, s2);
+            (t0, t1) = (t1, t2);
+        }
+
+        let inverse = self.inverse(r0[0]);
+        (self.scale(&s0, inverse), self.scale(&t0, inverse))
+    }
+
+    /// `base^exponent` modulo `modulus`, by repeated squaring.
+    fn pow_poly(&self, base: &[u64], exponent: &BigUint, modulus: &[u64]) -> Poly {
+        let mut result = vec![1];
+        let base = self.rem(base, modulus);
+
+        for i in (0..exponent.bits()).rev() {
+            result = self.rem(&self.mul(&result, &result), modulus);
+            if exponent.bit(i) {
+                result = self.rem(&self.mul(&result, &base), modulus);
+            }
+        }
+
+        self.rem(&result, modulus)
+    }
+
+    pub fn derivative(&self, a: &[u64]) -> Poly {
+        let mut derivative: Poly = a
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, c)| self.mul_mod(i as u64 % self.p, *c))
+            .collect();
+        trim(&mut derivative);
+        derivative
+    }
+
+    /// Factors a monic square-free polynomial into monic irreducible ones, by distinct-degree
+    /// factorization followed by the equal-degree factorization of Cantor and Zassenhaus.
+    pub fn factor(&self, f: &[u64]) -> Vec<Poly> {
+        let mut factors = vec![];
+        let mut rest = f.to_vec();
+        let x: Poly = vec![0, 1];
+        let mut power = x.clone();
+        let mut degree = 1;
+
+        while rest.len() > 2 * degree {
+            // The product of the irreducible factors of degree `degree` is gcd(x^(p^d) - x, f).
+            power = self.pow_poly(&power, &BigUint::from(self.p), &rest);
+            let product = self.gcd(&self.sub(&power, &x), &rest);
+
+            if product.len() > 1 {
+                rest = self.div_rem(&rest, &product).0;
+                power = self.rem(&power, &rest);
+                self.split_equal_degree(&product, degree, &mut factors);
+            }
+            degree += 1;
+        }
+        if rest.len() > 1 {
+            factors.push(self.monic(&rest));
+        }
+
+        factors
+    }
+
+    /// Splits a product of distinct monic irreducible polynomials of degree `degree`.
+    fn split_equal_degree(&self, f: &[u64], degree: usize, factors: &mut Vec<Poly>) {
+        if f.len() - 1 == degree {
+            factors.push(f.to_vec());
+            return;
+        }
+
+        // gcd(a^((p^d - 1)/2) - 1, f) is a proper factor for about half of all a.
+        let exponent = (BigUint::from(self.p).pow(degree as u32) - 1u32) / 2u32;
+        let mut random = Random((f.len() as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ self.p);
+        loop {
+            let mut a: Poly = (0..f.len() - 1).map(|_| random.next() % self.p).collect();
+            trim(&mut a);
+            if a.len() < 2 {
+                continue;
+            }
+
+            let power = self.pow_poly(&a, &exponent, f);
+            let candidate = self.gcd(&self.sub(&power, &[1]), f);
+            if candidate.len() > 1 && candidate.len() < f.len() {
+                let other = self.div_rem(f, &candidate).0;
+                self.split_equal_degree(&candidate, degree, factors);
+                self.split_equal_degree(&other, degree, factors);
+                return;
+            }
+        }
+    }
+}
+
+/// A deterministic xorshift generator, so that factorizations are reproducible.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// Removes the zero coefficients at the top.
+pub(super) fn trim<T: Zero>(f: &mut Vec<T>) {
+    while f.last().is_some_and(Zero::is_zero) {
+        f.pop();
+    }
+}
+
+fn to_big(f: &[u64]) -> Vec<BigInt> {
+    f.iter().map(|c| BigInt::from(*c)).collect()
+}
+
+fn mul_big(a: &[BigInt], b: &[BigInt]) -> Vec<BigInt> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+
+    let mut product = vec![BigInt::zero(); a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            product[i + j] += x * y;
+        }
+    }
+    product
+}
+
+/// Lifts the factorization `f = lead * factors[0] * factors[1] * ...` modulo `p`, where `lead`
+/// is the leading coefficient of `f` and the factors are monic and pairwise coprime, to one
+/// modulo `p^k`. Returns the lifted monic factors, with coefficients in `[0, p^k)`.
+pub(super) fn hensel_lift(
+    modular: Modular,
+    f: &[BigInt],
+    factors: &[Poly],
+    k: u32,
+) -> Vec<Vec<BigInt>> {
+    let modulus = BigInt::from(modular.p).pow(k);
+    let lead = f.last().unwrap().mod_floor(&modulus);
+    let mut rest = f.iter().map(|c| c.mod_floor(&modulus)).collect::<Vec<_>>();
+    let mut lifted = vec![];
+
+    for (i, factor) in factors.iter().enumerate().take(factors.len() - 1) {
+        // The product of the remaining factors, with the leading coefficient of `f`.
+        let others = factors[i + 1..]
+            .iter()
+            .fold(vec![modular.reduce(&lead)], |product, g| {
+                modular.mul(&product, g)
+            });
+
+        let (g, h) = lift_pair(modular, &rest, factor, &others, k);
+        lifted.push(g);
+        rest = h.iter().map(|c| c.mod_floor(&modulus)).collect();
+    }
+
+    // The last factor is what is left, divided by the leading coefficient.
+    let inverse = lead.extended_gcd(&modulus).x.mod_floor(&modulus);
+    lifted.push(
+        rest.iter()
+            .map(|c| (c * &inverse).mod_floor(&modulus))
+            .collect(),
+    );
+
+    lifted
+}
+
+/// Lifts `f = g h` modulo `p` to modulo `p^k` with linear Hensel lifting, where `g` is monic and
+/// coprime to `h`. The lifted `g` stays monic.
+fn lift_pair(
+    modular: Modular,
+    f: &[BigInt],
+    g: &[u64],
+    h: &[u64],
+    k: u32,
+) -> (Vec<BigInt>, Vec<BigInt>) {
+    let (s, t) = modular.bezout(g, h);
+    let (mut big_g, mut big_h) = (to_big(g), to_big(h));
+    let mut q = BigInt::from(modular.p);
+
+    for _ in 1..k {
+        // The error f - g h is divisible by q, so e is what remains to be corrected modulo p.
+        let product = mul_big(&big_g, &big_h);
+        let error: Vec<BigInt> = (0..f.len().max(product.len()))
+            .map(|i| {
+                let zero = BigInt::zero();
+                (f.get(i).unwrap_or(&zero) - product.get(i).unwrap_or(&zero)) / &q
+            })
+            .collect();
+        let e = modular.reduce_poly(&error);
+
+        // With s g + t h = 1, e = (s e + c h) g + d h where t e = c g + d.
+        let (c, d) = modular.div_rem(&modular.mul(&t, &e), g);
+        let correction_h = modular.add(&modular.mul(&s, &e), &modular.mul(&c, h));
+
+        for (i, coefficient) in d.iter().enumerate() {
+            big_g[i] += &q * coefficient;
+        }
+        if big_h.len() < correction_h.len() {
+            big_h.resize(correction_h.len(), BigInt::zero());
+        }
+        for (i, coefficient) in correction_h.iter().enumerate() {
+            big_h[i] += &q * coefficient;
+        }
+
+        q *= modular.p;
+    }
+
+    (big_g, big_h)
+}
+
+/// Whether `n` is prime, by trial division.
+pub(super) fn is_prime(n: u64) -> bool {
+    n >= 2
+        && (2..)
+            .take_while(|d| d * d <= n)
+            .all(|d| !n.is_multiple_of(d))
+}
+
+/// Symmetric representatives of `f` modulo `modulus`, in `(-modulus/2, modulus/2]`.
+pub(super) fn symmetric(f: &[BigInt], modulus: &BigInt) -> Vec<BigInt> {
+    let half = modulus / 2;
+    let mut symmetric: Vec<BigInt> = f
+        .iter()
+        .map(|c| {
+            let c = c.mod_floor(modulus);
+            if c > half {
+                c - modulus
+            } else {
+                c
+            }
+        })
+        .collect();
+    trim(&mut symmetric);
+    symmetric
+}
+
+/// The product of polynomials modulo `modulus`.
+pub(super) fn product_mod(
+    factors: &[&Vec<BigInt>],
+    lead: &BigInt,
+    modulus: &BigInt,
+) -> Vec<BigInt> {
+    factors
+        .iter()
+        .fold(vec![lead.mod_floor(modulus)], |product, factor| {
+            mul_big(&product, factor)
+                .iter()
+                .map(|c| c.mod_floor(modulus))
+                .collect()
+        })
+}
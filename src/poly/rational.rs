@@ -0,0 +1,144 @@
+//! Cancelling rational functions to lowest terms.
+
+use crate::{
+    expr::Expr,
+    num::Num,
+    var::{Var, VarMap},
+};
+
+use super::{is_rational, Polynomial};
+
+/// The parts of an expression which are not rational functions, along with the variables
+/// standing for them.
+pub(super) type Atoms = Vec<(Expr, Var)>;
+
+impl Expr {
+    /// Writes the expression as a single fraction `p/q` of polynomials without a common factor,
+    /// so that e.g. `(x^2 - 1)/(x - 1)` becomes `x + 1`. The denominator has coprime integer
+    /// coefficients and a positive leading coefficient, and is left out when it is 1.
+    ///
+    /// Parts which are not rational functions, such as `sin(x)`, `\pi` or `x^(1/2)`, are
+    /// treated as variables of their own, so `sin(x)^2 / sin(x)` becomes `sin(x)`. The
+    /// expression is returned as it is if it divides by zero.
+    pub fn cancel(&self) -> Expr {
+        let Some((numerator, denominator, atoms)) = self.lowest_terms() else {
+            return self.clone();
+        };
+
+        let result = if denominator.as_constant().is_some() {
+            numerator.to_expr()
+        } else {
+            numerator.to_expr() / denominator.to_expr()
+        };
+
+        restore_atoms(&result, &atoms)
+    }
+
+    /// The numerator and denominator of [`Expr::cancel`], along with the parts which are not
+    /// rational functions and the variables standing for them. Returns `None` if the
+    /// expression divides by zero.
+    pub(super) fn lowest_terms(&self) -> Option<(Polynomial, Polynomial, Atoms)> {
+        let mut atoms = vec![];
+        let (numerator, denominator) = self.fraction(&mut atoms)?;
+
+        let gcd = numerator.gcd(&denominator);
+        let numerator = numerator.divide_exact(&gcd).unwrap();
+        let denominator = denominator.divide_exact(&gcd).unwrap();
+
+        let content = denominator.content();
+        let numerator = numerator.scale(&Num::One.checked_div(&content).unwrap());
+        let denominator = denominator.primitive_part();
+
+        Some((numerator, denominator, atoms))
+    }
+
+    /// The expression as a numerator and denominator, where `atoms` holds the parts which are
+    /// not rational functions along with the variables standing for them. Returns `None` if
+    /// the denominator is zero.
+    fn fraction(&self, atoms: &mut Atoms) -> Option<(Polynomial, Polynomial)> {
+        let one = || Polynomial::constant(&[], Num::One);
+
+        match self {
+            Expr::Sum(terms) => {
+                let mut sum = (Polynomial::zero(&[]), one());
+                for term in terms {
+                    let (numerator, denominator) = term.fraction(atoms)?;
+
+                    // Over the least common multiple of the denominators.
+                    let gcd = sum.1.gcd(&denominator);
+                    let other = denominator.divide_exact(&gcd).unwrap();
+                    let own = sum.1.divide_exact(&gcd).unwrap();
+                    sum = (&(&sum.0 * &other) + &(&numerator * &own), &sum.1 * &other);
+                }
+                Some(sum)
+            }
+
+            Expr::Product(factors) => {
+                let mut product = (one(), one());
+                for factor in factors {
+                    let (numerator, denominator) = factor.fraction(atoms)?;
+                    product = (&product.0 * &numerator, &product.1 * &denominator);
+                }
+                Some(product)
+            }
+
+            Expr::Pow(base, exponent) => {
+                let n = match exponent.as_ref() {
+                    Expr::Const(n) => n.as_integer(),
+                    _ => None,
+                };
+                let Some(n) = n else {
+                    return Some(atom(self, atoms));
+                };
+
+                let (numerator, denominator) = base.fraction(atoms)?;
+                let (numerator, denominator) = if n >= 0 {
+                    (numerator, denominator)
+                } else if numerator.is_zero() {
+                    return None;
+                } else {
+                    (denominator, numerator)
+                };
+                let n = n.unsigned_abs();
+                Some((numerator.pow(n), denominator.pow(n)))
+            }
+
+            Expr::Var(var) => {
+                let var = var.as_ref();
+                Some((Polynomial::from_terms(&[var], [(vec![1], Num::One)]), one()))
+            }
+
+            Expr::Const(num) if is_rational(num) => {
+                Some((Polynomial::constant(&[], num.clone()), one()))
+            }
+
+            Expr::Const(_) | Expr::Function(..) => Some(atom(self, atoms)),
+        }
+    }
+}
+
+/// Replaces the variables standing for atoms with the atoms again.
+pub(super) fn restore_atoms(expr: &Expr, atoms: &Atoms) -> Expr {
+    let replacements: VarMap<Expr> = atoms
+        .iter()
+        .map(|(atom, var)| (var, atom.clone()))
+        .collect();
+    expr.substitute_all(&replacements)
+}
+
+/// The variable standing for `expr`, as a fraction.
+fn atom(expr: &Expr, atoms: &mut Atoms) -> (Polynomial, Polynomial) {
+    let var = match atoms.iter().find(|(atom, _)| atom.exact_match(expr)) {
+        Some((_, var)) => var.clone(),
+        None => {
+            let var = Var::new_owned(&format!("#{}", atoms.len()));
+            atoms.push((expr.clone(), var.clone()));
+            var
+        }
+    };
+
+    (
+        Polynomial::from_terms(&[&var], [(vec![1], Num::One)]),
+        Polynomial::constant(&[], Num::One),
+    )
+}
@@ -0,0 +1,271 @@
+//! Exact roots of polynomials whose irreducible parts have degree at most 4.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::{
+    expr::{EvalError, Expr},
+    num::Num,
+    var::Var,
+};
+
+use super::{
+    add,
+    division::{div, neg, sub},
+    mul, SolveError, UnivariatePolynomial,
+};
+
+/// How far a root may be from zero, relative to the size of the terms of the polynomial at
+/// it, when it is substituted back.
+const TOLERANCE: f64 = 1e-9;
+
+impl Expr {
+    /// Solves `expr = 0` for `var`, where the expression must be a polynomial in `var` alone,
+    /// see [`UnivariatePolynomial::roots`].
+    pub fn solve(&self, var: &Var) -> Result<Vec<(Expr, u32)>, SolveError> {
+        let polynomial = self.to_polynomial(&[var])?.to_univariate().unwrap();
+        polynomial.roots()
+    }
+}
+
+impl UnivariatePolynomial {
+    /// The complex roots of the polynomial as exact expressions in rationals, radicals and
+    /// `i`, along with their multiplicities.
+    ///
+    /// The polynomial is factored into irreducible polynomials over the rationals, see
+    /// [`UnivariatePolynomial::factor`]. Its linear factors give the rational roots, and the
+    /// other factors are solved with the quadratic formula, Cardano's formula or Ferrari's
+    /// method, so they must have degree at most 4. Every root is checked by substituting it back
+    /// into the polynomial.
+    ///
+    /// The roots are ordered by multiplicity, with the rational ones first in ascending order.
+    pub fn roots(&self) -> Result<Vec<(Expr, u32)>, SolveError> {
+        if self.is_zero() {
+            return Err(SolveError::ZeroPolynomial);
+        }
+
+        let (_, mut factors) = self.factor();
+        factors.sort_by(|(f, m), (g, n)| {
+            let rational = match (rational_root(f), rational_root(g)) {
+                (Some(a), Some(b)) => compare(&a, &b),
+                _ => Ordering::Equal,
+            };
+            m.cmp(n).then(f.degree().cmp(&g.degree())).then(rational)
+        });
+
+        let mut roots = vec![];
+        for (factor, multiplicity) in factors {
+            for root in solve(&factor)? {
+                if !self.is_root(&root) {
+                    return Err(SolveError::Unverified { root });
+                }
+                roots.push((root, multiplicity));
+            }
+        }
+        Ok(roots)
+    }
+
+    /// Whether the polynomial is numerically zero at `root`.
+    fn is_root(&self, root: &Expr) -> bool {
+        let Ok(x) = root.eval(&HashMap::new()) else {
+            return false;
+        };
+        let (Ok(value), Ok(scale)) = (self.eval(x), self.magnitude_at(x.norm())) else {
+            return false;
+        };
+        value.norm() <= TOLERANCE * scale
+    }
+
+    /// The sum of the absolute values of the terms at a point of absolute value `r`.
+    fn magnitude_at(&self, r: f64) -> Result<f64, EvalError> {
+        let mut value = 0.0;
+        for coefficient in self.coefficients.iter().rev() {
+            value = value * r + coefficient.eval_float()?.norm();
+        }
+        Ok(value)
+    }
+}
+
+/// The roots of an irreducible polynomial.
+fn solve(f: &UnivariatePolynomial) -> Result<Vec<Expr>, SolveError> {
+    let coefficients: Vec<Num> = f.monic().coefficients().to_vec();
+    let roots = match coefficients.as_slice() {
+        [] | [_] => vec![],
+        [a, _] => vec![Expr::Const(neg(a))],
+        [b, a, _] => quadratic(a, b),
+        [c, b, a, _] => cubic(a, b, c),
+        [d, c, b, a, _] => quartic(a, b, c, d)?,
+        _ => {
+            return Err(SolveError::DegreeTooHigh {
+                degree: f.degree().unwrap(),
+            })
+        }
+    };
+
+    Ok(roots.iter().map(Expr::simplify).collect())
+}
+
+/// The root of a linear polynomial.
+fn rational_root(f: &UnivariatePolynomial) -> Option<Num> {
+    match f.coefficients() {
+        [b, a] => Some(div(&neg(b), a)),
+        _ => None,
+    }
+}
+
+/// The roots of `x^2 + a x + b`, by the quadratic formula.
+fn quadratic(a: &Num, b: &Num) -> Vec<Expr> {
+    // x = -a/2 ± \sqrt{a^2/4 - b}
+    let center = Expr::Const(div(&neg(a), &Num::from(2)));
+    let discriminant = sub(&div(&mul(a, a), &Num::from(4)), b);
+    let root = sqrt(Expr::Const(discriminant));
+
+    vec![center.clone() - root.clone(), center + root]
+}
+
+/// The roots of `x^3 + a x^2 + b x + c`, by Cardano's formula.
+fn cubic(a: &Num, b: &Num, c: &Num) -> Vec<Expr> {
+    // x = t - a/3 gives the depressed cubic t^3 + p t + q.
+    let shift = div(a, &Num::from(3));
+    let p = sub(b, &mul(a, &shift));
+    let q = add(
+        &sub(
+            &mul(&Num::rational(2, 27), &mul(a, &mul(a, a))),
+            &div(&mul(a, b), &Num::from(3)),
+        ),
+        c,
+    );
+
+    // The primitive cube roots of unity, (-1 ± i\sqrt{3})/2.
+    let half = Expr::Const(Num::rational(-1, 2));
+    let imaginary =
+        Expr::Const(Num::I) * sqrt(Expr::Const(Num::from(3))) / Expr::Const(Num::from(2));
+    let unity = [
+        Expr::Const(Num::One),
+        half.clone() + imaginary.clone(),
+        half - imaginary,
+    ];
+
+    let t: Vec<Expr> = if p.is_zero() {
+        let root = cbrt(Expr::Const(neg(&q)));
+        unity.into_iter().map(|w| w * root.clone()).collect()
+    } else {
+        // t = u - p/(3u), where u^3 = -q/2 + \sqrt{q^2/4 + p^3/27}. Taking v = -p/(3u) rather
+        // than a cube root of its own keeps the cube roots consistent.
+        let discriminant = add(
+            &div(&mul(&q, &q), &Num::from(4)),
+            &div(&mul(&p, &mul(&p, &p)), &Num::from(27)),
+        );
+        let u = cbrt(Expr::Const(div(&neg(&q), &Num::from(2))) + sqrt(Expr::Const(discriminant)));
+
+        unity
+            .into_iter()
+            .map(|w| {
+                let u = w * u.clone();
+                u.clone() - Expr::Const(div(&p, &Num::from(3))) / u
+            })
+            .collect()
+    };
+
+    t.into_iter()
+        .map(|t| t - Expr::Const(shift.clone()))
+        .collect()
+}
+
+/// The roots of `x^4 + a x^3 + b x^2 + c x + d`, by Ferrari's method.
+fn quartic(a: &Num, b: &Num, c: &Num, d: &Num) -> Result<Vec<Expr>, SolveError> {
+    // x = y - a/4 gives the depressed quartic y^4 + p y^2 + q y + r.
+    let shift = div(a, &Num::from(4));
+    let a2 = mul(a, a);
+    let p = sub(b, &mul(&Num::rational(3, 8), &a2));
+    let q = add(
+        &sub(c, &div(&mul(a, b), &Num::from(2))),
+        &div(&mul(&a2, a), &Num::from(8)),
+    );
+    let r = add(
+        &sub(d, &div(&mul(a, c), &Num::from(4))),
+        &sub(
+            &div(&mul(&a2, b), &Num::from(16)),
+            &mul(&Num::rational(3, 256), &mul(&a2, &a2)),
+        ),
+    );
+
+    let y: Vec<Expr> = if q.is_zero() {
+        // A quadratic in y^2.
+        quadratic(&p, &r)
+            .into_iter()
+            .flat_map(|z| {
+                let root = sqrt(z);
+                [Expr::Const(Num::from(-1)) * root.clone(), root]
+            })
+            .collect()
+    } else {
+        // y^4 + p y^2 + q y + r = (y^2 + p/2 + m)^2 - (\sqrt{2m} y - q/(2\sqrt{2m}))^2 for any
+        // root m of the resolvent cubic 8m^3 + 8p m^2 + (2p^2 - 8r) m - q^2, none of which is
+        // zero as q is not.
+        let var = Var::new_owned("#m");
+        let resolvent = UnivariatePolynomial::new(
+            &var,
+            vec![
+                neg(&mul(&q, &q)),
+                sub(&mul(&Num::from(2), &mul(&p, &p)), &mul(&Num::from(8), &r)),
+                mul(&Num::from(8), &p),
+                Num::from(8),
+            ],
+        );
+        let m = resolvent.roots()?.swap_remove(0).0;
+        let s = sqrt(Expr::Const(Num::from(2)) * m.clone());
+
+        // Each sign gives y^2 ∓ \sqrt{2m} y + p/2 + m ± q/(2\sqrt{2m}) = 0.
+        let mut roots = vec![];
+        for sign in [Num::One, Num::from(-1)] {
+            let sign = Expr::Const(sign);
+            let discriminant = Expr::Const(mul(&Num::from(-2), &p))
+                - Expr::Const(Num::from(2)) * m.clone()
+                - sign.clone() * Expr::Const(mul(&Num::from(2), &q)) / s.clone();
+            let root = sqrt(discriminant);
+            let half = Expr::Const(Num::rational(1, 2));
+            roots.push(half.clone() * (sign.clone() * s.clone() - root.clone()));
+            roots.push(half * (sign * s.clone() + root));
+        }
+        roots
+    };
+
+    Ok(y.into_iter()
+        .map(|y| y - Expr::Const(shift.clone()))
+        .collect())
+}
+
+/// The principal square root, written with `i` for negative constants.
+fn sqrt(value: Expr) -> Expr {
+    let half = Expr::Const(Num::rational(1, 2));
+    match value.simplify() {
+        Expr::Const(num) if is_negative(&num) => {
+            Expr::Const(Num::I) * Expr::Const(num.checked_neg().unwrap()).pow(half)
+        }
+        value => value.pow(half),
+    }
+}
+
+/// The cube root, which is real for real constants.
+fn cbrt(value: Expr) -> Expr {
+    let third = Expr::Const(Num::rational(1, 3));
+    match value.simplify() {
+        Expr::Const(num) if is_negative(&num) => {
+            Expr::Const(Num::from(-1)) * Expr::Const(num.checked_neg().unwrap()).pow(third)
+        }
+        value => value.pow(third),
+    }
+}
+
+fn is_negative(num: &Num) -> bool {
+    num.eval_float()
+        .is_ok_and(|value| value.im == 0.0 && value.re < 0.0)
+}
+
+/// Orders rationals by value.
+fn compare(a: &Num, b: &Num) -> Ordering {
+    let (num_a, den_a) = a.as_fraction().unwrap();
+    let (num_b, den_b) = b.as_fraction().unwrap();
+    (num_a * den_b).cmp(&(num_b * den_a))
+}
@@ -0,0 +1,152 @@
+use crate::{expr::Expr, num::Num};
+
+use super::{is_symbol, parenthesized, Node, ATOM, OPERATORS, PRODUCT, SUM};
+
+/// Parentheses that grow with what they enclose.
+const PARENS: (&str, &str) = ("\\left(", "\\right)");
+
+impl Expr {
+    /// Renders the expression as LaTeX math, e.g. `\frac{x^{2}}{2} - \sin\left(y\right)`.
+    ///
+    /// Parentheses are only added where precedence requires them.
+    pub fn to_latex(&self) -> String {
+        let mut out = String::new();
+        latex(&Node::from_expr(self), &mut out);
+        out
+    }
+}
+
+fn latex(node: &Node, out: &mut String) {
+    match node {
+        Node::Sum(terms) => {
+            for (i, (negative, term)) in terms.iter().enumerate() {
+                match (i, negative) {
+                    (0, false) => {}
+                    (0, true) => out.push('-'),
+                    (_, false) => out.push_str(" + "),
+                    (_, true) => out.push_str(" - "),
+                }
+
+                let min = if *negative { PRODUCT } else { SUM + 1 };
+                parenthesized(term, min, PARENS, latex, out);
+            }
+        }
+
+        Node::Product(factors) => {
+            for (i, factor) in factors.iter().enumerate() {
+                let mut rendered = String::new();
+                parenthesized(factor, PRODUCT, PARENS, latex, &mut rendered);
+
+                if i > 0 {
+                    out.push_str(separator(out, &rendered));
+                }
+                out.push_str(&rendered);
+            }
+        }
+
+        Node::Frac(num, den) => {
+            out.push_str("\\frac{");
+            latex(num, out);
+            out.push_str("}{");
+            latex(den, out);
+            out.push('}');
+        }
+
+        Node::Neg(inner) => {
+            out.push('-');
+            parenthesized(inner, PRODUCT, PARENS, latex, out);
+        }
+
+        Node::Pow(base, exponent) => {
+            parenthesized(base, ATOM, PARENS, latex, out);
+            out.push_str("^{");
+            latex(exponent, out);
+            out.push('}');
+        }
+
+        Node::Root(radicand, index) => {
+            match index {
+                2 => out.push_str("\\sqrt{"),
+                _ => out.push_str(&format!("\\sqrt[{index}]{{")),
+            }
+            latex(radicand, out);
+            out.push('}');
+        }
+
+        Node::Call(name, args) => match (name.as_str(), args.as_slice()) {
+            ("abs", [arg]) => {
+                out.push_str("\\left|");
+                latex(arg, out);
+                out.push_str("\\right|");
+            }
+            ("log", [base, arg]) => {
+                out.push_str("\\log_{");
+                latex(base, out);
+                out.push('}');
+                latex_args(std::slice::from_ref(arg), out);
+            }
+            ("root", [index, radicand]) => {
+                out.push_str("\\sqrt[");
+                latex(index, out);
+                out.push_str("]{");
+                latex(radicand, out);
+                out.push('}');
+            }
+            _ => {
+                if OPERATORS.contains(&name.as_str()) {
+                    out.push('\\');
+                    out.push_str(name);
+                } else if is_symbol(name) {
+                    out.push_str(name);
+                } else {
+                    out.push_str(&format!("\\operatorname{{{name}}}"));
+                }
+                latex_args(args, out);
+            }
+        },
+
+        Node::Var(name) => {
+            if is_symbol(name) {
+                out.push_str(name);
+            } else {
+                out.push_str(&format!("\\mathrm{{{name}}}"));
+            }
+        }
+
+        Node::Int(n) => out.push_str(&n.to_string()),
+
+        Node::Symbol(num) => out.push_str(match num {
+            Num::Pi => "\\pi",
+            Num::E => "e",
+            Num::I => "i",
+            Num::Infinity => "\\infty",
+            _ => "\\text{undefined}",
+        }),
+    }
+}
+
+fn latex_args(args: &[Node], out: &mut String) {
+    out.push_str("\\left(");
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        latex(arg, out);
+    }
+    out.push_str("\\right)");
+}
+
+/// What to put between two adjacent factors: `\cdot` before a number, so that `2 \cdot 3` is not
+/// read as `23`, and a space between letters, so that commands are not run together.
+fn separator(before: &str, after: &str) -> &'static str {
+    let ends_in_letter = before.ends_with(|c: char| c.is_ascii_alphabetic());
+    let starts_with_letter = after.starts_with(|c: char| c.is_ascii_alphabetic());
+
+    if after.starts_with(|c: char| c.is_ascii_digit()) {
+        " \\cdot "
+    } else if ends_in_letter && starts_with_letter {
+        " "
+    } else {
+        ""
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{expr::Expr, num::Num};
+
+use super::{parenthesized, Node, ATOM, PRODUCT, SUM};
+
+const PARENS: (&str, &str) = ("<mrow><mo>(</mo>", "<mo>)</mo></mrow>");
+
+impl Expr {
+    /// Renders the expression as presentation MathML, wrapped in a `<math>` element.
+    pub fn to_mathml(&self) -> String {
+        let mut out = String::from("<math xmlns=\"http://www.w3.org/1998/Math/MathML\">");
+        mathml(&Node::from_expr(self), &mut out);
+        out.push_str("</math>");
+        out
+    }
+}
+
+/// Invisible operators which tell assistive technology how adjacent elements relate.
+const FUNCTION_APPLICATION: &str = "<mo>&#x2061;</mo>";
+const INVISIBLE_TIMES: &str = "<mo>&#x2062;</mo>";
+
+fn mathml(node: &Node, out: &mut String) {
+    match node {
+        Node::Sum(terms) => {
+            out.push_str("<mrow>");
+            for (i, (negative, term)) in terms.iter().enumerate() {
+                match (i, negative) {
+                    (0, false) => {}
+                    (_, false) => out.push_str("<mo>+</mo>"),
+                    (_, true) => out.push_str("<mo>&#x2212;</mo>"),
+                }
+
+                let min = if *negative { PRODUCT } else { SUM + 1 };
+                parenthesized(term, min, PARENS, mathml, out);
+            }
+            out.push_str("</mrow>");
+        }
+
+        Node::Product(factors) => {
+            out.push_str("<mrow>");
+            for (i, factor) in factors.iter().enumerate() {
+                if i > 0 {
+                    if factor.starts_with_number() {
+                        out.push_str("<mo>&#x22C5;</mo>");
+                    } else {
+                        out.push_str(INVISIBLE_TIMES);
+                    }
+                }
+                parenthesized(factor, PRODUCT, PARENS, mathml, out);
+            }
+            out.push_str("</mrow>");
+        }
+
+        Node::Frac(num, den) => {
+            out.push_str("<mfrac>");
+            mathml(num, out);
+            mathml(den, out);
+            out.push_str("</mfrac>");
+        }
+
+        Node::Neg(inner) => {
+            out.push_str("<mrow><mo>&#x2212;</mo>");
+            parenthesized(inner, PRODUCT, PARENS, mathml, out);
+            out.push_str("</mrow>");
+        }
+
+        Node::Pow(base, exponent) => {
+            out.push_str("<msup>");
+            parenthesized(base, ATOM, PARENS, mathml, out);
+            mathml(exponent, out);
+            out.push_str("</msup>");
+        }
+
+        Node::Root(radicand, 2) => {
+            out.push_str("<msqrt>");
+            mathml(radicand, out);
+            out.push_str("</msqrt>");
+        }
+
+        Node::Root(radicand, index) => {
+            out.push_str("<mroot>");
+            mathml(radicand, out);
+            out.push_str(&format!("<mn>{index}</mn>"));
+            out.push_str("</mroot>");
+        }
+
+        Node::Call(name, args) => match (name.as_str(), args.as_slice()) {
+            ("abs", [arg]) => {
+                out.push_str("<mrow><mo>|</mo>");
+                mathml(arg, out);
+                out.push_str("<mo>|</mo></mrow>");
+            }
+            ("root", [index, radicand]) => {
+                out.push_str("<mroot>");
+                mathml(radicand, out);
+                mathml(index, out);
+                out.push_str("</mroot>");
+            }
+            ("log", [base, arg]) => {
+                out.push_str("<mrow><msub><mi>log</mi>");
+                mathml(base, out);
+                out.push_str("</msub>");
+                out.push_str(FUNCTION_APPLICATION);
+                mathml_args(std::slice::from_ref(arg), out);
+                out.push_str("</mrow>");
+            }
+            _ => {
+                out.push_str(&format!("<mrow><mi>{}</mi>", escape(name)));
+                out.push_str(FUNCTION_APPLICATION);
+                mathml_args(args, out);
+                out.push_str("</mrow>");
+            }
+        },
+
+        Node::Var(name) => out.push_str(&format!("<mi>{}</mi>", escape(name))),
+
+        Node::Int(n) => out.push_str(&format!("<mn>{n}</mn>")),
+
+        Node::Symbol(num) => out.push_str(match num {
+            Num::Pi => "<mi>&#x3C0;</mi>",
+            Num::E => "<mi>e</mi>",
+            Num::I => "<mi>i</mi>",
+            Num::Infinity => "<mi>&#x221E;</mi>",
+            _ => "<mtext>undefined</mtext>",
+        }),
+    }
+}
+
+fn mathml_args(args: &[Node], out: &mut String) {
+    out.push_str("<mrow><mo>(</mo>");
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            out.push_str("<mo>,</mo>");
+        }
+        mathml(arg, out);
+    }
+    out.push_str("<mo>)</mo></mrow>");
+}
+
+/// Escapes the characters with a special meaning in XML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,372 @@
+//! Renderers turning expressions into text for humans and other programs.
+
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, Signed, Zero};
+
+use crate::{expr::Expr, num::Num};
+
+mod latex;
+mod mathml;
+mod text;
+mod typst;
+
+pub use text::{Colored, Formatted};
+
+// Precedence levels, see `Node::precedence`.
+pub(crate) const SUM: u8 = 1;
+pub(crate) const NEG: u8 = 2;
+pub(crate) const PRODUCT: u8 = 3;
+pub(crate) const POWER: u8 = 4;
+pub(crate) const ATOM: u8 = 5;
+
+/// Functions that LaTeX and Typst both write as upright operators of their own.
+pub(crate) const OPERATORS: &[&str] = &[
+    "sin", "cos", "tan", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh", "exp", "ln", "log",
+    "min", "max",
+];
+
+/// Whether a name can be written as is, i.e. it is a single letter with optional primes.
+pub(crate) fn is_symbol(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(char::is_alphabetic) && chars.all(|c| c == '\'')
+}
+
+/// Renders `node` with `render`, wrapped in the `open` and `close` brackets if it binds looser
+/// than `min`.
+pub(crate) fn parenthesized(
+    node: &Node,
+    min: u8,
+    (open, close): (&str, &str),
+    render: fn(&Node, &mut String),
+    out: &mut String,
+) {
+    if node.precedence() < min {
+        out.push_str(open);
+        render(node, out);
+        out.push_str(close);
+    } else {
+        render(node, out);
+    }
+}
+
+/// The character set used when printing expressions as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Style {
+    /// Plain ASCII that can be parsed back with [`crate::parse::parse`], e.g. `x^2 - 2sqrt(y)`.
+    #[default]
+    Ascii,
+    /// Unicode with superscripts and mathematical symbols, e.g. `x² − 2√y`.
+    Unicode,
+}
+
+/// Options for printing expressions as text, see [`Expr::display_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrintOptions {
+    pub style: Style,
+    /// Whether a leading coefficient is written next to what it multiplies (`2x`) rather than
+    /// with an explicit operator (`2*x`).
+    pub implicit_multiplication: bool,
+}
+
+impl PrintOptions {
+    pub fn ascii() -> Self {
+        Self {
+            style: Style::Ascii,
+            ..Self::default()
+        }
+    }
+
+    pub fn unicode() -> Self {
+        Self {
+            style: Style::Unicode,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            style: Style::default(),
+            implicit_multiplication: true,
+        }
+    }
+}
+
+/// An ANSI terminal color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+}
+
+impl Color {
+    /// The SGR code setting this color as the foreground.
+    pub(crate) fn code(self) -> u8 {
+        match self {
+            Color::Black => 30,
+            Color::Red => 31,
+            Color::Green => 32,
+            Color::Yellow => 33,
+            Color::Blue => 34,
+            Color::Magenta => 35,
+            Color::Cyan => 36,
+            Color::White => 37,
+            Color::BrightBlack => 90,
+            Color::BrightRed => 91,
+            Color::BrightGreen => 92,
+            Color::BrightYellow => 93,
+            Color::BrightBlue => 94,
+            Color::BrightMagenta => 95,
+            Color::BrightCyan => 96,
+            Color::BrightWhite => 97,
+        }
+    }
+}
+
+/// The colors used by [`Expr::colored`] for each part of an expression. `None` leaves that part
+/// uncolored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub variable: Option<Color>,
+    pub constant: Option<Color>,
+    pub function: Option<Color>,
+    pub operator: Option<Color>,
+    pub bracket: Option<Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            variable: Some(Color::BrightMagenta),
+            constant: Some(Color::BrightBlue),
+            function: Some(Color::BrightCyan),
+            operator: None,
+            bracket: Some(Color::BrightBlack),
+        }
+    }
+}
+
+/// An expression rearranged for display: subtraction, division and roots are made explicit,
+/// and the rational coefficients of a product are pulled to the front.
+#[derive(Debug, Clone)]
+pub(crate) enum Node {
+    /// Terms along with whether they are subtracted.
+    Sum(Vec<(bool, Node)>),
+    Product(Vec<Node>),
+    Frac(Box<Node>, Box<Node>),
+    Neg(Box<Node>),
+    Pow(Box<Node>, Box<Node>),
+    /// The `index`-th root.
+    Root(Box<Node>, u32),
+    Call(String, Vec<Node>),
+    Var(String),
+    Int(BigUint),
+    /// `π`, `e`, `i`, `∞` or the undefined marker.
+    Symbol(Num),
+}
+
+impl Node {
+    pub(crate) fn from_expr(expr: &Expr) -> Node {
+        match expr {
+            Expr::Sum(terms) => {
+                let mut result = vec![];
+
+                for term in terms {
+                    match Node::from_expr(term) {
+                        Node::Sum(inner) => result.extend(inner),
+                        Node::Neg(inner) => result.push((true, *inner)),
+                        node => result.push((false, node)),
+                    }
+                }
+
+                match result.len() {
+                    0 => Node::Int(BigUint::zero()),
+                    1 if !result[0].0 => result.pop().unwrap().1,
+                    _ => Node::Sum(result),
+                }
+            }
+
+            Expr::Product(factors) => Node::product(factors.iter().map(Node::from_expr)),
+
+            Expr::Pow(base, exponent) => {
+                let base = Node::from_expr(base);
+
+                match exponent.as_ref() {
+                    // x^-n = 1/x^n
+                    Expr::Const(num) if num.as_fraction().is_some_and(|(n, _)| n.is_negative()) => {
+                        let (n, d) = num.as_fraction().unwrap();
+                        Node::Frac(
+                            Box::new(Node::Int(BigUint::one())),
+                            Box::new(Node::power(base, -n, d)),
+                        )
+                    }
+                    Expr::Const(num) if num.as_fraction().is_some() => {
+                        let (n, d) = num.as_fraction().unwrap();
+                        Node::power(base, n, d)
+                    }
+                    exponent => Node::Pow(Box::new(base), Box::new(Node::from_expr(exponent))),
+                }
+            }
+
+            Expr::Var(var) => Node::Var(var.get_name()),
+
+            Expr::Const(num) => Node::from_num(num),
+
+            Expr::Function(def, args) => {
+                Node::Call(def.name(), args.iter().map(Node::from_expr).collect())
+            }
+        }
+    }
+
+    pub(crate) fn from_num(num: &Num) -> Node {
+        match num {
+            Num::Zero => Node::Int(BigUint::zero()),
+            Num::One => Node::Int(BigUint::one()),
+            Num::Rational { num, den } => Node::product([
+                Node::Int(num.magnitude().clone()),
+                Node::Frac(
+                    Box::new(Node::Int(BigUint::one())),
+                    Box::new(Node::Int(den.clone())),
+                ),
+            ])
+            .negate_if(num.is_negative()),
+            Num::Radical {
+                num,
+                den,
+                radicand,
+                index,
+            } => Node::product([
+                Node::Int(num.magnitude().clone()),
+                Node::Frac(
+                    Box::new(Node::Int(BigUint::one())),
+                    Box::new(Node::Int(den.clone())),
+                ),
+                Node::Root(Box::new(Node::Int(radicand.clone())), *index),
+            ])
+            .negate_if(num.is_negative()),
+            _ => Node::Symbol(num.clone()),
+        }
+    }
+
+    /// Builds `base^(n/d)` for a positive exponent, using a root when `n` is 1.
+    fn power(base: Node, n: BigInt, d: BigInt) -> Node {
+        match (n.is_one(), d.is_one(), d.to_u32_digits().1.as_slice()) {
+            (true, true, _) => base,
+            (true, false, [index]) => Node::Root(Box::new(base), *index),
+            _ => {
+                let exponent = Node::product([
+                    Node::Int(n.magnitude().clone()),
+                    Node::Frac(
+                        Box::new(Node::Int(BigUint::one())),
+                        Box::new(Node::Int(d.magnitude().clone())),
+                    ),
+                ]);
+                Node::Pow(
+                    Box::new(base),
+                    Box::new(exponent.negate_if(n.is_negative())),
+                )
+            }
+        }
+    }
+
+    /// Combines factors into a single fraction with the integer coefficients first, pulling any
+    /// signs out to the front.
+    fn product(factors: impl IntoIterator<Item = Node>) -> Node {
+        let mut negative = false;
+        let (mut coefficient_num, mut coefficient_den) = (BigUint::one(), BigUint::one());
+        let (mut numerator, mut denominator) = (vec![], vec![]);
+        let mut pending: Vec<(Node, bool)> =
+            factors.into_iter().map(|factor| (factor, false)).collect();
+        pending.reverse();
+
+        while let Some((factor, inverted)) = pending.pop() {
+            let (coefficient, rest) = if inverted {
+                (&mut coefficient_den, &mut denominator)
+            } else {
+                (&mut coefficient_num, &mut numerator)
+            };
+
+            match factor {
+                Node::Int(n) => *coefficient *= n,
+                Node::Neg(inner) => {
+                    negative = !negative;
+                    pending.push((*inner, inverted));
+                }
+                Node::Product(inner) => {
+                    pending.extend(inner.into_iter().rev().map(|factor| (factor, inverted)))
+                }
+                Node::Frac(num, den) => {
+                    pending.push((*den, !inverted));
+                    pending.push((*num, inverted));
+                }
+                factor => rest.push(factor),
+            }
+        }
+
+        let combine = |coefficient: BigUint, mut rest: Vec<Node>| {
+            if !coefficient.is_one() || rest.is_empty() {
+                rest.insert(0, Node::Int(coefficient));
+            }
+            match rest.len() {
+                1 => rest.pop().unwrap(),
+                _ => Node::Product(rest),
+            }
+        };
+
+        let node = if coefficient_den.is_one() && denominator.is_empty() {
+            combine(coefficient_num, numerator)
+        } else {
+            Node::Frac(
+                Box::new(combine(coefficient_num, numerator)),
+                Box::new(combine(coefficient_den, denominator)),
+            )
+        };
+
+        node.negate_if(negative)
+    }
+
+    fn negate_if(self, negative: bool) -> Node {
+        if negative {
+            Node::Neg(Box::new(self))
+        } else {
+            self
+        }
+    }
+
+    /// Whether the first thing shown for the node as a product factor is a number. Adjacent
+    /// numbers need a visible operator between them to not read as one number.
+    pub(crate) fn starts_with_number(&self) -> bool {
+        match self {
+            Node::Int(_) => true,
+            Node::Product(factors) => factors[0].starts_with_number(),
+            Node::Pow(base, _) => base.precedence() == ATOM && base.starts_with_number(),
+            _ => false,
+        }
+    }
+
+    /// How tightly the node binds, from sums (loosest) to atoms (tightest).
+    pub(crate) fn precedence(&self) -> u8 {
+        match self {
+            Node::Sum(_) => SUM,
+            Node::Neg(_) => NEG,
+            Node::Product(_) | Node::Frac(..) => PRODUCT,
+            Node::Pow(..) => POWER,
+            Node::Root(..) | Node::Call(..) | Node::Var(_) | Node::Int(_) | Node::Symbol(_) => ATOM,
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::{expr::Expr, num::Num};
+
+use super::{Node, PrintOptions, Style, Theme, ATOM, POWER, PRODUCT, SUM};
+
+impl Expr {
+    /// Displays the expression as plain text, e.g. `x - 2y^2` or `x − 2y²`, depending on the
+    /// options.
+    pub fn display_with(&self, options: PrintOptions) -> Formatted<'_> {
+        Formatted {
+            expr: self,
+            options,
+        }
+    }
+
+    /// Displays the expression as text highlighted with ANSI colors, using the default
+    /// [`Theme`]. Colors are left out when the `NO_COLOR` environment variable is set.
+    pub fn colored(&self) -> Colored<'_> {
+        Colored {
+            expr: self,
+            options: PrintOptions::default(),
+            theme: Theme::default(),
+        }
+    }
+}
+
+/// An expression displayed with the given [`PrintOptions`], see [`Expr::display_with`].
+#[derive(Debug, Clone, Copy)]
+pub struct Formatted<'a> {
+    expr: &'a Expr,
+    options: PrintOptions,
+}
+
+impl Display for Formatted<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", print(self.expr, self.options, None))
+    }
+}
+
+/// An expression displayed with ANSI colors, see [`Expr::colored`].
+#[derive(Debug, Clone, Copy)]
+pub struct Colored<'a> {
+    expr: &'a Expr,
+    options: PrintOptions,
+    theme: Theme,
+}
+
+impl Colored<'_> {
+    pub fn with_theme(self, theme: Theme) -> Self {
+        Self { theme, ..self }
+    }
+
+    pub fn with_options(self, options: PrintOptions) -> Self {
+        Self { options, ..self }
+    }
+}
+
+impl Display for Colored<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // See https://no-color.org
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        let theme = (!no_color).then_some(&self.theme);
+
+        write!(f, "{}", print(self.expr, self.options, theme))
+    }
+}
+
+fn print(expr: &Expr, options: PrintOptions, theme: Option<&Theme>) -> String {
+    let mut printer = Printer {
+        options,
+        theme,
+        out: String::new(),
+    };
+    printer.node(&Node::from_expr(expr));
+    printer.out
+}
+
+/// What a piece of output is, which decides its color.
+#[derive(Debug, Clone, Copy)]
+enum Class {
+    Variable,
+    Constant,
+    Function,
+    Operator,
+    Bracket,
+}
+
+struct Printer<'t> {
+    options: PrintOptions,
+    theme: Option<&'t Theme>,
+    out: String,
+}
+
+impl Printer<'_> {
+    fn unicode(&self) -> bool {
+        self.options.style == Style::Unicode
+    }
+
+    fn emit(&mut self, class: Class, text: &str) {
+        let color = self.theme.and_then(|theme| match class {
+            Class::Variable => theme.variable,
+            Class::Constant => theme.constant,
+            Class::Function => theme.function,
+            Class::Operator => theme.operator,
+            Class::Bracket => theme.bracket,
+        });
+
+        match color {
+            Some(color) => self
+                .out
+                .push_str(&format!("\u{001b}[{}m{text}\u{001b}[0m", color.code())),
+            None => self.out.push_str(text),
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::Sum(terms) => {
+                let minus = if self.unicode() { "−" } else { "-" };
+
+                for (i, (negative, term)) in terms.iter().enumerate() {
+                    match (i, negative) {
+                        (0, false) => {}
+                        (0, true) => self.emit(Class::Operator, minus),
+                        (_, false) => self.emit(Class::Operator, " + "),
+                        (_, true) => self.emit(Class::Operator, &format!(" {minus} ")),
+                    }
+
+                    let min = if *negative { PRODUCT } else { SUM + 1 };
+                    self.parenthesized(term, min);
+                }
+            }
+
+            Node::Product(factors) => {
+                for (i, factor) in factors.iter().enumerate() {
+                    // A leading coefficient is written next to what it multiplies, as in `2x`.
+                    let after_coefficient = i == 1 && matches!(factors[0], Node::Int(_));
+                    let implicit = self.options.implicit_multiplication
+                        && after_coefficient
+                        && !self.starts_with_digit(factor, PRODUCT);
+
+                    if i > 0 && !implicit {
+                        self.emit(Class::Operator, if self.unicode() { "·" } else { "*" });
+                    }
+                    self.parenthesized(factor, PRODUCT);
+                }
+            }
+
+            Node::Frac(num, den) => {
+                self.parenthesized(num, PRODUCT);
+                self.emit(Class::Operator, "/");
+                self.parenthesized(den, POWER);
+            }
+
+            Node::Neg(inner) => {
+                self.emit(Class::Operator, if self.unicode() { "−" } else { "-" });
+                self.parenthesized(inner, PRODUCT);
+            }
+
+            Node::Pow(base, exponent) => {
+                self.parenthesized(base, ATOM);
+
+                match superscript(exponent).filter(|_| self.unicode()) {
+                    Some(superscript) => self.emit(Class::Constant, &superscript),
+                    None => {
+                        self.emit(Class::Operator, "^");
+                        self.parenthesized(exponent, POWER);
+                    }
+                }
+            }
+
+            Node::Root(radicand, index) => {
+                if self.unicode() {
+                    let symbol = match index {
+                        2 => "√".to_string(),
+                        3 => "∛".to_string(),
+                        4 => "∜".to_string(),
+                        _ => superscript(&Node::Int((*index).into())).unwrap() + "√",
+                    };
+                    self.emit(Class::Operator, &symbol);
+                    self.parenthesized(radicand, ATOM);
+                } else if *index == 2 {
+                    self.emit(Class::Function, "sqrt");
+                    self.emit(Class::Bracket, "(");
+                    self.node(radicand);
+                    self.emit(Class::Bracket, ")");
+                } else {
+                    self.parenthesized(radicand, ATOM);
+                    self.emit(Class::Operator, "^");
+                    self.emit(Class::Bracket, "(");
+                    self.emit(Class::Constant, "1");
+                    self.emit(Class::Operator, "/");
+                    self.emit(Class::Constant, &index.to_string());
+                    self.emit(Class::Bracket, ")");
+                }
+            }
+
+            Node::Call(name, args) => {
+                self.emit(Class::Function, name);
+                self.emit(Class::Bracket, "(");
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        self.emit(Class::Operator, ", ");
+                    }
+                    self.node(arg);
+                }
+                self.emit(Class::Bracket, ")");
+            }
+
+            Node::Var(name) => self.emit(Class::Variable, name),
+
+            Node::Int(n) => self.emit(Class::Constant, &n.to_string()),
+
+            Node::Symbol(num) => {
+                let symbol = match (num, self.unicode()) {
+                    (Num::Pi, true) => "π",
+                    (Num::Pi, false) => "pi",
+                    (Num::E, _) => "e",
+                    (Num::I, _) => "i",
+                    (Num::Infinity, true) => "∞",
+                    (Num::Infinity, false) => "inf",
+                    _ => "undefined",
+                };
+                self.emit(Class::Constant, symbol);
+            }
+        }
+    }
+
+    /// Prints `node`, wrapped in parentheses if it binds looser than `min`.
+    fn parenthesized(&mut self, node: &Node, min: u8) {
+        if node.precedence() < min {
+            self.emit(Class::Bracket, "(");
+            self.node(node);
+            self.emit(Class::Bracket, ")");
+        } else {
+            self.node(node);
+        }
+    }
+
+    /// Whether `node` is printed starting with a digit when it binds at least as tight as `min`.
+    fn starts_with_digit(&self, node: &Node, min: u8) -> bool {
+        if node.precedence() < min {
+            return false;
+        }
+
+        match node {
+            Node::Int(_) => true,
+            Node::Product(factors) => self.starts_with_digit(&factors[0], PRODUCT),
+            Node::Frac(num, _) => self.starts_with_digit(num, PRODUCT),
+            Node::Pow(base, _) => self.starts_with_digit(base, ATOM),
+            Node::Root(radicand, index) => {
+                !self.unicode() && *index != 2 && self.starts_with_digit(radicand, ATOM)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Writes an integer exponent in superscript digits, e.g. `²`.
+fn superscript(exponent: &Node) -> Option<String> {
+    const DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+
+    match exponent {
+        Node::Int(n) => Some(
+            n.to_string()
+                .chars()
+                .map(|c| DIGITS[c.to_digit(10).unwrap() as usize])
+                .collect(),
+        ),
+        _ => None,
+    }
+}
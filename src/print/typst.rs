@@ -0,0 +1,144 @@
+use crate::{expr::Expr, num::Num};
+
+use super::{is_symbol, parenthesized, Node, ATOM, OPERATORS, PRODUCT, SUM};
+
+const PARENS: (&str, &str) = ("(", ")");
+
+impl Expr {
+    /// Renders the expression as Typst math, to be placed between `$` signs, e.g.
+    /// `frac(x^2, 2) - sin(y)`.
+    pub fn to_typst(&self) -> String {
+        let mut out = String::new();
+        typst(&Node::from_expr(self), &mut out);
+        out
+    }
+}
+
+fn typst(node: &Node, out: &mut String) {
+    match node {
+        Node::Sum(terms) => {
+            for (i, (negative, term)) in terms.iter().enumerate() {
+                match (i, negative) {
+                    (0, false) => {}
+                    (0, true) => out.push('-'),
+                    (_, false) => out.push_str(" + "),
+                    (_, true) => out.push_str(" - "),
+                }
+
+                let min = if *negative { PRODUCT } else { SUM + 1 };
+                parenthesized(term, min, PARENS, typst, out);
+            }
+        }
+
+        Node::Product(factors) => {
+            for (i, factor) in factors.iter().enumerate() {
+                let mut rendered = String::new();
+                parenthesized(factor, PRODUCT, PARENS, typst, &mut rendered);
+
+                if i > 0 {
+                    if factor.starts_with_number() {
+                        out.push_str(" dot.op ");
+                    } else {
+                        out.push(' ');
+                    }
+                }
+                out.push_str(&rendered);
+            }
+        }
+
+        Node::Frac(num, den) => {
+            out.push_str("frac(");
+            typst(num, out);
+            out.push_str(", ");
+            typst(den, out);
+            out.push(')');
+        }
+
+        Node::Neg(inner) => {
+            out.push('-');
+            parenthesized(inner, PRODUCT, PARENS, typst, out);
+        }
+
+        Node::Pow(base, exponent) => {
+            parenthesized(base, ATOM, PARENS, typst, out);
+            out.push('^');
+            // Typst drops the parentheses around an exponent.
+            parenthesized(exponent, ATOM, PARENS, typst, out);
+        }
+
+        Node::Root(radicand, 2) => {
+            out.push_str("sqrt(");
+            typst(radicand, out);
+            out.push(')');
+        }
+
+        Node::Root(radicand, index) => {
+            out.push_str(&format!("root({index}, "));
+            typst(radicand, out);
+            out.push(')');
+        }
+
+        Node::Call(name, args) => match (name.as_str(), args.as_slice()) {
+            ("abs", [arg]) => {
+                out.push_str("abs(");
+                typst(arg, out);
+                out.push(')');
+            }
+            ("root", [index, radicand]) => {
+                out.push_str("root(");
+                typst(index, out);
+                out.push_str(", ");
+                typst(radicand, out);
+                out.push(')');
+            }
+            ("log", [base, arg]) => {
+                out.push_str("log_");
+                parenthesized(base, ATOM, PARENS, typst, out);
+                out.push(' ');
+                typst_args(std::slice::from_ref(arg), out);
+            }
+            _ => {
+                if OPERATORS.contains(&name.as_str()) || is_symbol(name) {
+                    out.push_str(name);
+                } else {
+                    out.push_str(&format!("op({})", quote(name)));
+                }
+                typst_args(args, out);
+            }
+        },
+
+        Node::Var(name) => {
+            if is_symbol(name) {
+                out.push_str(name);
+            } else {
+                out.push_str(&quote(name));
+            }
+        }
+
+        Node::Int(n) => out.push_str(&n.to_string()),
+
+        Node::Symbol(num) => out.push_str(match num {
+            Num::Pi => "pi",
+            Num::E => "e",
+            Num::I => "i",
+            Num::Infinity => "infinity",
+            _ => "\"undefined\"",
+        }),
+    }
+}
+
+fn typst_args(args: &[Node], out: &mut String) {
+    out.push('(');
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        typst(arg, out);
+    }
+    out.push(')');
+}
+
+/// Writes `text` as a Typst string, which is typeset upright.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,404 @@
+use num_bigint::{BigInt, BigUint};
+use num_traits::One;
+use std::cmp::Ordering;
+
+use crate::{expr::Expr, num::Num};
+
+impl Expr {
+    /// Simplifies the expression into a canonical form.
+    ///
+    /// On top of [`Expr::simplify_trivial`], this folds rational constants exactly, collects
+    /// like terms (`5x + x -> 6x`), combines repeated factors into powers (`x * x -> x^2`) and
+    /// sorts the operands of every sum and product, so that two expressions which only differ
+    /// in these respects simplify to the same tree.
+    pub fn simplify(&self) -> Expr {
+        match self {
+            Expr::Sum(terms) => Self::simplify_sum(terms.iter().map(Expr::simplify).collect()),
+
+            Expr::Product(factors) => {
+                Self::simplify_product(factors.iter().map(Expr::simplify).collect())
+            }
+
+            Expr::Pow(base, exponent) => Self::simplify_pow(base.simplify(), exponent.simplify()),
+
+            Expr::Function(def, args) => {
+                Expr::Function(def.clone(), args.iter().map(Expr::simplify).collect())
+            }
+
+            Expr::Var(_) => self.clone(),
+
+            Expr::Const(num) => Expr::Const(num.reduce()),
+        }
+    }
+
+    /// Simplifies a sum whose operands are already simplified.
+    fn simplify_sum(terms: Vec<Expr>) -> Expr {
+        let mut constant = Num::Zero;
+        // Each distinct non-constant term along with its accumulated rational coefficient.
+        let mut collected: Vec<(Expr, Num)> = vec![];
+
+        let flattened = terms.into_iter().flat_map(|term| match term {
+            Expr::Sum(subterms) => subterms,
+            _ => vec![term],
+        });
+
+        for term in flattened {
+            if let Expr::Const(num) = &term {
+                if num.is_undefined() {
+                    return Expr::Const(Num::Undefined);
+                }
+                // Radicals are collected like any other term, so that 1 + \sqrt{2} stays a sum.
+                if let Some(sum) = fold_add(&constant, num).filter(|_| !is_radical(num)) {
+                    constant = sum;
+                    continue;
+                }
+            }
+
+            let (coefficient, rest) = term.split_coefficient();
+
+            match collected
+                .iter_mut()
+                .find(|(other, _)| Expr::exact_match(&rest, other))
+            {
+                Some((_, total)) => match fold_add(total, &coefficient) {
+                    Some(sum) => *total = sum,
+                    None => collected.push((rest, coefficient)),
+                },
+                None => collected.push((rest, coefficient)),
+            }
+        }
+
+        // Positive infinities are folded into the constant, while negative ones are collected
+        // like terms, so `inf - inf` has to be caught here.
+        let mut infinite_signs = collected
+            .iter()
+            .filter_map(|(rest, coefficient)| match (rest, coefficient) {
+                (Expr::Const(Num::Infinity), sign) | (Expr::Const(sign), Num::Infinity) => {
+                    sign.signum()
+                }
+                _ => None,
+            })
+            .chain(matches!(constant, Num::Infinity).then_some(1));
+        if infinite_signs.clone().any(|sign| sign > 0) && infinite_signs.any(|sign| sign < 0) {
+            return Expr::Const(Num::Undefined);
+        }
+
+        collected.sort_by(|(a, coefficient_a), (b, coefficient_b)| {
+            a.canonical_cmp(b)
+                .then_with(|| cmp_num(coefficient_a, coefficient_b))
+        });
+
+        let mut result: Vec<Expr> = collected
+            .into_iter()
+            .filter(|(_, coefficient)| !coefficient.is_zero())
+            .map(|(rest, coefficient)| rest.with_coefficient(coefficient))
+            .collect();
+
+        if !constant.is_zero() {
+            result.push(Expr::Const(constant));
+        }
+
+        match result.len() {
+            0 => Expr::Const(Num::Zero),
+            1 => result.pop().unwrap(),
+            _ => Expr::Sum(result),
+        }
+    }
+
+    /// Simplifies a product whose operands are already simplified.
+    fn simplify_product(factors: Vec<Expr>) -> Expr {
+        let mut coefficient = Num::One;
+        // Each distinct base along with the exponents it appears with.
+        let mut powers: Vec<(Expr, Vec<Expr>)> = vec![];
+        let mut pending = factors;
+
+        while let Some(factor) = pending.pop() {
+            let (base, exponent) = match factor {
+                Expr::Product(subfactors) => {
+                    pending.extend(subfactors);
+                    continue;
+                }
+                Expr::Const(num) if num.is_undefined() => return Expr::Const(Num::Undefined),
+                Expr::Const(num) if fold_mul(&coefficient, &num).is_some() => {
+                    coefficient = fold_mul(&coefficient, &num).unwrap();
+                    continue;
+                }
+                Expr::Pow(base, exponent) => (*base, *exponent),
+                _ => (factor, Expr::Const(Num::One)),
+            };
+
+            match powers
+                .iter_mut()
+                .find(|(other, _)| Expr::exact_match(&base, other))
+            {
+                Some((_, exponents)) => exponents.push(exponent),
+                None => powers.push((base, vec![exponent])),
+            }
+        }
+
+        if coefficient.is_zero() {
+            return Expr::Const(Num::Zero);
+        }
+
+        let mut factors = vec![];
+        let mut needs_another_pass = false;
+
+        for (base, mut exponents) in powers {
+            let exponent = if exponents.len() == 1 {
+                exponents.pop().unwrap()
+            } else {
+                // x^a * x^b = x^(a + b)
+                Self::simplify_sum(exponents)
+            };
+
+            // Collapsed powers may produce rationals or products that must be folded again,
+            // e.g. sqrt(2) * sqrt(2) = 2.
+            match Self::simplify_pow(base, exponent) {
+                Expr::Const(num) if num.is_undefined() => return Expr::Const(Num::Undefined),
+                Expr::Const(num) if fold_mul(&coefficient, &num).is_some() => {
+                    coefficient = fold_mul(&coefficient, &num).unwrap();
+                }
+                factor @ Expr::Product(_) => {
+                    needs_another_pass = true;
+                    factors.push(factor);
+                }
+                factor => factors.push(factor),
+            }
+        }
+
+        if coefficient.is_zero() {
+            return Expr::Const(Num::Zero);
+        }
+
+        if needs_another_pass {
+            factors.push(Expr::Const(coefficient));
+            return Self::simplify_product(factors);
+        }
+
+        factors.sort_by(Expr::canonical_cmp);
+        if !coefficient.is_one() {
+            factors.insert(0, Expr::Const(coefficient));
+        }
+
+        match factors.len() {
+            0 => Expr::Const(Num::One),
+            1 => factors.pop().unwrap(),
+            _ => Expr::Product(factors),
+        }
+    }
+
+    /// Simplifies a power whose base and exponent are already simplified.
+    fn simplify_pow(base: Expr, exponent: Expr) -> Expr {
+        if let Expr::Const(n) = &exponent {
+            // x^0 = 1, x^1 = x
+            if n.is_zero() {
+                return Expr::Const(Num::One);
+            }
+            if n.is_one() {
+                return base;
+            }
+        }
+
+        let integer_exponent = match &exponent {
+            Expr::Const(n) => n.as_integer(),
+            _ => None,
+        };
+
+        match (&base, integer_exponent) {
+            // 1^x = 1
+            (Expr::Const(b), _) if b.is_one() => Expr::Const(Num::One),
+
+            // Exact powers of constants, e.g. (2/3)^-2 = 9/4 or 8^(1/3) = 2.
+            (Expr::Const(b), _) => match &exponent {
+                Expr::Const(e) => match b.checked_pow(e) {
+                    Some(power) => Expr::Const(power),
+                    None => base.pow(exponent),
+                },
+                _ => base.pow(exponent),
+            },
+
+            // (x^a)^n = x^(a*n) and (xy)^n = x^n y^n, which only hold in general for integer n.
+            (Expr::Pow(inner_base, inner_exponent), Some(_)) => Self::simplify_pow(
+                inner_base.as_ref().clone(),
+                Self::simplify_product(vec![inner_exponent.as_ref().clone(), exponent]),
+            ),
+            (Expr::Product(factors), Some(_)) => Self::simplify_product(
+                factors
+                    .iter()
+                    .map(|factor| Self::simplify_pow(factor.clone(), exponent.clone()))
+                    .collect(),
+            ),
+
+            _ => base.pow(exponent),
+        }
+    }
+
+    /// Splits a term into its rational coefficient and the remaining factors.
+    fn split_coefficient(self) -> (Num, Expr) {
+        match self {
+            Expr::Product(factors) => {
+                let mut coefficient = Num::One;
+                let mut rest = vec![];
+
+                for factor in factors {
+                    match &factor {
+                        Expr::Const(num) => match fold_mul(&coefficient, num) {
+                            Some(product) => coefficient = product,
+                            None => rest.push(factor),
+                        },
+                        _ => rest.push(factor),
+                    }
+                }
+
+                let rest = match rest.len() {
+                    0 => Expr::Const(Num::One),
+                    1 => rest.pop().unwrap(),
+                    _ => Expr::Product(rest),
+                };
+
+                (coefficient, rest)
+            }
+            // 2\sqrt{3} is treated as 2 times the term \sqrt{3}.
+            Expr::Const(Num::Radical {
+                num,
+                den,
+                radicand,
+                index,
+            }) => (
+                Num::rational(num, den),
+                Expr::Const(Num::Radical {
+                    num: BigInt::one(),
+                    den: BigUint::one(),
+                    radicand,
+                    index,
+                }),
+            ),
+            _ => (Num::One, self),
+        }
+    }
+
+    /// Multiplies a term by a rational coefficient, keeping products flat.
+    fn with_coefficient(self, coefficient: Num) -> Expr {
+        if coefficient.is_one() {
+            return self;
+        }
+
+        match self {
+            Expr::Const(n) if fold_mul(&coefficient, &n).is_some() => {
+                Expr::Const(fold_mul(&coefficient, &n).unwrap())
+            }
+            Expr::Product(mut factors) => {
+                factors.insert(0, Expr::Const(coefficient));
+                Expr::Product(factors)
+            }
+            _ => Expr::Product(vec![Expr::Const(coefficient), self]),
+        }
+    }
+
+    /// A total order on expressions, used to sort the operands of sums and products.
+    ///
+    /// Constants come first, followed by variables, powers, products, sums and function calls.
+    pub fn canonical_cmp(&self, other: &Expr) -> Ordering {
+        fn rank(expr: &Expr) -> u8 {
+            match expr {
+                Expr::Const(_) => 0,
+                Expr::Var(_) => 1,
+                Expr::Pow(..) => 2,
+                Expr::Product(_) => 3,
+                Expr::Sum(_) => 4,
+                Expr::Function(..) => 5,
+            }
+        }
+
+        fn cmp_all(a: &[Expr], b: &[Expr]) -> Ordering {
+            a.iter()
+                .zip(b)
+                .map(|(a, b)| a.canonical_cmp(b))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len()))
+        }
+
+        match (self, other) {
+            (Expr::Const(a), Expr::Const(b)) => cmp_num(a, b),
+            (Expr::Var(a), Expr::Var(b)) => a.get_name().cmp(&b.get_name()),
+            (Expr::Pow(base_a, exponent_a), Expr::Pow(base_b, exponent_b)) => base_a
+                .canonical_cmp(base_b)
+                .then_with(|| exponent_a.canonical_cmp(exponent_b)),
+            (Expr::Product(a), Expr::Product(b)) | (Expr::Sum(a), Expr::Sum(b)) => cmp_all(a, b),
+            (Expr::Function(def_a, args_a), Expr::Function(def_b, args_b)) => def_a
+                .name()
+                .cmp(&def_b.name())
+                .then_with(|| cmp_all(args_a, args_b)),
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+}
+
+/// Orders rationals by value, followed by the other constants.
+fn cmp_num(a: &Num, b: &Num) -> Ordering {
+    fn rank(num: &Num) -> u8 {
+        match num {
+            Num::Zero | Num::One | Num::Rational { .. } => 0,
+            Num::Radical { .. } => 1,
+            Num::Pi => 2,
+            Num::E => 3,
+            Num::I => 4,
+            Num::Infinity => 5,
+            Num::Undefined => 6,
+        }
+    }
+
+    match (a.as_fraction(), b.as_fraction()) {
+        (Some((num_a, den_a)), Some((num_b, den_b))) => (num_a * den_b).cmp(&(num_b * den_a)),
+        _ => match (a, b) {
+            (
+                Num::Radical {
+                    num: num_a,
+                    den: den_a,
+                    radicand: radicand_a,
+                    index: index_a,
+                },
+                Num::Radical {
+                    num: num_b,
+                    den: den_b,
+                    radicand: radicand_b,
+                    index: index_b,
+                },
+            ) => index_a
+                .cmp(index_b)
+                .then(radicand_a.cmp(radicand_b))
+                .then_with(|| {
+                    (num_a * BigInt::from(den_b.clone()))
+                        .cmp(&(num_b * BigInt::from(den_a.clone())))
+                }),
+            _ => rank(a).cmp(&rank(b)),
+        },
+    }
+}
+
+/// Whether a constant takes part in constant folding. Transcendental constants are kept
+/// symbolic so that e.g. `2π + π` collects into `3π`.
+fn is_foldable(num: &Num) -> bool {
+    num.as_fraction().is_some()
+        || matches!(num, Num::Radical { .. } | Num::Infinity | Num::Undefined)
+}
+
+fn is_radical(num: &Num) -> bool {
+    matches!(num, Num::Radical { .. })
+}
+
+fn fold_add(a: &Num, b: &Num) -> Option<Num> {
+    if is_foldable(a) && is_foldable(b) {
+        a.checked_add(b)
+    } else {
+        None
+    }
+}
+
+fn fold_mul(a: &Num, b: &Num) -> Option<Num> {
+    if is_foldable(a) && is_foldable(b) {
+        a.checked_mul(b)
+    } else {
+        None
+    }
+}
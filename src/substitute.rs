@@ -0,0 +1,96 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    expr::Expr,
+    var::{Var, VarMap},
+};
+
+impl Expr {
+    /// Replaces every occurrence of `var` with `replacement`.
+    pub fn substitute(&self, var: &Var, replacement: &Expr) -> Expr {
+        self.substitute_all(&HashMap::from([(var, replacement.clone())]))
+    }
+
+    /// Replaces every variable in `replacements` with its expression, simultaneously, so that
+    /// e.g. swapping `x` and `y` works as expected.
+    ///
+    /// Substitution descends into function arguments and into the bodies of user-defined
+    /// functions, where parameters shadow the variables being replaced. Parameters that would
+    /// capture a variable of a replacement are renamed first.
+    pub fn substitute_all(&self, replacements: &VarMap<Expr>) -> Expr {
+        if replacements.is_empty() {
+            return self.clone();
+        }
+
+        match self {
+            Expr::Sum(terms) => Expr::Sum(
+                terms
+                    .iter()
+                    .map(|term| term.substitute_all(replacements))
+                    .collect(),
+            ),
+
+            Expr::Product(factors) => Expr::Product(
+                factors
+                    .iter()
+                    .map(|factor| factor.substitute_all(replacements))
+                    .collect(),
+            ),
+
+            Expr::Pow(base, exponent) => base
+                .substitute_all(replacements)
+                .pow(exponent.substitute_all(replacements)),
+
+            Expr::Var(var) => match replacements.get(var.as_ref()) {
+                Some(replacement) => replacement.clone(),
+                None => self.clone(),
+            },
+
+            Expr::Const(_) => self.clone(),
+
+            Expr::Function(def, args) => Expr::Function(
+                def.substitute(replacements).unwrap_or_else(|| def.clone()),
+                args.iter()
+                    .map(|arg| arg.substitute_all(replacements))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Returns the variables the expression depends on, including the free variables in the
+    /// bodies of user-defined functions.
+    pub fn variables(&self) -> HashSet<Var> {
+        let mut variables = HashSet::new();
+        self.collect_variables(&mut variables);
+        variables
+    }
+
+    fn collect_variables(&self, variables: &mut HashSet<Var>) {
+        match self {
+            Expr::Sum(terms) | Expr::Product(terms) => {
+                for term in terms {
+                    term.collect_variables(variables);
+                }
+            }
+
+            Expr::Pow(base, exponent) => {
+                base.collect_variables(variables);
+                exponent.collect_variables(variables);
+            }
+
+            Expr::Var(var) => {
+                variables.insert(var.as_ref().clone());
+            }
+
+            Expr::Const(_) => {}
+
+            Expr::Function(def, args) => {
+                variables.extend(def.free_variables());
+
+                for arg in args {
+                    arg.collect_variables(variables);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! Reverse-mode automatic differentiation, which records an evaluation on a tape and then
+//! propagates derivatives back through it.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use num_complex::Complex64;
+
+use crate::{
+    dual::{self, Dual},
+    expr::{self, EvalError, Expr},
+    function::{Arity, FuncDef},
+    var::{Var, VarMap},
+};
+
+impl Expr {
+    /// Evaluates the expression along with its gradient, which holds the partial derivative
+    /// with respect to every variable in `var_values`, in time proportional to a single
+    /// evaluation however many variables there are.
+    ///
+    /// Functions are differentiated forward along their arguments, see [`Expr::eval_dual`], so
+    /// a function with many arguments costs more.
+    pub fn eval_gradient<'v>(
+        &self,
+        var_values: &VarMap<'v, Complex64>,
+    ) -> Result<(Complex64, VarMap<'v, Complex64>), EvalError> {
+        let mut tape = Tape {
+            var_values,
+            leaves: HashMap::with_capacity(var_values.len()),
+            nodes: Vec::with_capacity(var_values.len()),
+        };
+        for (var, value) in var_values {
+            tape.nodes.push(vec![]);
+            let leaf = Tracked {
+                value: *value,
+                node: Some(tape.nodes.len() - 1),
+            };
+            tape.leaves.insert(*var, leaf);
+        }
+
+        let result = tape.record(self)?;
+        let adjoints = tape.adjoints(result);
+
+        let gradient = tape
+            .leaves
+            .iter()
+            .map(|(var, leaf)| {
+                let adjoint = leaf.node.and_then(|node| adjoints.get(node));
+                (*var, adjoint.copied().unwrap_or(Complex64::new(0.0, 0.0)))
+            })
+            .collect();
+
+        Ok((result.value, gradient))
+    }
+}
+
+/// The nodes of an evaluation, where each node lists the nodes it was computed from along with
+/// the partial derivatives with respect to them.
+struct Tape<'a, 'v> {
+    var_values: &'a VarMap<'v, Complex64>,
+    leaves: HashMap<&'v Var, Tracked>,
+    nodes: Vec<Vec<(usize, Complex64)>>,
+}
+
+/// A value along with the node it is recorded in, if it depends on any variable.
+#[derive(Debug, Clone, Copy)]
+struct Tracked {
+    value: Complex64,
+    node: Option<usize>,
+}
+
+impl Tracked {
+    fn constant(value: Complex64) -> Self {
+        Self { value, node: None }
+    }
+}
+
+impl Tape<'_, '_> {
+    /// Records a node computed from `parents` with the given partial derivatives, leaving out
+    /// the parents that are constants.
+    fn push(&mut self, value: Complex64, parents: Vec<(Tracked, Complex64)>) -> Tracked {
+        let edges: Vec<(usize, Complex64)> = parents
+            .into_iter()
+            .filter_map(|(parent, partial)| parent.node.map(|node| (node, partial)))
+            .collect();
+        if edges.is_empty() {
+            return Tracked::constant(value);
+        }
+
+        self.nodes.push(edges);
+        Tracked {
+            value,
+            node: Some(self.nodes.len() - 1),
+        }
+    }
+
+    fn record(&mut self, expr: &Expr) -> Result<Tracked, EvalError> {
+        match expr {
+            Expr::Sum(terms) => {
+                let terms = terms
+                    .iter()
+                    .map(|term| self.record(term))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let value = expr.defined(terms.iter().map(|term| term.value).sum())?;
+                let one = Complex64::new(1.0, 0.0);
+
+                Ok(self.push(value, terms.into_iter().map(|term| (term, one)).collect()))
+            }
+
+            Expr::Product(terms) => {
+                let terms = terms
+                    .iter()
+                    .map(|term| self.record(term))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                // The partial derivative with respect to a term is the product of all the others,
+                // which is the product of the terms before it and the terms after it.
+                let mut after = vec![Complex64::new(1.0, 0.0); terms.len() + 1];
+                for (i, term) in terms.iter().enumerate().rev() {
+                    after[i] = after[i + 1] * term.value;
+                }
+                let value = expr.defined(after[0])?;
+
+                let mut before = Complex64::new(1.0, 0.0);
+                let mut parents = Vec::with_capacity(terms.len());
+                for (i, term) in terms.into_iter().enumerate() {
+                    parents.push((term, before * after[i + 1]));
+                    before *= term.value;
+                }
+
+                Ok(self.push(value, parents))
+            }
+
+            Expr::Pow(base_expr, exponent) => {
+                let base = self.record(base_expr)?;
+                let exponent = self.record(exponent)?;
+                let (u, v) = (base.value, exponent.value);
+
+                if u == Complex64::new(0.0, 0.0) && v.re < 0.0 {
+                    return Err(EvalError::DivisionByZero {
+                        divisor: base_expr.as_ref().clone(),
+                    });
+                }
+
+                let value = expr.defined(expr::pow(u, v))?;
+                let mut parents = vec![];
+                if base.node.is_some() && v != Complex64::new(0.0, 0.0) {
+                    parents.push((base, v * expr::pow(u, v - 1.0)));
+                }
+                if exponent.node.is_some() {
+                    // u^v ln(u) tends to 0 as u does when v > 0, which is when u^v = 0.
+                    let partial = if value == Complex64::new(0.0, 0.0) {
+                        value
+                    } else {
+                        value * u.ln()
+                    };
+                    parents.push((exponent, partial));
+                }
+
+                Ok(self.push(value, parents))
+            }
+
+            Expr::Var(var) => self
+                .leaves
+                .get(var.as_ref())
+                .copied()
+                .ok_or(EvalError::VarMissing {
+                    name: var.get_name(),
+                }),
+
+            Expr::Const(num) => Ok(Tracked::constant(num.eval_float()?)),
+
+            Expr::Function(def, args) => {
+                Arity::check(def.as_ref(), args.len())?;
+
+                let args = args
+                    .iter()
+                    .map(|arg| self.record(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                match def.as_dual() {
+                    Some(dual_def) => self.forward(dual_def, def.free_variables(), args),
+                    None => self.symbolic(def, args),
+                }
+            }
+        }
+    }
+
+    /// Applies a function by differentiating it forward along its arguments and the global
+    /// variables it uses.
+    fn forward(
+        &mut self,
+        def: &dyn FuncDef<Dual>,
+        globals: Vec<Var>,
+        args: Vec<Tracked>,
+    ) -> Result<Tracked, EvalError> {
+        let globals: Vec<(&Var, Tracked)> = globals
+            .iter()
+            .filter_map(|var| self.leaves.get_key_value(var))
+            .map(|(var, leaf)| (*var, *leaf))
+            .collect();
+        let count = args.len() + globals.len();
+
+        let dual_args = args
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| Dual::variable(arg.value, i, count))
+            .collect();
+        let dual_globals: VarMap<Dual> = globals
+            .iter()
+            .enumerate()
+            .map(|(i, (var, leaf))| (*var, Dual::variable(leaf.value, args.len() + i, count)))
+            .collect();
+
+        let result = def.eval(dual_args, &dual_globals)?;
+        let parents = args
+            .into_iter()
+            .chain(globals.into_iter().map(|(_, leaf)| leaf))
+            .enumerate()
+            .map(|(i, parent)| (parent, result.partial(i)))
+            .collect();
+
+        Ok(self.push(result.value(), parents))
+    }
+
+    /// Applies a function without a dual number version using its symbolic partial
+    /// derivatives.
+    fn symbolic(
+        &mut self,
+        def: &Arc<dyn FuncDef>,
+        args: Vec<Tracked>,
+    ) -> Result<Tracked, EvalError> {
+        let values: Vec<Complex64> = args.iter().map(|arg| arg.value).collect();
+        let varying: Vec<bool> = args.iter().map(|arg| arg.node.is_some()).collect();
+        let partials =
+            dual::symbolic_partials(def.as_ref(), &values, &varying, self.var_values, false)?;
+
+        let parents = args
+            .into_iter()
+            .zip(partials.first)
+            .filter(|(arg, _)| arg.node.is_some())
+            .collect();
+
+        Ok(self.push(partials.value, parents))
+    }
+
+    /// The partial derivatives of `output` with respect to every node up to it, or nothing if it
+    /// is a constant.
+    fn adjoints(&self, output: Tracked) -> Vec<Complex64> {
+        let Some(output) = output.node else {
+            return vec![];
+        };
+
+        let mut adjoints = vec![Complex64::new(0.0, 0.0); output + 1];
+        adjoints[output] = Complex64::new(1.0, 0.0);
+
+        // Every node comes after its parents, so its adjoint is complete once it is reached.
+        for node in (0..=output).rev() {
+            let adjoint = adjoints[node];
+            if adjoint == Complex64::new(0.0, 0.0) {
+                continue;
+            }
+
+            for (parent, partial) in &self.nodes[node] {
+                adjoints[*parent] += adjoint * partial;
+            }
+        }
+
+        adjoints
+    }
+}
@@ -1,3 +1,30 @@
+/// Helpers shared between the test modules below.
+#[cfg(test)]
+mod fixtures {
+    use num_complex::Complex64;
+
+    use crate::{
+        parse::parse,
+        poly::{Polynomial, UnivariatePolynomial},
+        var::Var,
+    };
+
+    /// Whether `a` is within a small relative tolerance of the expected value `b`.
+    pub(super) fn close(a: Complex64, b: Complex64) -> bool {
+        (a - b).norm() <= 1e-9 * (1.0 + b.norm())
+    }
+
+    /// Parses `input` as a polynomial in `vars`.
+    pub(super) fn polynomial(input: &str, vars: &[&Var]) -> Polynomial {
+        parse(input).unwrap().to_polynomial(vars).unwrap()
+    }
+
+    /// Parses `input` as a polynomial in `x` alone.
+    pub(super) fn univariate(input: &str, x: &Var) -> UnivariatePolynomial {
+        polynomial(input, &[x]).to_univariate().unwrap()
+    }
+}
+
 #[cfg(test)]
 mod exact_match {
     use crate::{
@@ -116,3 +143,3410 @@ mod simplify {
         ));
     }
 }
+
+#[cfg(test)]
+mod parse {
+    use std::sync::Arc;
+
+    use crate::{
+        expr::Expr::{self, *},
+        function::{Arity, Function},
+        num::Num::{self, *},
+        parse::{parse, ParseErrorKind, Span},
+        var::Var,
+    };
+
+    fn var(name: &str) -> Expr {
+        Var(Var::new(name))
+    }
+
+    #[test]
+    fn precedence() {
+        assert!(Expr::exact_match(
+            &parse("x*5 + sin(y)^2").unwrap(),
+            &Sum(vec![
+                Product(vec![var("x"), Const(Num::from(5))]),
+                Function(Arc::new(Function::Sin), vec![var("y")]).pow(Const(Num::from(2))),
+            ]),
+        ));
+        assert!(Expr::exact_match(
+            &parse("1 + 2 * (x - y)").unwrap(),
+            &Sum(vec![
+                Const(One),
+                Product(vec![
+                    Const(Num::from(2)),
+                    Sum(vec![
+                        var("x"),
+                        Product(vec![Const(Num::from(-1)), var("y")])
+                    ]),
+                ]),
+            ]),
+        ));
+    }
+
+    #[test]
+    fn unary_minus_binds_looser_than_power() {
+        assert!(Expr::exact_match(
+            &parse("-x^2").unwrap(),
+            &Product(vec![
+                Const(Num::from(-1)),
+                var("x").pow(Const(Num::from(2)))
+            ]),
+        ));
+        assert!(Expr::exact_match(
+            &parse("-3").unwrap(),
+            &Const(Num::from(-3))
+        ));
+    }
+
+    #[test]
+    fn implicit_multiplication() {
+        assert!(Expr::exact_match(
+            &parse("2x(y + 1)").unwrap(),
+            &Product(vec![
+                Const(Num::from(2)),
+                var("x"),
+                Sum(vec![var("y"), Const(One)]),
+            ]),
+        ));
+    }
+
+    #[test]
+    fn constants_and_rationals() {
+        assert!(Expr::exact_match(
+            &parse("pi + e + i + inf + 3/4 + 2.5").unwrap(),
+            &Sum(vec![
+                Const(Pi),
+                Const(E),
+                Const(I),
+                Const(Infinity),
+                Const(Num::rational(3, 4)),
+                Const(Num::rational(5, 2)),
+            ]),
+        ));
+        assert!(Expr::exact_match(
+            &parse("x / 2").unwrap(),
+            &Product(vec![var("x"), Const(Num::rational(1, 2))]),
+        ));
+    }
+
+    #[test]
+    fn powers_and_division() {
+        assert!(Expr::exact_match(
+            &parse("x^y^2").unwrap(),
+            &var("x").pow(var("y").pow(Const(Num::from(2)))),
+        ));
+        assert!(Expr::exact_match(
+            &parse("x^-1").unwrap(),
+            &var("x").pow(Const(Num::from(-1))),
+        ));
+        assert!(Expr::exact_match(
+            &parse("1 / x").unwrap(),
+            &Product(vec![Const(One), var("x").pow(Const(Num::from(-1)))]),
+        ));
+        assert!(Expr::exact_match(
+            &parse("sqrt(x)").unwrap(),
+            &var("x").pow(Const(Num::rational(1, 2))),
+        ));
+    }
+
+    #[test]
+    fn from_str_evaluates() {
+        let x = Var::new("x");
+        let expr: Expr = "exp(ln(x)) + 2x".parse().unwrap();
+
+        let mut ctx = std::collections::HashMap::new();
+        ctx.insert(x.as_ref(), num_complex::Complex64::new(3.0, 0.0));
+
+        assert!((expr.eval(&ctx).unwrap().re - 9.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn errors_carry_spans() {
+        let err = parse("1 + ").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::UnexpectedEnd);
+        assert_eq!(err.span, Span { start: 4, end: 4 });
+
+        let err = parse("foo(x)").unwrap_err();
+        assert_eq!(
+            err.kind,
+            ParseErrorKind::UnknownFunction {
+                name: "foo".to_string()
+            }
+        );
+        assert_eq!(err.span, Span { start: 0, end: 3 });
+
+        let err = parse("2 * (x + 1").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::UnclosedParen);
+        assert_eq!(err.span, Span { start: 4, end: 5 });
+
+        let err = parse("x $ y").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::UnexpectedChar('$'));
+        assert_eq!(err.span, Span { start: 2, end: 3 });
+
+        let err = parse("sin(x, y)").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ParseErrorKind::FnArgCountMismatch {
+                expected: Arity::Exactly(1),
+                found: 2,
+                ..
+            }
+        ));
+
+        let err = parse("x)").unwrap_err();
+        assert_eq!(err.span, Span { start: 1, end: 2 });
+    }
+}
+
+#[cfg(test)]
+mod pow {
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+
+    use crate::{
+        expr::Expr::{self, *},
+        num::Num::{self, *},
+        parse::parse,
+        var::Var,
+    };
+
+    fn var(name: &str) -> Expr {
+        Var(Var::new(name))
+    }
+
+    #[test]
+    fn eval() {
+        let x = Var::new("x");
+        let mut ctx = HashMap::new();
+        ctx.insert(x.as_ref(), Complex64::new(2.0, 0.0));
+
+        assert_eq!(
+            parse("x^10").unwrap().eval(&ctx).unwrap(),
+            Complex64::new(1024.0, 0.0)
+        );
+        assert_eq!(
+            parse("x^-2").unwrap().eval(&ctx).unwrap(),
+            Complex64::new(0.25, 0.0)
+        );
+        assert!((parse("x^0.5").unwrap().eval(&ctx).unwrap().re - 2f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn derivative() {
+        let x = Var::new("x");
+        let mut ctx = HashMap::new();
+        ctx.insert(x.as_ref(), Complex64::new(1.5, 0.0));
+
+        // d/dx x^3 = 3x^2
+        let d = parse("x^3").unwrap().derivative(&x);
+        assert!((d.eval(&ctx).unwrap().re - 3.0 * 1.5f64.powi(2)).abs() < 1e-12);
+
+        // d/dx x^x = x^x (ln(x) + 1)
+        let d = parse("x^x").unwrap().derivative(&x);
+        let expected = 1.5f64.powf(1.5) * (1.5f64.ln() + 1.0);
+        assert!((d.eval(&ctx).unwrap().re - expected).abs() < 1e-12);
+
+        // d/dx 1/x = -1/x^2
+        let d = parse("1/x").unwrap().derivative(&x);
+        assert!((d.eval(&ctx).unwrap().re + 1.0 / 1.5f64.powi(2)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn simplify() {
+        assert!(Expr::exact_match(
+            &var("x").pow(Const(Zero)).simplify_trivial(),
+            &Const(One)
+        ));
+        assert!(Expr::exact_match(
+            &var("x").pow(Const(One)).simplify_trivial(),
+            &var("x")
+        ));
+        assert!(Expr::exact_match(
+            &var("x")
+                .pow(var("a"))
+                .pow(Const(Num::from(3)))
+                .simplify_trivial(),
+            &var("x").pow(Product(vec![var("a"), Const(Num::from(3))])),
+        ));
+        assert!(Expr::exact_match(
+            &var("x")
+                .pow(Const(Num::from(2)))
+                .pow(Const(Num::from(3)))
+                .simplify_trivial(),
+            &var("x").pow(Const(Num::from(6))),
+        ));
+        assert!(Expr::exact_match(
+            &Product(vec![var("x"), var("y"), var("x")]).simplify_trivial(),
+            &Product(vec![var("x").pow(Const(Num::from(2))), var("y")]),
+        ));
+        assert!(Expr::exact_match(
+            &Product(vec![var("x"), var("x").pow(Const(Num::from(-1)))]).simplify_trivial(),
+            &Const(One),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod chain_rule {
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+
+    use crate::{
+        expr::Expr,
+        function::Function,
+        num::Num,
+        parse::parse,
+        var::{Var, VarMap},
+    };
+
+    /// Compares `d/dx expr` against a central difference at `at`.
+    fn assert_derivative(expr: &Expr, x: &Var, at: f64) {
+        let eval = |value: f64| {
+            let mut ctx: VarMap<Complex64> = HashMap::new();
+            ctx.insert(x, Complex64::new(value, 0.0));
+            expr.eval(&ctx).unwrap()
+        };
+        let h = 1e-6;
+        let numeric = (eval(at + h) - eval(at - h)) / (2.0 * h);
+
+        let mut ctx: VarMap<Complex64> = HashMap::new();
+        ctx.insert(x, Complex64::new(at, 0.0));
+        let symbolic = expr.derivative(x).eval(&ctx).unwrap();
+
+        assert!(
+            (numeric - symbolic).norm() < 1e-6,
+            "d/dx {expr} at {at}: expected {numeric}, got {symbolic}"
+        );
+    }
+
+    #[test]
+    fn built_in_functions() {
+        let x = Var::new("x");
+
+        for name in [
+            "abs", "exp", "ln", "sin", "cos", "tan", "arcsin", "arccos", "arctan", "sinh", "cosh",
+            "tanh", "arcsinh", "arctanh",
+        ] {
+            let expr = parse(&format!("{name}(x^2 + 0.1)")).unwrap();
+            assert_derivative(&expr, &x, 0.4);
+        }
+
+        assert_derivative(&parse("arccosh(x^2 + 1)").unwrap(), &x, 0.7);
+        assert_derivative(&parse("sin(exp(x)) * ln(cos(x))").unwrap(), &x, 0.3);
+    }
+
+    #[test]
+    fn constant_arguments_vanish() {
+        let x = Var::new("x");
+        let y = Var::new("y");
+        let expr = parse("sin(y) + x").unwrap();
+
+        assert!(Expr::exact_match(
+            &expr.derivative(&x).simplify_trivial(),
+            &Expr::Const(Num::One)
+        ));
+        assert!(!expr.derivative(&y).is_variant_on(&x));
+    }
+
+    #[test]
+    fn user_defined_function() {
+        let x = Var::new("x");
+        let t = Var::new_owned("t");
+
+        // f(t) = t^3 + sin(t)
+        let f = Function::F("f".to_string(), parse("t^3 + sin(t)").unwrap(), vec![t]);
+
+        // f(x^2)
+        let expr = f.apply(vec![parse("x^2").unwrap()]);
+        assert_derivative(&expr, &x, 0.8);
+    }
+}
+
+#[cfg(test)]
+mod full_simplify {
+    use std::cmp::Ordering;
+
+    use crate::{
+        expr::Expr::{self, *},
+        num::Num,
+        parse::parse,
+        var::Var,
+    };
+
+    fn var(name: &str) -> Expr {
+        Var(Var::new(name))
+    }
+
+    fn int(n: i32) -> Expr {
+        Const(Num::from(n))
+    }
+
+    fn simplified(input: &str) -> Expr {
+        parse(input).unwrap().simplify()
+    }
+
+    #[test]
+    fn constant_folding() {
+        assert!(Expr::exact_match(
+            &simplified("2 + 3/4"),
+            &Const(Num::rational(11, 4))
+        ));
+        assert!(Expr::exact_match(
+            &simplified("2 * 3 * (1/6)"),
+            &Const(Num::One)
+        ));
+        assert!(Expr::exact_match(
+            &simplified("(2/3)^-2"),
+            &Const(Num::rational(9, 4))
+        ));
+        assert!(Expr::exact_match(&simplified("0 * x"), &Const(Num::Zero)));
+    }
+
+    #[test]
+    fn opposite_infinities() {
+        assert!(matches!(simplified("inf - inf"), Const(Num::Undefined)));
+        assert!(matches!(
+            simplified("-inf + x + inf"),
+            Const(Num::Undefined)
+        ));
+        assert!(matches!(simplified("2inf - 3inf"), Const(Num::Undefined)));
+        assert!(Expr::exact_match(
+            &simplified("inf + 1"),
+            &Const(Num::Infinity)
+        ));
+        assert!(!matches!(simplified("-inf - inf"), Const(Num::Undefined)));
+    }
+
+    #[test]
+    fn like_terms() {
+        assert!(Expr::exact_match(
+            &simplified("5x + x - 2y"),
+            &Sum(vec![
+                Product(vec![int(6), var("x")]),
+                Product(vec![int(-2), var("y")]),
+            ]),
+        ));
+        assert!(Expr::exact_match(&simplified("x - x"), &Const(Num::Zero)));
+        assert!(Expr::exact_match(
+            &simplified("x*y + 2y*x + 1 + 1"),
+            &Sum(vec![Product(vec![int(3), var("x"), var("y")]), int(2)]),
+        ));
+    }
+
+    #[test]
+    fn repeated_factors() {
+        assert!(Expr::exact_match(
+            &simplified("x * 2 * x * 3"),
+            &Product(vec![int(6), var("x").pow(int(2))]),
+        ));
+        assert!(Expr::exact_match(&simplified("x / x"), &Const(Num::One)));
+        assert!(Expr::exact_match(&simplified("sqrt(2) * sqrt(2)"), &int(2)));
+        assert!(Expr::exact_match(
+            &simplified("x^a * x^b"),
+            &var("x").pow(Sum(vec![var("a"), var("b")])),
+        ));
+        assert!(Expr::exact_match(
+            &simplified("(2x)^2"),
+            &Product(vec![int(4), var("x").pow(int(2))]),
+        ));
+    }
+
+    #[test]
+    fn canonical_order() {
+        for (a, b) in [
+            ("x + y + 1", "1 + y + x"),
+            ("2 * y * sin(x) * x", "x * sin(x) * 2y"),
+            ("x^2 + 2x + 1", "1 + x * 2 + x * x"),
+        ] {
+            assert_eq!(simplified(a).canonical_cmp(&simplified(b)), Ordering::Equal);
+        }
+    }
+
+    #[test]
+    fn derivative_simplifies() {
+        let x = Var::new("x");
+
+        assert!(Expr::exact_match(
+            &parse("5x + x - 2y").unwrap().derivative(&x).simplify(),
+            &int(6),
+        ));
+        assert!(Expr::exact_match(
+            &parse("x^3").unwrap().derivative(&x).simplify(),
+            &Product(vec![int(3), var("x").pow(int(2))]),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod num_arithmetic {
+    use crate::{
+        expr::Expr,
+        num::Num::{self, *},
+    };
+
+    fn exact(expr: Expr) -> Num {
+        match expr {
+            Expr::Const(num) => num,
+            _ => panic!("expected an exact result, got {expr:?}"),
+        }
+    }
+
+    #[test]
+    fn rationals() {
+        assert_eq!(
+            exact(Num::from(2) + Num::rational(3, 4)),
+            Num::rational(11, 4)
+        );
+        assert_eq!(
+            exact(Num::rational(1, 2) - Num::rational(1, 3)),
+            Num::rational(1, 6)
+        );
+        assert_eq!(
+            exact(Num::rational(-2, 3) * Num::rational(9, 4)),
+            Num::rational(-3, 2)
+        );
+        assert_eq!(
+            exact(Num::rational(2, 3) / Num::rational(-4, 9)),
+            Num::rational(-3, 2)
+        );
+        assert_eq!(exact(-Num::rational(2, 3)), Num::rational(-2, 3));
+        assert_eq!(
+            exact(Num::rational(2, 3).pow(Num::from(-2))),
+            Num::rational(9, 4)
+        );
+        assert_eq!(exact(Num::from(3) - Num::from(3)), Zero);
+    }
+
+    #[test]
+    fn special_values() {
+        assert_eq!(exact(Infinity + Num::from(-5)), Infinity);
+        assert_eq!(exact(Num::from(5) / Infinity), Zero);
+        assert_eq!(exact(Infinity * Num::rational(1, 2)), Infinity);
+        assert_eq!(exact(I * I), Num::from(-1));
+        assert_eq!(exact(Pi - Pi), Zero);
+        assert_eq!(exact(E / E), One);
+        assert_eq!(exact(Num::rational(1, 2).pow(Infinity)), Zero);
+    }
+
+    #[test]
+    fn indeterminate_forms_are_undefined() {
+        assert!(exact(Zero / Zero).is_undefined());
+        assert!(exact(One / Zero).is_undefined());
+        assert!(exact(Infinity - Infinity).is_undefined());
+        assert!(exact(Zero * Infinity).is_undefined());
+        assert!(exact(Infinity / Infinity).is_undefined());
+        assert!(exact(Zero.pow(Zero)).is_undefined());
+        assert!(exact(One.pow(Infinity)).is_undefined());
+        assert!(exact(Zero.pow(Num::from(-1))).is_undefined());
+        assert!(exact(Undefined + One).is_undefined());
+    }
+
+    #[test]
+    fn radicals() {
+        assert_eq!(exact(Num::from(8).pow(Num::rational(1, 3))), Num::from(2));
+        assert_eq!(
+            exact(Num::from(2).pow(Num::rational(1, 2))),
+            Num::radical(2u32, 2)
+        );
+        assert_eq!(
+            exact(Num::radical(2u32, 3) * Num::radical(5u32, 3)),
+            Num::radical(10u32, 3)
+        );
+        assert_eq!(
+            exact(Num::radical(10u32, 2) / Num::radical(5u32, 2)),
+            Num::radical(2u32, 2)
+        );
+        assert_eq!(exact(Num::radical(3u32, 2).pow(Num::from(4))), Num::from(9));
+    }
+
+    #[test]
+    fn symbolic_fallback() {
+        assert!(matches!(Pi + One, Expr::Sum(_)));
+        assert!(matches!(Num::from(2) * E, Expr::Product(_)));
+        assert!(matches!(-Pi, Expr::Product(_)));
+        assert!(matches!(E.pow(Num::from(2)), Expr::Pow(..)));
+        assert!(matches!(One - Infinity, Expr::Sum(_)));
+    }
+}
+
+#[cfg(test)]
+mod big_num {
+    use num_bigint::BigInt;
+
+    use crate::{expr::Expr, num::Num, parse::parse};
+
+    #[test]
+    fn no_overflow_at_i32_limits() {
+        let min = Num::from(i32::MIN);
+        assert_eq!(min.reduce(), Num::from(i32::MIN));
+        assert_eq!(
+            min.checked_mul(&Num::from(-1)).unwrap(),
+            Num::from(-(i32::MIN as i64))
+        );
+        assert_eq!(Num::rational(i32::MIN, i32::MIN).reduce(), Num::One);
+    }
+
+    #[test]
+    fn factorial() {
+        let mut factorial = Num::One;
+        for n in 1..=30 {
+            factorial = factorial.checked_mul(&Num::from(n)).unwrap();
+        }
+
+        assert_eq!(format!("{factorial}"), "265252859812191058636308480000000");
+        assert_eq!(
+            factorial.checked_div(&Num::from(29 * 30)).unwrap(),
+            Num::int("304888344611713860501504000000".parse::<BigInt>().unwrap())
+        );
+    }
+
+    #[test]
+    fn large_rationals() {
+        let big = BigInt::from(10).pow(40);
+        let third = Num::rational(big.clone(), big.clone() * 3);
+        assert_eq!(third, Num::rational(1, 3));
+        assert_eq!(
+            format!("{}", Num::rational(big.clone() + 1, 2)),
+            format!("{}/2", big + 1)
+        );
+
+        let expr = parse("123456789012345678901234567890 / 10").unwrap();
+        assert!(Expr::exact_match(
+            &expr,
+            &Expr::Const(Num::int(
+                "12345678901234567890123456789".parse::<BigInt>().unwrap()
+            ))
+        ));
+    }
+
+    #[test]
+    fn eval_float_of_huge_values() {
+        let num = Num::rational(BigInt::from(10).pow(400), BigInt::from(10).pow(399));
+        assert_eq!(num.eval_float().unwrap().re, 10.0);
+
+        let num = Num::rational(BigInt::from(10).pow(400) + 1, BigInt::from(10).pow(399) * 4);
+        assert!((num.eval_float().unwrap().re - 2.5).abs() < 1e-12);
+    }
+}
+
+#[cfg(test)]
+mod radical {
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+
+    use crate::{expr::Expr, num::Num, parse::parse};
+
+    fn exact(expr: Expr) -> Num {
+        match expr {
+            Expr::Const(num) => num,
+            _ => panic!("expected an exact result, got {expr:?}"),
+        }
+    }
+
+    fn sqrt(n: u32) -> Num {
+        Num::radical(n, 2)
+    }
+
+    #[test]
+    fn perfect_powers_are_extracted() {
+        assert_eq!(sqrt(8), exact(Num::from(2) * sqrt(2)));
+        assert_eq!(
+            Num::radical(54u32, 3),
+            exact(Num::from(3) * Num::radical(2u32, 3))
+        );
+        assert_eq!(sqrt(72).to_string(), "6\\sqrt[2]{2}");
+        assert_eq!(sqrt(16), Num::from(4));
+        assert_eq!(Num::radical(1000u32, 3), Num::from(10));
+    }
+
+    #[test]
+    fn index_is_reduced() {
+        assert_eq!(Num::radical(4u32, 4), sqrt(2));
+        assert_eq!(Num::radical(8u32, 6), sqrt(2));
+        assert_eq!(Num::radical(36u32, 4), sqrt(6));
+        assert_eq!(
+            Num::radical(32u32, 4),
+            exact(Num::from(2) * Num::radical(2u32, 4))
+        );
+    }
+
+    #[test]
+    fn equality_with_rationals() {
+        assert_ne!(sqrt(2), Num::One);
+        assert_ne!(sqrt(8), Num::from(2));
+        assert_eq!(exact(sqrt(2) * sqrt(2)), Num::from(2));
+        assert_eq!(exact(sqrt(6) / sqrt(6)), Num::One);
+
+        // Radicals built by hand have to be canonicalised before comparing.
+        let raw = Num::Radical {
+            num: 1.into(),
+            den: 1u32.into(),
+            radicand: 12u32.into(),
+            index: 2,
+        };
+        assert_eq!(raw.reduce(), exact(Num::from(2) * sqrt(3)));
+        assert_eq!(raw.reduce().to_string(), "2\\sqrt[2]{3}");
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(exact(sqrt(8) + sqrt(2)), exact(Num::from(3) * sqrt(2)));
+        assert_eq!(exact(sqrt(8) - exact(sqrt(2) * Num::from(2))), Num::Zero);
+        assert!(matches!(sqrt(2) + sqrt(3), Expr::Sum(_)));
+        assert!(matches!(sqrt(2) + Num::One, Expr::Sum(_)));
+        assert_eq!(exact(-sqrt(2)).to_string(), "-\\sqrt[2]{2}");
+
+        // 1/sqrt(2) = sqrt(2)/2
+        assert_eq!(exact(Num::One / sqrt(2)), exact(sqrt(2) / Num::from(2)));
+        assert_eq!(
+            exact(sqrt(2) * Num::radical(2u32, 3)),
+            Num::radical(32u32, 6)
+        );
+        assert_eq!(
+            exact(Num::rational(1, 4).pow(Num::rational(1, 2))),
+            Num::rational(1, 2)
+        );
+        assert_eq!(
+            exact(Num::from(2).pow(Num::rational(-1, 2))),
+            exact(sqrt(2) / Num::from(2))
+        );
+        assert_eq!(
+            exact(sqrt(2).pow(Num::from(3))),
+            exact(Num::from(2) * sqrt(2))
+        );
+        assert!(matches!(
+            Num::from(-4).pow(Num::rational(1, 2)),
+            Expr::Pow(..)
+        ));
+    }
+
+    #[test]
+    fn roots_of_negative_numbers_stay_symbolic() {
+        // Raising to an even power first would lose the sign, giving (-8)^(2/3) = 4.
+        assert!(matches!(
+            Num::from(-8).pow(Num::rational(2, 3)),
+            Expr::Pow(..)
+        ));
+        assert!(Num::from(-8).checked_pow(&Num::rational(1, 3)).is_none());
+        assert!(Num::from(-2).checked_pow(&Num::rational(-4, 3)).is_none());
+        assert_eq!(exact(Num::from(-2).pow(Num::from(2))), Num::from(4));
+
+        let expr = parse("(-8)^(2/3)").unwrap();
+        let (simplified, value) = (expr.simplify(), expr.eval(&HashMap::new()).unwrap());
+        assert!((simplified.eval(&HashMap::new()).unwrap() - value).norm() < 1e-12);
+        assert!((value - Complex64::new(-2.0, 12f64.sqrt())).norm() < 1e-12);
+    }
+
+    #[test]
+    fn radicands_with_large_prime_factors() {
+        // Both primes are above the trial division bound, so p^2 stays in the radicand.
+        let (p, q) = (65537u64, 65539u64);
+        let inside = Num::radical(p * p * q, 2);
+        let outside = exact(Num::from(p as i64) * Num::radical(q, 2));
+
+        assert_eq!(inside, outside);
+        assert_ne!(inside, exact(Num::from(-(p as i64)) * Num::radical(q, 2)));
+        assert_ne!(inside, Num::radical(p * q * q, 2));
+        assert_eq!(inside.checked_sub(&outside), Some(Num::Zero));
+        assert_eq!(inside.checked_div(&outside), Some(Num::One));
+        assert_eq!(
+            inside.checked_add(&outside),
+            Some(exact(Num::from(2 * p as i64) * Num::radical(q, 2)))
+        );
+        assert_eq!(
+            Num::radical(p, 2).checked_mul(&Num::radical(p * q, 2)),
+            Some(outside)
+        );
+    }
+
+    #[test]
+    fn eval_float() {
+        let num = exact(Num::from(3) * sqrt(8));
+        assert!((num.eval_float().unwrap().re - 6.0 * 2f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn simplify_agrees() {
+        let a = parse("sqrt(8)").unwrap().simplify();
+        let b = parse("2 * sqrt(2)").unwrap().simplify();
+        assert!(Expr::exact_match(&a, &b));
+        assert!(Expr::exact_match(&a, &Expr::Const(sqrt(8))));
+
+        let a = parse("sqrt(2) * x + sqrt(8) * x + 1 + sqrt(2)")
+            .unwrap()
+            .simplify();
+        let b = parse("3 * sqrt(2) * x + sqrt(2) + 1").unwrap().simplify();
+        assert!(Expr::exact_match(&a, &b));
+    }
+}
+
+#[cfg(test)]
+mod eval_errors {
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+
+    use crate::{
+        expr::{EvalError, Expr},
+        num::Num,
+        parse::parse,
+        var::Var,
+    };
+
+    #[test]
+    fn undefined_constants() {
+        let ctx = HashMap::new();
+
+        assert!(matches!(
+            Num::Undefined.eval_float(),
+            Err(EvalError::Undefined { .. })
+        ));
+        assert!(matches!(
+            Expr::Const(Num::Undefined).eval(&ctx),
+            Err(EvalError::Undefined { .. })
+        ));
+        assert!(matches!(
+            parse("inf - inf").unwrap().eval(&ctx),
+            Err(EvalError::Undefined { .. })
+        ));
+        assert!(matches!(
+            parse("0 * inf").unwrap().eval(&ctx),
+            Err(EvalError::Undefined { .. })
+        ));
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let x = Var::new("x");
+        let ctx = HashMap::from([(x.as_ref(), Complex64::new(1.0, 0.0))]);
+
+        let result = parse("1 / (x - 1)").unwrap().eval(&ctx);
+        match result {
+            Err(EvalError::DivisionByZero { divisor }) => {
+                assert!(Expr::exact_match(&divisor, &parse("x - 1").unwrap()));
+            }
+            _ => panic!("expected a division by zero, got {result:?}"),
+        }
+
+        assert!(parse("0^2").unwrap().eval(&ctx).is_ok());
+    }
+
+    #[test]
+    fn domain_errors() {
+        let ctx = HashMap::new();
+
+        for (input, function) in [("ln(0)", "ln"), ("atanh(1)", "arctanh")] {
+            match parse(input).unwrap().eval(&ctx) {
+                Err(EvalError::DomainError {
+                    function: name,
+                    arg,
+                }) => {
+                    assert_eq!(name, function);
+                    assert!(arg.is_finite());
+                }
+                result => panic!("expected a domain error for {input}, got {result:?}"),
+            }
+        }
+
+        // ln of a negative number is fine over the complex numbers.
+        assert!(parse("ln(-1)").unwrap().eval(&ctx).is_ok());
+        assert_eq!(
+            parse("sgn(0)").unwrap().eval(&ctx).unwrap(),
+            Complex64::new(0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn num_equals_complex() {
+        assert!(Num::from(2) == Complex64::new(2.0, 0.0));
+        assert!(Num::I == Complex64::new(0.0, 1.0));
+        assert!(Num::rational(1, 2) != Complex64::new(2.0, 0.0));
+        assert!(Num::Undefined != Complex64::new(f64::NAN, 0.0));
+    }
+
+    #[test]
+    fn functions_display() {
+        let expr = parse("sin(x)").unwrap();
+        assert!(expr.to_string().starts_with("sin("));
+
+        let message = EvalError::DomainError {
+            function: "ln".to_string(),
+            arg: Complex64::new(0.0, 0.0),
+        }
+        .to_string();
+        assert!(message.contains("ln"));
+    }
+}
+
+#[cfg(test)]
+mod multi_arg_functions {
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use crate::{
+        expr::{EvalError, EvalResult, Expr},
+        function::{Arity, FuncDef, Function},
+        parse::{parse, ParseErrorKind},
+        var::{Var, VarMap},
+    };
+
+    fn eval(input: &str) -> f64 {
+        let value = parse(input).unwrap().eval(&HashMap::new()).unwrap();
+        assert_eq!(value.im, 0.0);
+        value.re
+    }
+
+    #[test]
+    fn built_ins() {
+        assert!((eval("atan2(1, -1)") - 3.0 * std::f64::consts::FRAC_PI_4).abs() < 1e-12);
+        assert!((eval("log(2, 1024)") - 10.0).abs() < 1e-12);
+        assert_eq!(eval("min(3, -1, 2)"), -1.0);
+        assert_eq!(eval("max(3, -1, 2)"), 3.0);
+        assert_eq!(eval("max(7)"), 7.0);
+        assert_eq!(eval("hypot(3, 4)"), 5.0);
+        assert!((eval("root(3, 27)") - 3.0).abs() < 1e-12);
+        assert!((eval("root(3, -8)") + 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn complex_atan2_matches_real() {
+        let y = Complex64::new(1.0, 1e-300);
+        let x = Complex64::new(-1.0, 0.0);
+        let value = Function::Atan2.eval(vec![y, x], &HashMap::new()).unwrap();
+        assert!((value.re - 3.0 * std::f64::consts::FRAC_PI_4).abs() < 1e-12);
+    }
+
+    #[test]
+    fn domain_errors() {
+        let ctx = HashMap::new();
+        for input in ["log(1, 2)", "min(i, 1)", "root(0, 2)"] {
+            assert!(
+                matches!(
+                    parse(input).unwrap().eval(&ctx),
+                    Err(EvalError::DomainError { .. })
+                ),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn arity() {
+        assert_eq!(
+            <Function as FuncDef>::arity(&Function::Sin),
+            Arity::Exactly(1)
+        );
+        assert_eq!(
+            <Function as FuncDef>::arity(&Function::Min),
+            Arity::AtLeast(1)
+        );
+        assert!(Arity::AtLeast(1).accepts(5));
+        assert!(!Arity::AtLeast(1).accepts(0));
+
+        let err = parse("max()").unwrap_err();
+        assert_eq!(
+            err.kind,
+            ParseErrorKind::FnArgCountMismatch {
+                name: "max".to_string(),
+                expected: Arity::AtLeast(1),
+                found: 0,
+            }
+        );
+
+        let err = parse("log(8)").unwrap_err();
+        assert!(err
+            .kind
+            .to_string()
+            .contains("takes 2 argument(s) but 1 were given"));
+    }
+
+    #[test]
+    fn user_functions_with_several_parameters() {
+        let (x, y) = (Var::new("x"), Var::new("y"));
+        let body = Expr::Var(x.clone()) * Expr::Var(y.clone());
+        let f = Function::F(
+            "f".to_string(),
+            body,
+            vec![x.as_ref().clone(), y.as_ref().clone()],
+        );
+
+        let call = f
+            .clone()
+            .apply(vec![parse("2").unwrap(), parse("3").unwrap()]);
+        assert_eq!(
+            call.eval(&HashMap::new()).unwrap(),
+            Complex64::new(6.0, 0.0)
+        );
+
+        match f.apply(vec![parse("2").unwrap()]).eval(&HashMap::new()) {
+            Err(EvalError::FnArgCountMismatch {
+                name,
+                expected,
+                found,
+            }) => {
+                assert_eq!(name, "f");
+                assert_eq!(expected, Arity::Exactly(2));
+                assert_eq!(found, 1);
+            }
+            result => panic!("expected an arity error, got {result:?}"),
+        }
+    }
+
+    /// A function implementing only the required methods of `FuncDef`.
+    #[derive(Debug)]
+    struct Sum;
+
+    impl FuncDef for Sum {
+        fn eval(&self, args: Vec<Complex64>, _: &VarMap<Complex64>) -> EvalResult {
+            Ok(args.into_iter().sum())
+        }
+
+        fn is_variant_on_global(&self, _: &Var) -> bool {
+            false
+        }
+
+        fn name(&self) -> String {
+            "sum".to_string()
+        }
+    }
+
+    #[test]
+    fn default_arity_and_derivatives() {
+        let x = Var::new("x");
+        let ctx = HashMap::from([(x.as_ref(), Complex64::new(2.0, 0.0))]);
+
+        let empty = Expr::Function(Arc::new(Sum), vec![]);
+        assert_eq!(empty.eval(&ctx).unwrap(), Complex64::new(0.0, 0.0));
+
+        let sum = Expr::Function(
+            Arc::new(Sum),
+            vec![Expr::Var(x.clone()), parse("3").unwrap()],
+        );
+        assert_eq!(sum.eval(&ctx).unwrap(), Complex64::new(5.0, 0.0));
+        assert!(matches!(
+            sum.derivative(&x).eval(&ctx),
+            Err(EvalError::Undefined { .. })
+        ));
+    }
+
+    #[test]
+    fn derivatives() {
+        let x = Var::new("x");
+        let h = 1e-6;
+
+        for input in [
+            "atan2(x, 2)",
+            "atan2(2, x)",
+            "log(x, 5)",
+            "log(3, x^2)",
+            "min(x, 1)",
+            "max(x^2, 1, 0)",
+            "hypot(x, 2)",
+            "root(3, x)",
+            "root(x, 10)",
+        ] {
+            let expr = parse(input).unwrap();
+            let at = |value: f64| {
+                let ctx = HashMap::from([(x.as_ref(), Complex64::new(value, 0.0))]);
+                expr.eval(&ctx).unwrap().re
+            };
+
+            let numeric = (at(1.5 + h) - at(1.5 - h)) / (2.0 * h);
+            let ctx = HashMap::from([(x.as_ref(), Complex64::new(1.5, 0.0))]);
+            let symbolic = expr.derivative(&x).eval(&ctx).unwrap().re;
+
+            assert!(
+                (numeric - symbolic).abs() < 1e-6,
+                "{input}: {numeric} != {symbolic}"
+            );
+        }
+    }
+
+    #[test]
+    fn tied_extrema_share_the_derivative() {
+        let (x, y, z) = (Var::new("x"), Var::new("y"), Var::new("z"));
+        let vars = [x.as_ref(), y.as_ref(), z.as_ref()];
+        let ctx = HashMap::from(vars.map(|var| (var, Complex64::new(1.0, 0.0))));
+        let third = Complex64::new(1.0 / 3.0, 0.0);
+
+        for input in ["max(x, y, z)", "min(x, y, z)"] {
+            let expr = parse(input).unwrap();
+            let dual = expr.eval_dual(&ctx, &vars).unwrap();
+            let (_, gradient) = expr.eval_gradient(&ctx).unwrap();
+
+            for (i, var) in vars.into_iter().enumerate() {
+                let symbolic = expr.derivative(var).eval(&ctx).unwrap();
+                assert!((symbolic - third).norm() < 1e-15, "{input}: {symbolic}");
+                assert!((dual.partial(i) - third).norm() < 1e-15, "{input}");
+                assert!((gradient[var] - third).norm() < 1e-15, "{input}");
+            }
+        }
+
+        // Only the tied extrema share it.
+        let ctx = HashMap::from([
+            (vars[0], Complex64::new(2.0, 0.0)),
+            (vars[1], Complex64::new(2.0, 0.0)),
+            (vars[2], Complex64::new(1.0, 0.0)),
+        ]);
+        let expr = parse("max(x, y, z)").unwrap();
+        let dual = expr.eval_dual(&ctx, &vars).unwrap();
+        for (i, expected) in [0.5, 0.5, 0.0].into_iter().enumerate() {
+            let symbolic = expr.derivative(vars[i]).eval(&ctx).unwrap();
+            assert_eq!(symbolic, Complex64::new(expected, 0.0));
+            assert_eq!(dual.partial(i), Complex64::new(expected, 0.0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod substitute {
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+
+    use crate::{expr::Expr, function::Function, parse::parse, var::Var};
+
+    fn var(name: &str) -> Expr {
+        Expr::Var(Var::new(name))
+    }
+
+    fn expr(input: &str) -> Expr {
+        parse(input).unwrap()
+    }
+
+    fn same(a: &Expr, b: &Expr) -> bool {
+        Expr::exact_match(&a.simplify(), &b.simplify())
+    }
+
+    #[test]
+    fn single_variable() {
+        let x = Var::new_owned("x");
+        let result = expr("x^2 + 3x").substitute(&x, &expr("y + 1"));
+        assert!(same(&result, &expr("(y + 1)^2 + 3(y + 1)")));
+
+        // Variables that don't appear are left alone.
+        let result = expr("y").substitute(&x, &expr("2"));
+        assert!(same(&result, &var("y")));
+    }
+
+    #[test]
+    fn simultaneous() {
+        let (x, y) = (Var::new_owned("x"), Var::new_owned("y"));
+        let replacements = HashMap::from([(&x, var("y")), (&y, var("x"))]);
+
+        let result = expr("x - 2y").substitute_all(&replacements);
+        assert!(same(&result, &expr("y - 2x")));
+    }
+
+    #[test]
+    fn function_arguments() {
+        let x = Var::new_owned("x");
+        let result = expr("sin(x) + max(x, 1)").substitute(&x, &expr("2t"));
+        assert!(same(&result, &expr("sin(2t) + max(2t, 1)")));
+    }
+
+    #[test]
+    fn parameters_shadow_replacements() {
+        let (x, a) = (Var::new_owned("x"), Var::new_owned("a"));
+        // f(x) = a * x
+        let f = Function::F("f".to_string(), expr("a * x"), vec![x.clone()]);
+        let call = f.apply(vec![var("x")]);
+
+        // Only the argument is replaced, not the bound parameter in the body.
+        let result = call.substitute(&x, &expr("3"));
+        let ctx = HashMap::from([(&a, Complex64::new(2.0, 0.0))]);
+        assert_eq!(result.eval(&ctx).unwrap(), Complex64::new(6.0, 0.0));
+
+        // Free variables of the body are replaced.
+        let result = result.substitute(&a, &expr("5"));
+        assert!(!result.is_variant_on(&a));
+        assert_eq!(
+            result.eval(&HashMap::new()).unwrap(),
+            Complex64::new(15.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn parameters_are_not_captured() {
+        let (x, a) = (Var::new_owned("x"), Var::new_owned("a"));
+        // f(x) = x + a, with a := x from the outside.
+        let f = Function::F("f".to_string(), expr("x + a"), vec![x.clone()]);
+        let result = f.apply(vec![expr("2")]).substitute(&a, &var("x"));
+
+        match &result {
+            Expr::Function(def, _) => {
+                assert_eq!(def.free_variables(), vec![x.clone()]);
+            }
+            _ => panic!("expected a function call, got {result:?}"),
+        }
+
+        let ctx = HashMap::from([(&x, Complex64::new(10.0, 0.0))]);
+        assert_eq!(result.eval(&ctx).unwrap(), Complex64::new(12.0, 0.0));
+    }
+}
+
+#[cfg(test)]
+mod latex {
+    use crate::{
+        expr::Expr,
+        function::Function,
+        num::Num,
+        parse::parse,
+        var::{Var, VarMap},
+    };
+
+    fn latex(input: &str) -> String {
+        parse(input).unwrap().to_latex()
+    }
+
+    #[test]
+    fn minimal_parentheses() {
+        assert_eq!(latex("x + y z"), "x + y z");
+        assert_eq!(latex("(x + y) z"), "\\left(x + y\\right)z");
+        assert_eq!(latex("x - (y - z)"), "x - \\left(y - z\\right)");
+        assert_eq!(latex("-x^2"), "-x^{2}");
+        assert_eq!(latex("(-x)^2"), "\\left(-x\\right)^{2}");
+        assert_eq!(latex("(x^2)^3"), "\\left(x^{2}\\right)^{3}");
+        assert_eq!(latex("x^(y + 1)"), "x^{y + 1}");
+    }
+
+    #[test]
+    fn subtraction_and_coefficients() {
+        assert_eq!(latex("x - 2y"), "x - 2y");
+        assert_eq!(latex("2 * 3^x"), "2 \\cdot 3^{x}");
+        assert_eq!(latex("2 * 3"), "6");
+        assert_eq!(latex("x * 2"), "2x");
+        assert_eq!(latex("2pi x"), "2\\pi x");
+    }
+
+    #[test]
+    fn fractions() {
+        assert_eq!(latex("3/4"), "\\frac{3}{4}");
+        assert_eq!(latex("x/2"), "\\frac{x}{2}");
+        assert_eq!(latex("-3x/4"), "-\\frac{3x}{4}");
+        assert_eq!(latex("x^-2"), "\\frac{1}{x^{2}}");
+        assert_eq!(latex("(x + 1) / (x - 1)"), "\\frac{x + 1}{x - 1}");
+        assert_eq!(latex("x^(2/3)"), "x^{\\frac{2}{3}}");
+    }
+
+    #[test]
+    fn roots_and_constants() {
+        assert_eq!(latex("sqrt(x + 1)"), "\\sqrt{x + 1}");
+        assert_eq!(latex("x^(1/3)"), "\\sqrt[3]{x}");
+        assert_eq!(latex("root(n, x)"), "\\sqrt[n]{x}");
+        assert_eq!(Expr::Const(Num::radical(12u32, 2)).to_latex(), "2\\sqrt{3}");
+        assert_eq!(
+            (Num::rational(-1, 2) * Num::radical(2u32, 3)).to_latex(),
+            "-\\frac{\\sqrt[3]{2}}{2}"
+        );
+        assert_eq!(latex("pi + e + i + inf"), "\\pi + e + i + \\infty");
+        assert_eq!(Expr::Const(Num::Undefined).to_latex(), "\\text{undefined}");
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(latex("sin(x)^2"), "\\sin\\left(x\\right)^{2}");
+        assert_eq!(
+            latex("ln(x) + arctanh(x)"),
+            "\\ln\\left(x\\right) + \\operatorname{arctanh}\\left(x\\right)"
+        );
+        assert_eq!(latex("abs(x - 1)"), "\\left|x - 1\\right|");
+        assert_eq!(latex("log(2, x)"), "\\log_{2}\\left(x\\right)");
+        assert_eq!(latex("max(x, y, 1)"), "\\max\\left(x, y, 1\\right)");
+        assert_eq!(
+            latex("atan2(y, x)"),
+            "\\operatorname{atan2}\\left(y, x\\right)"
+        );
+        assert_eq!(latex("sgn(x)"), "\\operatorname{sgn}\\left(x\\right)");
+
+        let x = Var::new_owned("x");
+        let f = Function::F("f".to_string(), parse("x^2").unwrap(), vec![x.clone()]);
+        assert_eq!(
+            f.apply(vec![parse("t").unwrap()]).to_latex(),
+            "f\\left(t\\right)"
+        );
+
+        let mut replacements = VarMap::new();
+        replacements.insert(&x, Expr::Var(Var::new("speed")));
+        assert_eq!(
+            parse("2x")
+                .unwrap()
+                .substitute_all(&replacements)
+                .to_latex(),
+            "2\\mathrm{speed}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod text {
+    use crate::{
+        expr::Expr,
+        num::Num,
+        parse::parse,
+        print::{PrintOptions, Style},
+    };
+
+    fn ascii(input: &str) -> String {
+        parse(input)
+            .unwrap()
+            .display_with(PrintOptions::ascii())
+            .to_string()
+    }
+
+    fn unicode(input: &str) -> String {
+        parse(input)
+            .unwrap()
+            .display_with(PrintOptions::unicode())
+            .to_string()
+    }
+
+    #[test]
+    fn subtraction_and_coefficients() {
+        assert_eq!(ascii("x - 2y"), "x - 2y");
+        assert_eq!(ascii("x + -1 * y * 2"), "x - 2y");
+        assert_eq!(unicode("x - 2y"), "x − 2y");
+        assert_eq!(ascii("-x + y z"), "-x + y*z");
+        assert_eq!(unicode("-x + y z"), "−x + y·z");
+        assert_eq!(ascii("x - (y - z)"), "x - (y - z)");
+        assert_eq!(ascii("2 * 3^x"), "2*3^x");
+
+        let explicit = PrintOptions {
+            style: Style::Ascii,
+            implicit_multiplication: false,
+        };
+        assert_eq!(
+            parse("x - 2y").unwrap().display_with(explicit).to_string(),
+            "x - 2*y"
+        );
+    }
+
+    #[test]
+    fn powers_fractions_and_roots() {
+        assert_eq!(ascii("x^2 + x^-1"), "x^2 + 1/x");
+        assert_eq!(unicode("x^2 + x^-1"), "x² + 1/x");
+        assert_eq!(unicode("x^10 y"), "x¹⁰·y");
+        assert_eq!(ascii("(x^2)^3"), "(x^2)^3");
+        assert_eq!(ascii("x^(y + 1)"), "x^(y + 1)");
+        assert_eq!(unicode("x^(y + 1)"), "x^(y + 1)");
+        assert_eq!(ascii("x / (2y)"), "x/(2y)");
+        assert_eq!(ascii("(x + 1)/(x - 1)"), "(x + 1)/(x - 1)");
+        assert_eq!(ascii("sqrt(x + 1)"), "sqrt(x + 1)");
+        assert_eq!(unicode("sqrt(x + 1)"), "√(x + 1)");
+        assert_eq!(unicode("x^(1/3) + x^(1/5)"), "∛x + ⁵√x");
+        assert_eq!(ascii("x^(1/3)"), "x^(1/3)");
+        assert_eq!(ascii("x^(2/3)"), "x^(2/3)");
+    }
+
+    #[test]
+    fn constants_and_functions() {
+        assert_eq!(unicode("2pi + inf"), "2π + ∞");
+        assert_eq!(ascii("2pi + inf"), "2pi + inf");
+        assert_eq!(unicode("sin(x)^2 + max(x, 1)"), "sin(x)² + max(x, 1)");
+
+        let radical = Expr::Const(Num::radical(12u32, 2));
+        assert_eq!(
+            radical.display_with(PrintOptions::unicode()).to_string(),
+            "2√3"
+        );
+        assert_eq!(
+            radical.display_with(PrintOptions::ascii()).to_string(),
+            "2sqrt(3)"
+        );
+
+        let radical = Num::rational(-3, 2) * Num::radical(2u32, 3);
+        assert_eq!(
+            radical.display_with(PrintOptions::unicode()).to_string(),
+            "−3∛2/2"
+        );
+        assert_eq!(
+            radical.display_with(PrintOptions::ascii()).to_string(),
+            "-3*2^(1/3)/2"
+        );
+    }
+
+    #[test]
+    fn ascii_round_trips() {
+        for input in [
+            "x - 2y",
+            "x^2 + 3x - 1/2",
+            "-(x + y)^2",
+            "(x + 1)/(x - 1)",
+            "2 * 3^x",
+            "x^(1/3) - 4sqrt(2)",
+            "sin(x)^2 / (2 cos(x y))",
+            "x^-2 - e^(-x)",
+            "2pi i + inf",
+            "log(2, x) * atan2(y, -x)",
+            "-x^(y^2)",
+        ] {
+            let expr = parse(input).unwrap();
+            let printed = expr.display_with(PrintOptions::ascii()).to_string();
+            let reparsed = parse(&printed).unwrap_or_else(|err| panic!("{printed}: {err}"));
+
+            assert!(
+                Expr::exact_match(&expr.simplify(), &reparsed.simplify()),
+                "{input} printed as {printed}"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod colored {
+    use crate::{
+        parse::parse,
+        print::{Color, PrintOptions, Theme},
+    };
+
+    #[test]
+    fn display_is_plain() {
+        let expr = parse("2x + sin(y)").unwrap();
+        assert_eq!(expr.to_string(), "2x + sin(y)");
+        assert!(!format!("{expr}").contains('\u{001b}'));
+    }
+
+    #[test]
+    fn themes_and_no_color() {
+        let expr = parse("2x + sin(y)").unwrap();
+        let theme = Theme {
+            variable: Some(Color::Red),
+            constant: None,
+            function: Some(Color::BrightGreen),
+            operator: None,
+            bracket: None,
+        };
+
+        // Both cases live in one test, as the environment is shared between threads.
+        std::env::remove_var("NO_COLOR");
+        assert_eq!(
+            expr.colored().with_theme(theme).to_string(),
+            "2\u{001b}[31mx\u{001b}[0m + \u{001b}[92msin\u{001b}[0m(\u{001b}[31my\u{001b}[0m)"
+        );
+        assert!(expr
+            .colored()
+            .with_options(PrintOptions::unicode())
+            .to_string()
+            .contains("\u{001b}[95mx\u{001b}[0m"));
+
+        std::env::set_var("NO_COLOR", "1");
+        assert_eq!(expr.colored().to_string(), "2x + sin(y)");
+        std::env::remove_var("NO_COLOR");
+    }
+}
+
+#[cfg(test)]
+mod mathml {
+    use crate::{expr::Expr, num::Num, parse::parse};
+
+    /// The markup inside the `<math>` element.
+    fn mathml(input: &str) -> String {
+        inner(&parse(input).unwrap())
+    }
+
+    fn inner(expr: &Expr) -> String {
+        let markup = expr.to_mathml();
+        let start = "<math xmlns=\"http://www.w3.org/1998/Math/MathML\">";
+        assert!(markup.starts_with(start) && markup.ends_with("</math>"));
+        markup[start.len()..markup.len() - "</math>".len()].to_string()
+    }
+
+    #[test]
+    fn operators_and_parentheses() {
+        assert_eq!(
+            mathml("x - 2y"),
+            "<mrow><mi>x</mi><mo>&#x2212;</mo>\
+             <mrow><mn>2</mn><mo>&#x2062;</mo><mi>y</mi></mrow></mrow>"
+        );
+        assert_eq!(
+            mathml("(x + 1)^2"),
+            "<msup><mrow><mo>(</mo><mrow><mi>x</mi><mo>+</mo><mn>1</mn></mrow><mo>)</mo></mrow>\
+             <mn>2</mn></msup>"
+        );
+        assert_eq!(
+            mathml("2 * 3^x"),
+            "<mrow><mn>2</mn><mo>&#x22C5;</mo><msup><mn>3</mn><mi>x</mi></msup></mrow>"
+        );
+        assert_eq!(
+            mathml("x/(y + 1)"),
+            "<mfrac><mi>x</mi><mrow><mi>y</mi><mo>+</mo><mn>1</mn></mrow></mfrac>"
+        );
+        assert_eq!(mathml("-x"), "<mrow><mo>&#x2212;</mo><mi>x</mi></mrow>");
+    }
+
+    #[test]
+    fn roots() {
+        assert_eq!(mathml("sqrt(x)"), "<msqrt><mi>x</mi></msqrt>");
+        assert_eq!(mathml("x^(1/3)"), "<mroot><mi>x</mi><mn>3</mn></mroot>");
+        assert_eq!(mathml("root(n, x)"), "<mroot><mi>x</mi><mi>n</mi></mroot>");
+        assert_eq!(
+            inner(&Expr::Const(Num::radical(54u32, 3))),
+            "<mrow><mn>3</mn><mo>&#x2062;</mo><mroot><mn>2</mn><mn>3</mn></mroot></mrow>"
+        );
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(inner(&Expr::Const(Num::Pi)), "<mi>&#x3C0;</mi>");
+        assert_eq!(inner(&Expr::Const(Num::E)), "<mi>e</mi>");
+        assert_eq!(inner(&Expr::Const(Num::I)), "<mi>i</mi>");
+        assert_eq!(inner(&Expr::Const(Num::Infinity)), "<mi>&#x221E;</mi>");
+        assert_eq!(
+            inner(&Expr::Const(Num::Undefined)),
+            "<mtext>undefined</mtext>"
+        );
+        assert_eq!(inner(&Expr::Const(Num::Zero)), "<mn>0</mn>");
+        assert_eq!(
+            inner(&Expr::Const(Num::rational(-3, 4))),
+            "<mrow><mo>&#x2212;</mo><mfrac><mn>3</mn><mn>4</mn></mfrac></mrow>"
+        );
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(
+            mathml("sin(x)"),
+            "<mrow><mi>sin</mi><mo>&#x2061;</mo><mrow><mo>(</mo><mi>x</mi><mo>)</mo></mrow></mrow>"
+        );
+        assert_eq!(
+            mathml("abs(x)"),
+            "<mrow><mo>|</mo><mi>x</mi><mo>|</mo></mrow>"
+        );
+        assert_eq!(
+            mathml("log(2, x)"),
+            "<mrow><msub><mi>log</mi><mn>2</mn></msub><mo>&#x2061;</mo>\
+             <mrow><mo>(</mo><mi>x</mi><mo>)</mo></mrow></mrow>"
+        );
+        assert_eq!(
+            mathml("max(x, 1)"),
+            "<mrow><mi>max</mi><mo>&#x2061;</mo>\
+             <mrow><mo>(</mo><mi>x</mi><mo>,</mo><mn>1</mn><mo>)</mo></mrow></mrow>"
+        );
+    }
+}
+
+#[cfg(test)]
+mod typst {
+    use crate::{expr::Expr, num::Num, parse::parse, var::Var};
+
+    fn typst(input: &str) -> String {
+        parse(input).unwrap().to_typst()
+    }
+
+    #[test]
+    fn operators_and_parentheses() {
+        assert_eq!(typst("x - 2y"), "x - 2 y");
+        assert_eq!(typst("(x + y) z"), "(x + y) z");
+        assert_eq!(typst("(x + 1)^2"), "(x + 1)^2");
+        assert_eq!(typst("x^(y + 1)"), "x^(y + 1)");
+        assert_eq!(typst("2 * 3^x"), "2 dot.op 3^x");
+        assert_eq!(typst("-x^2"), "-x^2");
+    }
+
+    #[test]
+    fn fractions_and_roots() {
+        assert_eq!(typst("x/(y + 1)"), "frac(x, y + 1)");
+        assert_eq!(typst("x^-2"), "frac(1, x^2)");
+        assert_eq!(typst("x^(2/3)"), "x^(frac(2, 3))");
+        assert_eq!(typst("sqrt(x + 1)"), "sqrt(x + 1)");
+        assert_eq!(typst("x^(1/3)"), "root(3, x)");
+        assert_eq!(typst("root(n, x)"), "root(n, x)");
+        assert_eq!(
+            (Num::rational(-1, 2) * Num::radical(2u32, 3)).to_typst(),
+            "-frac(root(3, 2), 2)"
+        );
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(typst("pi + e + i + inf"), "pi + e + i + infinity");
+        assert_eq!(Expr::Const(Num::Undefined).to_typst(), "\"undefined\"");
+    }
+
+    #[test]
+    fn functions_and_names() {
+        assert_eq!(typst("sin(x)^2"), "sin(x)^2");
+        assert_eq!(typst("abs(x - 1)"), "abs(x - 1)");
+        assert_eq!(typst("log(2, x)"), "log_2 (x)");
+        assert_eq!(typst("atan2(y, x)"), "op(\"atan2\")(y, x)");
+        assert_eq!(Expr::Var(Var::new("speed")).to_typst(), "\"speed\"");
+    }
+}
+
+#[cfg(test)]
+mod compile {
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+
+    use crate::{
+        expr::{EvalError, Expr},
+        function::{FuncDef, Function},
+        num::Num,
+        parse::parse,
+        var::Var,
+    };
+
+    #[test]
+    fn matches_tree_evaluation() {
+        let (x, y) = (Var::new("x"), Var::new("y"));
+        let inputs = [
+            "3x^3 - 2x^2 y + 7",
+            "sin(x) y^2 + exp(-x/2) cos(y) - sqrt(x^2 + y^2)",
+            "x^(1/3) + y^-2 + i x",
+            "max(x, y, 2) + log(2, x y) + atan2(y, x)",
+            "pi e - 5/3",
+        ];
+
+        for input in inputs {
+            let expr = parse(input).unwrap();
+            let compiled = expr.compile(&[&x, &y]).unwrap();
+
+            for (a, b) in [(0.5, 2.0), (-1.5, 3.0), (4.0, -0.25)] {
+                let (a, b) = (Complex64::new(a, 0.0), Complex64::new(b, 0.0));
+                let ctx = HashMap::from([(x.as_ref(), a), (y.as_ref(), b)]);
+
+                assert_eq!(
+                    compiled.eval(&[a, b]).unwrap(),
+                    expr.eval(&ctx).unwrap(),
+                    "{input}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn slots_follow_the_given_order() {
+        let (x, y) = (Var::new("x"), Var::new("y"));
+        let compiled = parse("x - y").unwrap().compile(&[&y, &x]).unwrap();
+
+        assert_eq!(compiled.vars(), [y.as_ref().clone(), x.as_ref().clone()]);
+        assert_eq!(
+            compiled
+                .eval(&[Complex64::new(1.0, 0.0), Complex64::new(5.0, 0.0)])
+                .unwrap(),
+            Complex64::new(4.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn user_functions_read_globals() {
+        let (x, a) = (Var::new("x"), Var::new("a"));
+        let f = Function::F(
+            "f".to_string(),
+            parse("a * x").unwrap(),
+            vec![x.as_ref().clone()],
+        );
+        assert_eq!(
+            <Function as FuncDef>::free_variables(&f),
+            [a.as_ref().clone()]
+        );
+
+        let expr = f.apply(vec![parse("x + 1").unwrap()]);
+        let compiled = expr.compile(&[&x, &a]).unwrap();
+        assert_eq!(
+            compiled
+                .eval(&[Complex64::new(2.0, 0.0), Complex64::new(10.0, 0.0)])
+                .unwrap(),
+            Complex64::new(30.0, 0.0)
+        );
+
+        assert!(matches!(
+            expr.compile(&[&x]),
+            Err(EvalError::VarMissing { name }) if name == "a"
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        let x = Var::new("x");
+
+        assert!(matches!(
+            parse("x + y").unwrap().compile(&[&x]),
+            Err(EvalError::VarMissing { name }) if name == "y"
+        ));
+        assert!(matches!(
+            Expr::Function(std::sync::Arc::new(Function::Sin), vec![]).compile(&[]),
+            Err(EvalError::FnArgCountMismatch { .. })
+        ));
+
+        let compiled = parse("1/x + ln(x - 1)").unwrap().compile(&[&x]).unwrap();
+        assert!(matches!(
+            compiled.eval(&[]),
+            Err(EvalError::VarMissing { name }) if name == "x"
+        ));
+        assert!(matches!(
+            compiled.eval(&[Complex64::new(0.0, 0.0)]),
+            Err(EvalError::DivisionByZero { divisor }) if divisor.exact_match(&Expr::Var(x.clone()))
+        ));
+        assert!(matches!(
+            compiled.eval(&[Complex64::new(1.0, 0.0)]),
+            Err(EvalError::DomainError { .. })
+        ));
+
+        let undefined = (parse("x").unwrap() + Expr::Const(Num::Undefined))
+            .compile(&[&x])
+            .unwrap();
+        assert!(matches!(
+            undefined.eval(&[Complex64::new(1.0, 0.0)]),
+            Err(EvalError::Undefined { .. })
+        ));
+
+        // Errors deep in the expression report the subexpression at fault.
+        let nested = parse("sin(x) + 2 (3 + (x - 1)^-2)")
+            .unwrap()
+            .compile(&[&x])
+            .unwrap();
+        match nested.eval(&[Complex64::new(1.0, 0.0)]) {
+            Err(EvalError::DivisionByZero { divisor }) => {
+                assert_eq!(divisor.to_string(), parse("x - 1").unwrap().to_string())
+            }
+            result => panic!("expected a division by zero, got {result:?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod batch {
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+
+    use crate::{expr::EvalError, parse::parse, var::Var};
+
+    fn real(value: f64) -> Complex64 {
+        Complex64::new(value, 0.0)
+    }
+
+    #[test]
+    fn matches_pointwise_evaluation() {
+        let (x, y) = (Var::new("x"), Var::new("y"));
+        let expr = parse("sin(x) y^2 - sqrt(x^2 + y^2)").unwrap();
+
+        // More points than a parallel chunk, so several chunks are stitched together.
+        let n = 5000;
+        let xs: Vec<_> = (0..n).map(|i| real(i as f64 / 100.0)).collect();
+        let ys: Vec<_> = (0..n).map(|i| real(3.0 - i as f64 / 700.0)).collect();
+        let mut out = vec![Complex64::new(0.0, 0.0); n];
+
+        let errors = expr.eval_batch(&[(&x, &xs), (&y, &ys)], &mut out).unwrap();
+        assert!(errors.is_empty());
+
+        for i in 0..n {
+            let ctx = HashMap::from([(x.as_ref(), xs[i]), (y.as_ref(), ys[i])]);
+            assert_eq!(out[i], expr.eval(&ctx).unwrap());
+        }
+    }
+
+    #[test]
+    fn errors_are_reported_per_point() {
+        let x = Var::new("x");
+        let expr = parse("1/x + ln(x + 2)").unwrap();
+
+        let xs: Vec<_> = [1.0, 0.0, -2.0, 3.0].into_iter().map(real).collect();
+        let mut out = vec![Complex64::new(0.0, 0.0); xs.len()];
+        let errors = expr.eval_batch(&[(&x, &xs)], &mut out).unwrap();
+
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[0], (1, EvalError::DivisionByZero { .. })));
+        assert!(matches!(errors[1], (2, EvalError::DomainError { .. })));
+        assert!(out[1].is_nan() && out[2].is_nan());
+        assert_eq!(out[0], real(1.0 + 3f64.ln()));
+        assert_eq!(out[3], real(1.0 / 3.0 + 5f64.ln()));
+    }
+
+    #[test]
+    fn constants_and_missing_columns() {
+        let (x, y) = (Var::new("x"), Var::new("y"));
+
+        let mut out = vec![Complex64::new(0.0, 0.0); 3];
+        parse("2 + 3").unwrap().eval_batch(&[], &mut out).unwrap();
+        assert_eq!(out, [real(5.0); 3]);
+
+        let xs = [real(1.0); 3];
+        assert!(matches!(
+            parse("x y").unwrap().eval_batch(&[(&x, &xs)], &mut out),
+            Err(EvalError::VarMissing { name }) if name == "y"
+        ));
+
+        let compiled = parse("x y").unwrap().compile(&[&x, &y]).unwrap();
+        let errors = compiled.eval_batch(&[&xs, &[real(2.0), real(3.0), real(4.0)]], &mut out);
+        assert!(errors.is_empty());
+        assert_eq!(out, [real(2.0), real(3.0), real(4.0)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "one value per point")]
+    fn columns_must_match_the_output() {
+        let x = Var::new("x");
+        let mut out = vec![Complex64::new(0.0, 0.0); 3];
+        let _ = parse("x")
+            .unwrap()
+            .eval_batch(&[(&x, &[real(1.0)])], &mut out);
+    }
+}
+
+#[cfg(test)]
+mod real_eval {
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use crate::{
+        expr::{EvalError, EvalResult, Expr},
+        function::{Arity, FuncDef, Function},
+        num::Num,
+        parse::parse,
+        var::{Var, VarMap},
+    };
+
+    fn eval_real(input: &str, x: f64) -> Result<f64, EvalError> {
+        let var = Var::new_owned("x");
+        parse(input).unwrap().eval_real(&HashMap::from([(&var, x)]))
+    }
+
+    #[test]
+    fn agrees_with_complex_evaluation_on_reals() {
+        let var = Var::new_owned("x");
+        let inputs = [
+            "3x^3 - 2x + 1",
+            "sin(x) + cos(x)^2 - tan(x / 4)",
+            "exp(-x) ln(x) + sqrt(x) + abs(1 - x)",
+            "atan2(x, 2) + log(2, x) + hypot(x, 3) + max(x, 1) - min(x, 2)",
+            "arctan(x) + sinh(x) + root(3, x) + sgn(x - 1)",
+        ];
+
+        for input in inputs {
+            let expr = parse(input).unwrap();
+            for x in [0.5, 1.5, 3.0] {
+                let real = expr.eval_real(&HashMap::from([(&var, x)])).unwrap();
+                let complex = expr
+                    .eval(&HashMap::from([(&var, Complex64::new(x, 0.0))]))
+                    .unwrap();
+                assert!((real - complex.re).abs() < 1e-12, "{input} at {x}");
+            }
+        }
+    }
+
+    #[test]
+    fn domain_errors_off_the_real_line() {
+        for (input, x) in [
+            ("ln(x)", -1.0),
+            ("arcsin(x)", 2.0),
+            ("arccosh(x)", 0.5),
+            ("arctanh(x)", 1.0),
+            ("log(x, 8)", -2.0),
+            ("root(2, x)", -1.0),
+            // Poles.
+            ("ln(x)", 0.0),
+            ("arctanh(x)", -1.0),
+            ("log(2, x)", 0.0),
+            ("root(-2, x)", 0.0),
+        ] {
+            assert!(
+                matches!(eval_real(input, x), Err(EvalError::DomainError { .. })),
+                "{input} at {x}"
+            );
+        }
+
+        // Values too large for an f64 overflow to infinity inside the domain.
+        for (input, x, expected) in [
+            ("exp(x)", 1000.0, f64::INFINITY),
+            ("sinh(x)", -1000.0, f64::NEG_INFINITY),
+            ("cosh(x)", -1000.0, f64::INFINITY),
+            ("hypot(x, x)", 1.5e308, f64::INFINITY),
+        ] {
+            assert_eq!(eval_real(input, x).unwrap(), expected, "{input} at {x}");
+        }
+
+        // The complex evaluation happily leaves the real line.
+        let var = Var::new_owned("x");
+        let ln = parse("ln(x)").unwrap();
+        let value = ln
+            .eval(&HashMap::from([(&var, Complex64::new(-1.0, 0.0))]))
+            .unwrap();
+        assert_eq!(value, Complex64::new(0.0, std::f64::consts::PI));
+    }
+
+    #[test]
+    fn powers_of_negative_numbers() {
+        assert_eq!(eval_real("x^3", -2.0).unwrap(), -8.0);
+        assert!((eval_real("x^(1/3)", -8.0).unwrap() + 2.0).abs() < 1e-12);
+        assert!((eval_real("x^(2/3)", -8.0).unwrap() - 4.0).abs() < 1e-12);
+        assert_eq!(eval_real("root(3, x)", -8.0).unwrap(), -2.0);
+        assert!(matches!(
+            eval_real("sqrt(x)", -4.0),
+            Err(EvalError::NotReal { .. })
+        ));
+        assert!(matches!(
+            eval_real("x^(1/2)", -1.0),
+            Err(EvalError::NotReal { .. })
+        ));
+        assert!(matches!(
+            eval_real("x^pi", -1.0),
+            Err(EvalError::NotReal { .. })
+        ));
+        assert!(matches!(
+            eval_real("x^-1", 0.0),
+            Err(EvalError::DivisionByZero { .. })
+        ));
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(eval_real("pi", 0.0).unwrap(), std::f64::consts::PI);
+        assert_eq!(
+            Expr::Const(Num::radical(8u32, 3))
+                .eval_real(&HashMap::new())
+                .unwrap(),
+            2.0
+        );
+        assert!(matches!(
+            eval_real("2i", 0.0),
+            Err(EvalError::NotReal { .. })
+        ));
+        assert!(matches!(
+            eval_real("inf - inf", 0.0),
+            Err(EvalError::Undefined { .. })
+        ));
+    }
+
+    #[test]
+    fn user_functions() {
+        let (t, a) = (Var::new_owned("t"), Var::new_owned("a"));
+        // f(t) = ln(t) + a
+        let f = Function::F(
+            "f".to_string(),
+            parse("ln(t) + a").unwrap(),
+            vec![t.clone()],
+        );
+        let call = f.clone().apply(vec![parse("2").unwrap()]);
+
+        let ctx = HashMap::from([(&a, 1.0)]);
+        assert_eq!(call.eval_real(&ctx).unwrap(), 2f64.ln() + 1.0);
+
+        let call = f.clone().apply(vec![parse("-2").unwrap()]);
+        assert!(matches!(
+            call.eval_real(&ctx),
+            Err(EvalError::DomainError { function, .. }) if function == "ln"
+        ));
+
+        let args = vec![4.0];
+        assert_eq!(
+            FuncDef::<f64>::eval(&f, args, &ctx).unwrap(),
+            4f64.ln() + 1.0
+        );
+    }
+
+    /// A constant function without arguments or a real version.
+    #[derive(Debug)]
+    struct Constant(Complex64);
+
+    impl FuncDef for Constant {
+        fn eval(&self, _: Vec<Complex64>, _: &VarMap<Complex64>) -> EvalResult {
+            Ok(self.0)
+        }
+
+        fn is_variant_on_global(&self, _: &Var) -> bool {
+            false
+        }
+
+        fn name(&self) -> String {
+            "c".to_string()
+        }
+
+        fn arity(&self) -> Arity {
+            Arity::Exactly(0)
+        }
+
+        fn partial_derivative(&self, _: &[Expr], _: usize) -> Expr {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn functions_without_arguments() {
+        let one = Expr::Function(Arc::new(Constant(Complex64::new(1.0, 0.0))), vec![]);
+        assert_eq!(one.eval_real(&HashMap::new()).unwrap(), 1.0);
+
+        let i = Expr::Function(Arc::new(Constant(Complex64::new(0.0, 1.0))), vec![]);
+        assert!(matches!(
+            i.eval_real(&HashMap::new()),
+            Err(EvalError::NotReal { .. })
+        ));
+    }
+}
+
+#[cfg(test)]
+mod interval {
+    use std::collections::HashMap;
+    use std::f64::consts::PI;
+
+    use crate::{
+        expr::{EvalError, Expr},
+        interval::Interval,
+        num::Num,
+        parse::parse,
+        var::Var,
+    };
+
+    fn eval_interval(input: &str, x: Interval) -> Result<Interval, EvalError> {
+        let var = Var::new_owned("x");
+        parse(input)
+            .unwrap()
+            .eval_interval(&HashMap::from([(&var, x)]))
+    }
+
+    /// Checks that the enclosure over `x` contains the values at points spread over `x`.
+    fn assert_encloses(input: &str, x: Interval) {
+        let var = Var::new_owned("x");
+        let expr = parse(input).unwrap();
+        let enclosure = expr.eval_interval(&HashMap::from([(&var, x)]));
+
+        for i in 0..=100 {
+            let point = x.lo() + (x.hi() - x.lo()) * i as f64 / 100.0;
+            let value = expr.eval_real(&HashMap::from([(&var, point)]));
+
+            match (&enclosure, value) {
+                (Ok(enclosure), Ok(value)) => assert!(
+                    enclosure.contains(value),
+                    "{input} at {point} is {value}, outside of {enclosure}"
+                ),
+                // Without an enclosure, there is no value anywhere in the box.
+                (Err(error), Ok(value)) => {
+                    panic!("{input} over {x}: {error}, but it is {value} at {point}")
+                }
+                (_, Err(_)) => {}
+            }
+        }
+    }
+
+    #[test]
+    fn encloses_every_built_in_function() {
+        let inputs = [
+            "abs(x - 1)",
+            "sgn(x)",
+            "exp(x) - x^3",
+            "ln(x)",
+            "sin(x)",
+            "cos(3x)",
+            "tan(x)",
+            "arcsin(x / 4)",
+            "arccos(x / 4)",
+            "arctan(x)",
+            "sinh(x)",
+            "cosh(x)",
+            "tanh(x)",
+            "arcsinh(x)",
+            "arccosh(x)",
+            "arctanh(x / 3)",
+            "atan2(x, 1)",
+            "atan2(1, x)",
+            "log(2, x)",
+            "min(x, 1, -x)",
+            "max(x^2, 2)",
+            "hypot(x, 2)",
+            "root(3, x)",
+            "root(4, x)",
+            "root(5, x)",
+            "x^(1/3) + x^(2/5)",
+            "sqrt(x) + x^-2",
+            "(x - 1)(x + 2) / (x^2 + 1)",
+        ];
+        let boxes = [
+            Interval::new(-3.0, -2.5),
+            Interval::new(-1.0, 2.0),
+            Interval::new(0.5, 1.5),
+            Interval::new(1.2, 7.0),
+        ];
+
+        for input in inputs {
+            for x in boxes {
+                assert_encloses(input, x);
+            }
+        }
+    }
+
+    #[test]
+    fn trigonometric_extrema_and_poles() {
+        let sin = eval_interval("sin(x)", Interval::new(0.0, PI)).unwrap();
+        assert_eq!(sin.hi(), 1.0);
+        assert!(sin.lo() <= 0.0 && sin.lo() > -1e-15);
+
+        let cos = eval_interval("cos(x)", Interval::new(-0.1, 0.1)).unwrap();
+        assert_eq!(cos.hi(), 1.0);
+        assert!(cos.lo() > 0.99);
+
+        assert_eq!(
+            eval_interval("sin(x)", Interval::new(0.0, 10.0)).unwrap(),
+            Interval::new(-1.0, 1.0)
+        );
+
+        // tan has a pole at π/2.
+        assert_eq!(
+            eval_interval("tan(x)", Interval::new(1.0, 2.0)).unwrap(),
+            Interval::entire()
+        );
+        let tan = eval_interval("tan(x)", Interval::new(-1.0, 1.0)).unwrap();
+        assert!(tan.lo() < -1.5574 && tan.hi() > 1.5574 && tan.width() < 3.12);
+    }
+
+    #[test]
+    fn domains_and_division() {
+        let ln = eval_interval("ln(x)", Interval::new(-1.0, 1.0)).unwrap();
+        assert_eq!(ln.lo(), f64::NEG_INFINITY);
+        assert!(ln.hi() >= 0.0 && ln.hi() < 1e-15);
+
+        assert!(matches!(
+            eval_interval("ln(x)", Interval::new(-2.0, -1.0)),
+            Err(EvalError::DomainError { function, .. }) if function == "ln"
+        ));
+        assert!(matches!(
+            eval_interval("arcsin(x)", Interval::new(2.0, 3.0)),
+            Err(EvalError::DomainError { .. })
+        ));
+        assert!(matches!(
+            eval_interval("x^(1/2)", Interval::new(-2.0, -1.0)),
+            Err(EvalError::NotReal { .. })
+        ));
+
+        assert_eq!(
+            eval_interval("1/x", Interval::new(-1.0, 1.0)).unwrap(),
+            Interval::entire()
+        );
+        assert!(matches!(
+            eval_interval("1/x", Interval::point(0.0)),
+            Err(EvalError::DivisionByZero { .. })
+        ));
+
+        // Even powers are not negative, even though the base may be.
+        let square = eval_interval("x^2", Interval::new(-1.0, 2.0)).unwrap();
+        assert_eq!(square.lo(), 0.0);
+        assert!(square.contains(4.0) && square.hi() < 4.0 + 1e-14);
+
+        // The magnitude of the most negative exponent does not fit in an `i32`.
+        let tiny = eval_interval("x^(-2147483648)", Interval::new(2.0, 3.0)).unwrap();
+        assert!(tiny.contains(0.0) && tiny.hi() < 1e-300);
+        let unit = eval_interval("x^(-2147483648)", Interval::new(-1.0, 1.0)).unwrap();
+        assert!(unit.contains(1.0) && unit.hi() == f64::INFINITY);
+    }
+
+    #[test]
+    fn constants_and_rounding() {
+        let empty = HashMap::new();
+
+        let pi = Expr::Const(Num::Pi).eval_interval(&empty).unwrap();
+        assert!(pi.lo() < PI && PI < pi.hi());
+
+        let third = Expr::Const(Num::rational(1, 3))
+            .eval_interval(&empty)
+            .unwrap();
+        assert!(third.lo() < third.hi() && (third * Interval::point(3.0)).contains(1.0));
+
+        assert_eq!(
+            Expr::Const(Num::int(7)).eval_interval(&empty).unwrap(),
+            Interval::point(7.0)
+        );
+        assert_eq!(
+            Expr::Const(Num::Infinity)
+                .eval_interval(&empty)
+                .unwrap()
+                .hi(),
+            f64::INFINITY
+        );
+        assert!(matches!(
+            Expr::Const(Num::I).eval_interval(&empty),
+            Err(EvalError::NotReal { .. })
+        ));
+
+        // 0.1 + 0.2 is not 0.3 in floating point, but the enclosure contains both.
+        let sum = Interval::point(0.1) + Interval::point(0.2);
+        assert!(sum.contains(0.1 + 0.2) && sum.contains(0.3));
+    }
+
+    #[test]
+    fn several_variables() {
+        let (x, y) = (Var::new_owned("x"), Var::new_owned("y"));
+        let ctx = HashMap::from([
+            (&x, Interval::new(1.0, 2.0)),
+            (&y, Interval::new(-1.0, 3.0)),
+        ]);
+
+        let result = parse("x y - y^2").unwrap().eval_interval(&ctx).unwrap();
+        // x y is in [-2, 6] and y^2 in [0, 9].
+        assert!(result.contains(-11.0) && result.contains(6.0));
+        assert!(result.lo() > -11.0 - 1e-12 && result.hi() < 6.0 + 1e-12);
+
+        let atan2 = parse("atan2(y, x - 2)")
+            .unwrap()
+            .eval_interval(&ctx)
+            .unwrap();
+        assert!(atan2.contains(PI) && atan2.contains(-PI));
+
+        // Variable exponents of a base which may be negative.
+        let ctx = HashMap::from([(&x, Interval::new(-2.0, 1.0)), (&y, Interval::point(3.0))]);
+        let cube = parse("x^y").unwrap().eval_interval(&ctx).unwrap();
+        assert!(cube.contains(-8.0) && cube.contains(1.0) && cube.hi() < 1.0 + 1e-12);
+
+        let ctx = HashMap::from([
+            (&x, Interval::new(-2.0, 1.0)),
+            (&y, Interval::new(2.0, 3.0)),
+        ]);
+        let power = parse("x^y").unwrap().eval_interval(&ctx).unwrap();
+        assert_eq!(power, Interval::entire());
+    }
+
+    #[test]
+    fn roots_of_negative_index() {
+        assert_encloses("root(-3, x)", Interval::new(-2.0, 1.0));
+        assert_encloses("root(-2, x)", Interval::new(4.0, 9.0));
+        assert_encloses("root(-3, x)", Interval::new(-8.0, -1.0));
+
+        let root = eval_interval("root(-2, x)", Interval::new(4.0, 9.0)).unwrap();
+        assert!(root.contains(1.0 / 3.0) && root.contains(0.5) && root.hi() < 0.5 + 1e-12);
+        assert_eq!(
+            eval_interval("root(1/2, x)", Interval::new(-2.0, 1.0)).unwrap(),
+            Interval::entire()
+        );
+    }
+}
+
+#[cfg(test)]
+mod dual {
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use crate::{
+        expr::{EvalError, EvalResult, Expr},
+        function::{Arity, FuncDef, Function},
+        parse::parse,
+        var::{Var, VarMap},
+    };
+
+    use super::fixtures::close;
+
+    const INPUTS: &[&str] = &[
+        "x^3 y - 2x y^2 + 7",
+        "sin(x y) + cos(x)^2 - tan(y / 4)",
+        "exp(-x) ln(y) + sqrt(x^2 + y^2)",
+        "arcsin(x / 4) + arccos(y / 5) + arctan(x y)",
+        "sinh(x) cosh(y) + tanh(x - y)",
+        "arcsinh(x) + arccosh(y + 1) + arctanh(x / 3)",
+        "atan2(y, x) + log(y + 1, x + 2) + hypot(x, y)",
+        "root(y, x) + root(3, x y)",
+        "abs(x - 3) + x^y + y^(1/2)",
+        "max(x, y, 1) + min(x^2, y)",
+        "(x + 1) / (y^2 + 1) + 2^x",
+    ];
+
+    #[test]
+    fn matches_symbolic_derivatives() {
+        let (x, y) = (Var::new_owned("x"), Var::new_owned("y"));
+        let ctx = HashMap::from([
+            (&x, Complex64::new(0.7, 0.0)),
+            (&y, Complex64::new(2.3, 0.0)),
+        ]);
+
+        for input in INPUTS {
+            let expr = parse(input).unwrap();
+            let dual = expr.eval_dual(&ctx, &[&x, &y]).unwrap();
+
+            assert!(close(dual.value(), expr.eval(&ctx).unwrap()), "{input}");
+            for (i, var) in [&x, &y].into_iter().enumerate() {
+                let symbolic = expr.derivative(var).eval(&ctx).unwrap();
+                assert!(
+                    close(dual.partial(i), symbolic),
+                    "d/d{} {input}: {} != {symbolic}",
+                    var.get_name(),
+                    dual.partial(i)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn hyper_dual_matches_symbolic_second_derivatives() {
+        let (x, y) = (Var::new_owned("x"), Var::new_owned("y"));
+        let ctx = HashMap::from([
+            (&x, Complex64::new(0.7, 0.0)),
+            (&y, Complex64::new(2.3, 0.0)),
+        ]);
+        let vars = [&x, &y];
+
+        for input in INPUTS {
+            let expr = parse(input).unwrap();
+            let hyper = expr.eval_hyper_dual(&ctx, &vars).unwrap();
+            let dual = expr.eval_dual(&ctx, &vars).unwrap();
+
+            assert_eq!(hyper.gradient(), dual.gradient(), "{input}");
+            for i in 0..2 {
+                for j in 0..2 {
+                    let symbolic = expr
+                        .derivative(vars[i])
+                        .derivative(vars[j])
+                        .eval(&ctx)
+                        .unwrap();
+                    assert!(
+                        close(hyper.second_partial(i, j), symbolic),
+                        "d2/d{}d{} {input}: {} != {symbolic}",
+                        vars[i].get_name(),
+                        vars[j].get_name(),
+                        hyper.second_partial(i, j)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn chosen_variables_only() {
+        let (x, y) = (Var::new_owned("x"), Var::new_owned("y"));
+        let ctx = HashMap::from([
+            (&x, Complex64::new(2.0, 0.0)),
+            (&y, Complex64::new(3.0, 0.0)),
+        ]);
+        let expr = parse("x^2 y").unwrap();
+
+        let dual = expr.eval_dual(&ctx, &[&y]).unwrap();
+        assert_eq!(dual.gradient(), [Complex64::new(4.0, 0.0)]);
+
+        let constant = parse("2 + 3").unwrap().eval_hyper_dual(&ctx, &[&x, &y]);
+        let constant = constant.unwrap();
+        assert_eq!(constant.gradient(), [Complex64::new(0.0, 0.0); 2]);
+        assert_eq!(constant.second_partial(1, 1), Complex64::new(0.0, 0.0));
+
+        // The second derivative of x^1 at 0 is 0, not 0 * ∞.
+        let at_zero = HashMap::from([(&x, Complex64::new(0.0, 0.0))]);
+        let hyper = parse("x^1")
+            .unwrap()
+            .eval_hyper_dual(&at_zero, &[&x])
+            .unwrap();
+        assert_eq!(hyper.second_partial(0, 0), Complex64::new(0.0, 0.0));
+
+        // x^y ln(x) and its derivatives tend to 0 as x does, rather than being 0 * ∞.
+        let (x, y) = (Var::new_owned("x"), Var::new_owned("y"));
+        let ctx = HashMap::from([
+            (&x, Complex64::new(0.0, 0.0)),
+            (&y, Complex64::new(3.0, 0.0)),
+        ]);
+        let hyper = parse("x^y")
+            .unwrap()
+            .eval_hyper_dual(&ctx, &[&x, &y])
+            .unwrap();
+        assert_eq!(hyper.gradient(), [Complex64::new(0.0, 0.0); 2]);
+        for (i, j) in [(0, 1), (1, 0), (1, 1)] {
+            assert_eq!(hyper.second_partial(i, j), Complex64::new(0.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn user_functions() {
+        let (x, t, a) = (
+            Var::new_owned("x"),
+            Var::new_owned("t"),
+            Var::new_owned("a"),
+        );
+        // f(t) = a t^2, where a is global
+        let f = Function::F("f".to_string(), parse("a t^2").unwrap(), vec![t.clone()]);
+        let expr = f.apply(vec![parse("sin(x)").unwrap()]);
+        let ctx = HashMap::from([
+            (&x, Complex64::new(0.5, 0.0)),
+            (&a, Complex64::new(3.0, 0.0)),
+        ]);
+
+        let dual = expr.eval_dual(&ctx, &[&x, &a]).unwrap();
+        let (sin, cos) = (0.5f64.sin(), 0.5f64.cos());
+        assert!(close(dual.partial(0), Complex64::new(6.0 * sin * cos, 0.0)));
+        // The dependence on the global is tracked as well.
+        assert!(close(dual.partial(1), Complex64::new(sin * sin, 0.0)));
+    }
+
+    /// `cube(u) = u^3`, which only has a `Complex64` version.
+    #[derive(Debug)]
+    struct Cube;
+
+    impl FuncDef for Cube {
+        fn eval(&self, args: Vec<Complex64>, _: &VarMap<Complex64>) -> EvalResult {
+            Ok(args[0].powi(3))
+        }
+
+        fn is_variant_on_global(&self, _: &Var) -> bool {
+            false
+        }
+
+        fn name(&self) -> String {
+            "cube".to_string()
+        }
+
+        fn arity(&self) -> Arity {
+            Arity::Exactly(1)
+        }
+
+        fn partial_derivative(&self, args: &[Expr], _: usize) -> Expr {
+            parse("3").unwrap() * args[0].clone().pow(parse("2").unwrap())
+        }
+    }
+
+    #[test]
+    fn functions_without_a_dual_version() {
+        let x = Var::new_owned("x");
+        let ctx = HashMap::from([(&x, Complex64::new(2.0, 0.0))]);
+        let expr = Expr::Function(Arc::new(Cube), vec![parse("x^2").unwrap()]);
+
+        let hyper = expr.eval_hyper_dual(&ctx, &[&x]).unwrap();
+        assert!(close(hyper.value(), Complex64::new(64.0, 0.0)));
+        // d/dx x^6 = 6x^5, d²/dx² x^6 = 30x^4
+        assert!(close(hyper.partial(0), Complex64::new(192.0, 0.0)));
+        assert!(close(
+            hyper.second_partial(0, 0),
+            Complex64::new(480.0, 0.0)
+        ));
+    }
+
+    /// A function without known partial derivatives.
+    #[derive(Debug)]
+    struct Opaque;
+
+    impl FuncDef for Opaque {
+        fn eval(&self, args: Vec<Complex64>, _: &VarMap<Complex64>) -> EvalResult {
+            Ok(args[0] * 2.0)
+        }
+
+        fn is_variant_on_global(&self, _: &Var) -> bool {
+            false
+        }
+
+        fn name(&self) -> String {
+            "opaque".to_string()
+        }
+    }
+
+    #[test]
+    fn constant_arguments_need_no_derivatives() {
+        let x = Var::new_owned("x");
+        let ctx = HashMap::from([(&x, Complex64::new(3.0, 0.0))]);
+        let constant = Expr::Function(Arc::new(Opaque), vec![parse("2").unwrap()]);
+        let expr = constant * parse("x").unwrap();
+
+        let dual = expr.eval_dual(&ctx, &[&x]).unwrap();
+        assert_eq!(dual.partial(0), Complex64::new(4.0, 0.0));
+        let hyper = expr.eval_hyper_dual(&ctx, &[&x]).unwrap();
+        assert_eq!(hyper.second_partial(0, 0), Complex64::new(0.0, 0.0));
+
+        let varying = Expr::Function(Arc::new(Opaque), vec![parse("x").unwrap()]);
+        assert!(matches!(
+            varying.eval_dual(&ctx, &[&x]),
+            Err(EvalError::Undefined { .. })
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        let x = Var::new_owned("x");
+        let ctx = HashMap::from([(&x, Complex64::new(0.0, 0.0))]);
+
+        assert!(matches!(
+            parse("1/x").unwrap().eval_dual(&ctx, &[&x]),
+            Err(EvalError::DivisionByZero { .. })
+        ));
+        assert!(matches!(
+            parse("ln(x)").unwrap().eval_dual(&ctx, &[&x]),
+            Err(EvalError::DomainError { .. })
+        ));
+        assert!(matches!(
+            parse("y").unwrap().eval_dual(&ctx, &[&x]),
+            Err(EvalError::VarMissing { .. })
+        ));
+    }
+}
+
+#[cfg(test)]
+mod gradient {
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use crate::{
+        expr::{EvalError, EvalResult, Expr},
+        function::{Arity, FuncDef, Function},
+        parse::parse,
+        var::{Var, VarMap},
+    };
+
+    use super::fixtures::close;
+
+    #[test]
+    fn matches_forward_mode() {
+        let (x, y, z) = (
+            Var::new_owned("x"),
+            Var::new_owned("y"),
+            Var::new_owned("z"),
+        );
+        let ctx = HashMap::from([
+            (&x, Complex64::new(0.7, 0.0)),
+            (&y, Complex64::new(2.3, 0.0)),
+            (&z, Complex64::new(-1.2, 0.5)),
+        ]);
+
+        for input in [
+            "x^3 y - 2x y^2 z + 7",
+            "sin(x y z) + cos(x)^2 - tan(y / 4)",
+            "exp(-x) ln(y) + sqrt(x^2 + y^2 + z^2)",
+            "arcsin(x / 4) + arctan(x y) + arctanh(z / 3)",
+            "atan2(y, x) + log(y + 1, x + 2) + hypot(x, z)",
+            "root(y, x) + x^y + z^(x - y) + 2^z",
+            "max(x, y, 1) + min(x^2, y) + abs(x - 3)",
+            "x y z (x + y) (y - z) / (x^2 + 1)",
+        ] {
+            let expr = parse(input).unwrap();
+            let (value, gradient) = expr.eval_gradient(&ctx).unwrap();
+            let dual = expr.eval_dual(&ctx, &[&x, &y, &z]).unwrap();
+
+            assert!(close(value, dual.value()), "{input}");
+            assert_eq!(gradient.len(), 3);
+            for (i, var) in [&x, &y, &z].into_iter().enumerate() {
+                assert!(
+                    close(gradient[var], dual.partial(i)),
+                    "d/d{} {input}: {} != {}",
+                    var.get_name(),
+                    gradient[var],
+                    dual.partial(i)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn many_variables() {
+        let vars: Vec<Var> = (0..300).map(|i| Var::new_owned(&format!("x{i}"))).collect();
+        let ctx: VarMap<Complex64> = vars
+            .iter()
+            .enumerate()
+            .map(|(i, var)| (var, Complex64::new(i as f64 / 100.0, 0.0)))
+            .collect();
+        // The sum of (x_i - 1)^2, times the product of every x_i but the first.
+        let squares = Expr::Sum(
+            vars.iter()
+                .map(|var| parse(&format!("({} - 1)^2", var.get_name())).unwrap())
+                .collect(),
+        );
+        let product = Expr::Product(
+            vars[1..]
+                .iter()
+                .map(|var| Expr::Var(Arc::new(var.clone())))
+                .collect(),
+        );
+        let expr = squares + product.clone() * parse("10^-100").unwrap();
+
+        let (_, gradient) = expr.eval_gradient(&ctx).unwrap();
+        let product = product.eval(&ctx).unwrap();
+        for (i, var) in vars.iter().enumerate() {
+            let mut expected = 2.0 * (ctx[var] - 1.0);
+            if i > 0 {
+                expected += product / ctx[var] * 1e-100;
+            }
+            assert!(close(gradient[var], expected), "{}", var.get_name());
+        }
+    }
+
+    #[test]
+    fn unused_variables_and_constants() {
+        let (x, y) = (Var::new_owned("x"), Var::new_owned("y"));
+        let ctx = HashMap::from([
+            (&x, Complex64::new(3.0, 0.0)),
+            (&y, Complex64::new(0.0, 0.0)),
+        ]);
+        let zero = Complex64::new(0.0, 0.0);
+
+        let (value, gradient) = parse("x^2").unwrap().eval_gradient(&ctx).unwrap();
+        assert_eq!(value, Complex64::new(9.0, 0.0));
+        assert_eq!(gradient[&x], Complex64::new(6.0, 0.0));
+        assert_eq!(gradient[&y], zero);
+
+        let (value, gradient) = parse("2 + 3").unwrap().eval_gradient(&ctx).unwrap();
+        assert_eq!(value, Complex64::new(5.0, 0.0));
+        assert_eq!(gradient, HashMap::from([(&x, zero), (&y, zero)]));
+
+        let (_, gradient) = parse("y").unwrap().eval_gradient(&ctx).unwrap();
+        assert_eq!(gradient[&x], zero);
+        assert_eq!(gradient[&y], Complex64::new(1.0, 0.0));
+
+        // A zero factor must not make the other partial derivatives NaN.
+        let (_, gradient) = parse("x y").unwrap().eval_gradient(&ctx).unwrap();
+        assert_eq!(gradient[&x], zero);
+        assert_eq!(gradient[&y], Complex64::new(3.0, 0.0));
+
+        // The exponent's partial x^y ln(x) tends to 0 as x does.
+        let ctx = HashMap::from([
+            (&x, Complex64::new(0.0, 0.0)),
+            (&y, Complex64::new(2.0, 0.0)),
+        ]);
+        let (_, gradient) = parse("x^y").unwrap().eval_gradient(&ctx).unwrap();
+        assert_eq!(gradient[&x], zero);
+        assert_eq!(gradient[&y], zero);
+    }
+
+    #[test]
+    fn user_functions() {
+        let (x, t, a) = (
+            Var::new_owned("x"),
+            Var::new_owned("t"),
+            Var::new_owned("a"),
+        );
+        // f(t) = a t^2, where a is global
+        let f = Function::F("f".to_string(), parse("a t^2").unwrap(), vec![t.clone()]);
+        let expr = f.clone().apply(vec![parse("sin(x)").unwrap()]);
+        let ctx = HashMap::from([
+            (&x, Complex64::new(0.5, 0.0)),
+            (&a, Complex64::new(3.0, 0.0)),
+        ]);
+
+        let (_, gradient) = expr.eval_gradient(&ctx).unwrap();
+        let (sin, cos) = (0.5f64.sin(), 0.5f64.cos());
+        assert!(close(gradient[&x], Complex64::new(6.0 * sin * cos, 0.0)));
+        assert!(close(gradient[&a], Complex64::new(sin * sin, 0.0)));
+
+        // The symbolic derivative differentiates through the global too.
+        for var in [&x, &a] {
+            let derivative = expr.derivative(var).eval(&ctx).unwrap();
+            assert!(close(derivative, gradient[var]), "{}", var.get_name());
+        }
+
+        // f(x) = a x^2 at x = 2
+        let expr = f.apply(vec![parse("x").unwrap()]);
+        let ctx = HashMap::from([
+            (&x, Complex64::new(2.0, 0.0)),
+            (&a, Complex64::new(3.0, 0.0)),
+        ]);
+        let (_, gradient) = expr.eval_gradient(&ctx).unwrap();
+        let derivative = expr.derivative(&a).eval(&ctx).unwrap();
+        assert!(close(gradient[&a], Complex64::new(4.0, 0.0)));
+        assert!(close(derivative, gradient[&a]));
+    }
+
+    /// `cube(u) = u^3`, which only has a `Complex64` version.
+    #[derive(Debug)]
+    struct Cube;
+
+    impl FuncDef for Cube {
+        fn eval(&self, args: Vec<Complex64>, _: &VarMap<Complex64>) -> EvalResult {
+            Ok(args[0].powi(3))
+        }
+
+        fn is_variant_on_global(&self, _: &Var) -> bool {
+            false
+        }
+
+        fn name(&self) -> String {
+            "cube".to_string()
+        }
+
+        fn arity(&self) -> Arity {
+            Arity::Exactly(1)
+        }
+
+        fn partial_derivative(&self, args: &[Expr], _: usize) -> Expr {
+            parse("3").unwrap() * args[0].clone().pow(parse("2").unwrap())
+        }
+    }
+
+    #[test]
+    fn functions_without_a_dual_version() {
+        let x = Var::new_owned("x");
+        let ctx = HashMap::from([(&x, Complex64::new(2.0, 0.0))]);
+        let expr = Expr::Function(Arc::new(Cube), vec![parse("x^2").unwrap()]);
+
+        let (value, gradient) = expr.eval_gradient(&ctx).unwrap();
+        assert!(close(value, Complex64::new(64.0, 0.0)));
+        assert!(close(gradient[&x], Complex64::new(192.0, 0.0)));
+    }
+
+    #[test]
+    fn errors() {
+        let x = Var::new_owned("x");
+        let ctx = HashMap::from([(&x, Complex64::new(0.0, 0.0))]);
+
+        assert!(matches!(
+            parse("1/x").unwrap().eval_gradient(&ctx),
+            Err(EvalError::DivisionByZero { .. })
+        ));
+        assert!(matches!(
+            parse("ln(x)").unwrap().eval_gradient(&ctx),
+            Err(EvalError::DomainError { .. })
+        ));
+        assert!(matches!(
+            parse("y").unwrap().eval_gradient(&ctx),
+            Err(EvalError::VarMissing { .. })
+        ));
+    }
+}
+
+#[cfg(test)]
+mod polynomial {
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+
+    use crate::{
+        num::Num,
+        parse::parse,
+        poly::{Polynomial, PolynomialError, UnivariatePolynomial},
+        var::Var,
+    };
+
+    use super::fixtures::polynomial;
+
+    #[test]
+    fn to_and_from_expr() {
+        let (x, y) = (Var::new_owned("x"), Var::new_owned("y"));
+
+        for (input, expected) in [
+            ("(x + 1)^2", "x^2 + 2x + 1"),
+            ("(x - y)(x + y)", "x^2 - y^2"),
+            ("x y^2 / 2 - 3 + x^0", "x*y^2/2 - 2"),
+            ("2^-1 x + 4^(1/2) y", "x/2 + 2y"),
+            ("(x + 1)^2 - x^2 - 2x", "1"),
+            ("x - x", "0"),
+            ("-(y - x)^3", "x^3 - 3x^2*y + 3x*y^2 - y^3"),
+        ] {
+            let p = polynomial(input, &[&x, &y]);
+            assert_eq!(p.to_string(), expected, "{input}");
+            assert_eq!(p.to_expr().to_polynomial(&[&x, &y]).unwrap(), p, "{input}");
+        }
+    }
+
+    #[test]
+    fn not_polynomials() {
+        let x = Var::new_owned("x");
+
+        for input in ["1/x", "x^(1/2)", "2^x", "sin(x)", "x^y"] {
+            let result = parse(input).unwrap().to_polynomial(&[&x]);
+            assert!(
+                matches!(result, Err(PolynomialError::NotPolynomial { .. })),
+                "{input}"
+            );
+        }
+        for input in ["pi x", "sqrt(2) x", "i"] {
+            let result = parse(input).unwrap().to_polynomial(&[&x]);
+            assert!(
+                matches!(result, Err(PolynomialError::NotRational { .. })),
+                "{input}"
+            );
+        }
+        assert!(matches!(
+            parse("x + y").unwrap().to_polynomial(&[&x]),
+            Err(PolynomialError::UnknownVar { name }) if name == "y"
+        ));
+
+        // Exponents must fit in a u32.
+        for input in ["(x^65536)^65536", "x^2147483647 x^2147483647 x^2147483647"] {
+            let result = parse(input).unwrap().to_polynomial(&[&x]);
+            assert!(
+                matches!(result, Err(PolynomialError::ExponentTooLarge { .. })),
+                "{input}"
+            );
+        }
+        let p = polynomial("x^2147483647 x^2147483647", &[&x]);
+        let value = p.eval(&HashMap::from([(&x, Complex64::new(0.5, 0.0))]));
+        assert_eq!(value.unwrap(), Complex64::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn arithmetic() {
+        let (x, y) = (Var::new_owned("x"), Var::new_owned("y"));
+        let a = polynomial("x^2 + 3x y - 1", &[&x, &y]);
+        let b = polynomial("x - 2y", &[&x, &y]);
+
+        assert_eq!(&a + &b, polynomial("x^2 + 3x y - 1 + x - 2y", &[&x, &y]));
+        assert_eq!(&a - &a, Polynomial::zero(&[&x, &y]));
+        assert_eq!(&a * &b, polynomial("(x^2 + 3x y - 1)(x - 2y)", &[&x, &y]));
+        assert_eq!(b.pow(5), polynomial("(x - 2y)^5", &[&x, &y]));
+        assert_eq!(b.pow(0), Polynomial::constant(&[&x, &y], Num::One));
+        assert_eq!(-b.clone(), polynomial("2y - x", &[&x, &y]));
+
+        // Polynomials in different variables are combined in all of them.
+        let in_x = polynomial("x + 1", &[&x]);
+        let in_y = polynomial("y^2", &[&y]);
+        let product = &in_x * &in_y;
+        assert_eq!(product.vars(), [x.clone(), y.clone()]);
+        assert_eq!(product, polynomial("x y^2 + y^2", &[&x, &y]));
+    }
+
+    #[test]
+    fn degree_and_leading_coefficient() {
+        let (x, y) = (Var::new_owned("x"), Var::new_owned("y"));
+        let p = polynomial("3x^2 y - 5x y^4 + 2y^5 + 7", &[&x, &y]);
+
+        assert_eq!(p.degree(&x), Some(2));
+        assert_eq!(p.degree(&y), Some(5));
+        assert_eq!(p.total_degree(), Some(5));
+        assert_eq!(p.term_count(), 4);
+        assert_eq!(p.leading_coefficient(), Num::from(3));
+        assert_eq!(p.leading_term().unwrap().0, [2, 1]);
+        assert_eq!(p.coefficient(&[1, 4]), Num::from(-5));
+        assert_eq!(p.coefficient(&[1, 1]), Num::Zero);
+
+        // With y first, y^5 leads.
+        let p = p.to_expr().to_polynomial(&[&y, &x]).unwrap();
+        assert_eq!(p.leading_coefficient(), Num::from(2));
+
+        let zero = Polynomial::zero(&[&x]);
+        assert_eq!(zero.degree(&x), None);
+        assert_eq!(zero.total_degree(), None);
+        assert_eq!(zero.leading_coefficient(), Num::Zero);
+        assert_eq!(zero.as_constant(), Some(Num::Zero));
+    }
+
+    #[test]
+    fn evaluation() {
+        let (x, y) = (Var::new_owned("x"), Var::new_owned("y"));
+        let expr = parse("x^3 y - 2x y^2 / 3 + 7").unwrap();
+        let p = expr.to_polynomial(&[&x, &y]).unwrap();
+
+        let ctx = HashMap::from([
+            (&x, Complex64::new(1.5, -0.5)),
+            (&y, Complex64::new(-2.0, 1.0)),
+        ]);
+        assert!((p.eval(&ctx).unwrap() - expr.eval(&ctx).unwrap()).norm() < 1e-12);
+
+        let exact = HashMap::from([(&x, Num::rational(1, 2)), (&y, Num::from(3))]);
+        // 1/8 * 3 - 2 * 1/2 * 9 / 3 + 7 = 3/8 - 3 + 7
+        assert_eq!(p.eval_exact(&exact), Some(Num::rational(35, 8)));
+        assert_eq!(p.eval_exact(&HashMap::from([(&x, Num::One)])), None);
+    }
+
+    #[test]
+    fn univariate() {
+        let x = Var::new_owned("x");
+        let p = polynomial("2x^3 - x + 1/2", &[&x]).to_univariate().unwrap();
+
+        assert_eq!(p.degree(), Some(3));
+        assert_eq!(p.leading_coefficient(), Num::from(2));
+        assert_eq!(
+            p.coefficients(),
+            [Num::rational(1, 2), Num::from(-1), Num::Zero, Num::from(2)]
+        );
+        assert_eq!(p.to_string(), "2x^3 - x + 1/2");
+        assert_eq!(p.eval_exact(&Num::from(2)), Some(Num::rational(29, 2)));
+        assert_eq!(
+            p.eval(Complex64::new(2.0, 0.0)).unwrap(),
+            Complex64::new(14.5, 0.0)
+        );
+
+        let q = UnivariatePolynomial::new(&x, vec![Num::from(-1), Num::One]);
+        assert_eq!(
+            &p * &q,
+            polynomial("(2x^3 - x + 1/2)(x - 1)", &[&x])
+                .to_univariate()
+                .unwrap()
+        );
+        assert_eq!((&p - &p).degree(), None);
+        assert_eq!(q.pow(3).to_string(), "x^3 - 3x^2 + 3x - 1");
+        assert_eq!(Polynomial::from(q.clone()).to_univariate(), Some(q));
+
+        // Only polynomials in at most one variable are univariate.
+        let y = Var::new_owned("y");
+        assert!(polynomial("x y", &[&x, &y]).to_univariate().is_none());
+        let in_y = polynomial("y^2 + 1", &[&x, &y]).to_univariate().unwrap();
+        assert_eq!(in_y.var(), &y);
+    }
+}
+
+#[cfg(test)]
+mod polynomial_division {
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+
+    use crate::{num::Num, parse::parse, var::Var};
+
+    use super::fixtures::{polynomial, univariate};
+
+    #[test]
+    fn univariate_division() {
+        let x = Var::new_owned("x");
+        let a = univariate("2x^4 - 3x^3 + x - 5", &x);
+        let b = univariate("x^2 + x/2 - 1", &x);
+
+        let (quotient, remainder) = a.div_rem(&b);
+        assert!(remainder.degree() < b.degree());
+        assert_eq!(&(&quotient * &b) + &remainder, a);
+
+        let (quotient, remainder) = univariate("x^2 - 1", &x).div_rem(&univariate("x - 1", &x));
+        assert_eq!(quotient, univariate("x + 1", &x));
+        assert!(remainder.is_zero());
+
+        // Dividing by a polynomial of higher degree leaves everything as the remainder.
+        let (quotient, remainder) = b.div_rem(&a);
+        assert!(quotient.is_zero());
+        assert_eq!(remainder, b);
+    }
+
+    #[test]
+    fn pseudo_remainder_sequence() {
+        let x = Var::new_owned("x");
+        // The classic example from Knuth, whose remainders grow quickly without taking
+        // primitive parts.
+        let a = univariate("x^8 + x^6 - 3x^4 - 3x^3 + 8x^2 + 2x - 5", &x);
+        let b = univariate("3x^6 + 5x^4 - 4x^2 - 9x + 21", &x);
+
+        let prem = a.pseudo_rem(&b);
+        assert_eq!(prem, univariate("-15x^4 + 3x^2 - 9", &x));
+
+        let sequence = a.primitive_prs(&b);
+        let expected = [
+            "x^8 + x^6 - 3x^4 - 3x^3 + 8x^2 + 2x - 5",
+            "3x^6 + 5x^4 - 4x^2 - 9x + 21",
+            "5x^4 - x^2 + 3",
+            "13x^2 + 25x - 49",
+            "4663x - 6150",
+            "1",
+        ];
+        assert_eq!(sequence.len(), expected.len());
+        for (p, expected) in sequence.iter().zip(expected) {
+            assert_eq!(p, &univariate(expected, &x));
+        }
+        assert_eq!(a.gcd(&b), univariate("1", &x));
+    }
+
+    #[test]
+    fn univariate_gcd() {
+        let x = Var::new_owned("x");
+        let a = univariate("(x - 1)^2 (x + 2) (2x + 3)", &x);
+        let b = univariate("(x - 1) (x + 2)^2 (x^2 + 1) / 7", &x);
+
+        assert_eq!(a.gcd(&b), univariate("(x - 1)(x + 2)", &x));
+        assert_eq!(a.gcd(&a.scale(&Num::rational(-3, 5))), a.monic());
+        assert_eq!(a.gcd(&univariate("0", &x)), a.monic());
+        assert!(univariate("0", &x).gcd(&univariate("0", &x)).is_zero());
+        assert_eq!(univariate("3x + 3", &x).content(), Num::from(3));
+        assert_eq!(
+            univariate("-x/2 + 2/3", &x).primitive_part(),
+            univariate("3x - 4", &x)
+        );
+    }
+
+    #[test]
+    fn multivariate_division() {
+        let (x, y) = (Var::new_owned("x"), Var::new_owned("y"));
+        let a = polynomial("x^2 y + x y^2 + y^2", &[&x, &y]);
+        let b = polynomial("x y - 1", &[&x, &y]);
+
+        let (quotient, remainder) = a.div_rem(&b);
+        assert_eq!(&(&quotient * &b) + &remainder, a);
+        assert_eq!(quotient, polynomial("x + y", &[&x, &y]));
+        assert_eq!(remainder, polynomial("x + y^2 + y", &[&x, &y]));
+
+        let product = polynomial("(x^2 - 3y)(x y + 2)", &[&x, &y]);
+        assert_eq!(
+            product.divide_exact(&b.clone().pow(0)),
+            Some(product.clone())
+        );
+        assert_eq!(
+            product.divide_exact(&polynomial("x y + 2", &[&x, &y])),
+            Some(polynomial("x^2 - 3y", &[&x, &y]))
+        );
+        assert_eq!(product.divide_exact(&b), None);
+
+        // In x, the leading coefficient of x y - 1 is y.
+        let prem = a.pseudo_rem(&b, &x);
+        assert_eq!(prem, polynomial("y^4 + y^3 + y", &[&x, &y]));
+    }
+
+    #[test]
+    fn multivariate_gcd() {
+        let (x, y, z) = (
+            Var::new_owned("x"),
+            Var::new_owned("y"),
+            Var::new_owned("z"),
+        );
+        let vars = [&x, &y, &z];
+        let common = polynomial("x y - z^2 + 1", &vars);
+        let a = &common * &polynomial("(x + y + z)^2 (y - 2)", &vars);
+        let b = &common * &polynomial("(x - z)(y - 2) 3", &vars);
+
+        let expected = polynomial("(x y - z^2 + 1)(y - 2)", &vars);
+        assert_eq!(a.gcd(&b), expected);
+
+        assert_eq!(
+            polynomial("x^2 - y^2", &vars).gcd(&polynomial("x^2 + 2x y + y^2", &vars)),
+            polynomial("x + y", &vars)
+        );
+        assert_eq!(
+            polynomial("2x + 2y", &vars).gcd(&polynomial("4", &vars)),
+            polynomial("1", &vars)
+        );
+        assert_eq!(
+            polynomial("x^2 y", &[&x, &y]).gcd(&polynomial("x y^3 z", &vars)),
+            polynomial("x y", &[&x, &y])
+        );
+        assert_eq!(
+            polynomial("y z - x/2", &vars).content(),
+            Num::rational(-1, 2)
+        );
+    }
+
+    #[test]
+    fn cancel() {
+        for (input, expected) in [
+            ("(x^2 - 1)/(x - 1)", "x + 1"),
+            ("(x^2 - 1)/(2x - 2)", "x/2 + 1/2"),
+            ("1/x + 1/y", "(x + y)/(x*y)"),
+            ("(x^2 - y^2)/(x y + y^2)", "(x - y)/y"),
+            ("x/(x + 1) - 1", "-1/(x + 1)"),
+            ("(x + 1)^-2 (x^2 + 2x + 1)^2", "x^2 + 2x + 1"),
+            ("sin(x)^2 / sin(x)", "sin(x)"),
+            ("(pi x + pi) / (x + 1)", "pi"),
+            ("5", "5"),
+            ("1/(x - x)", "1/(x - x)"),
+        ] {
+            let expr = parse(input).unwrap();
+            assert_eq!(expr.cancel().to_string(), expected, "{input}");
+        }
+
+        let (x, y) = (Var::new_owned("x"), Var::new_owned("y"));
+        let expr = parse("(x^3 - y^3) / (x - y) + x / (x + y) - sqrt(x) / x").unwrap();
+        let ctx = HashMap::from([
+            (&x, Complex64::new(1.3, 0.2)),
+            (&y, Complex64::new(-0.7, 0.9)),
+        ]);
+        let (a, b) = (expr.eval(&ctx).unwrap(), expr.cancel().eval(&ctx).unwrap());
+        assert!((a - b).norm() < 1e-12, "{a} != {b}");
+    }
+}
+
+#[cfg(test)]
+mod factor {
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+
+    use crate::{
+        expr::Expr,
+        num::Num,
+        parse::parse,
+        poly::{Polynomial, UnivariatePolynomial},
+        var::Var,
+    };
+
+    use super::fixtures::{polynomial, univariate};
+
+    fn assert_factors(p: &UnivariatePolynomial, expected: &[(&str, u32)]) {
+        let (constant, factors) = p.factor();
+        let mut product = UnivariatePolynomial::new(p.var(), vec![constant]);
+        for (factor, multiplicity) in &factors {
+            product = &product * &factor.pow(*multiplicity);
+        }
+        assert_eq!(&product, p);
+
+        let x = p.var();
+        assert_eq!(factors.len(), expected.len(), "{p}");
+        for (expected, actual) in expected {
+            let expected = univariate(expected, x);
+            assert!(
+                factors.contains(&(expected.clone(), *actual)),
+                "{expected}^{actual} is not a factor of {p}"
+            );
+        }
+    }
+
+    #[test]
+    fn content_and_derivative() {
+        let x = Var::new_owned("x");
+        assert_eq!(
+            univariate("x^3 - 2x + 5", &x).derivative(),
+            univariate("3x^2 - 2", &x)
+        );
+
+        let y = Var::new_owned("y");
+        assert_eq!(
+            polynomial("x^2 y^3 + x y", &[&x, &y]).derivative(&y),
+            polynomial("3x^2 y^2 + x", &[&x, &y])
+        );
+    }
+
+    #[test]
+    fn square_free_decomposition() {
+        let x = Var::new_owned("x");
+        let p = univariate("(x - 1)^3 (x + 2) (x^2 + 1)^2 3", &x);
+        let parts = p.square_free_decomposition();
+        assert_eq!(
+            parts,
+            vec![
+                (univariate("x + 2", &x), 1),
+                (univariate("x^2 + 1", &x), 2),
+                (univariate("x - 1", &x), 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn univariate_factor() {
+        let x = Var::new_owned("x");
+        assert_factors(&univariate("x^2 - 1", &x), &[("x - 1", 1), ("x + 1", 1)]);
+        assert_factors(&univariate("2x^2 - 2", &x), &[("x - 1", 1), ("x + 1", 1)]);
+        assert_factors(
+            &univariate("(x - 1)^3 (x + 2)", &x),
+            &[("x - 1", 3), ("x + 2", 1)],
+        );
+        assert_factors(&univariate("x^4 + 1", &x), &[("x^4 + 1", 1)]);
+        assert_factors(
+            &univariate("x^6 - 1", &x),
+            &[
+                ("x - 1", 1),
+                ("x + 1", 1),
+                ("x^2 + x + 1", 1),
+                ("x^2 - x + 1", 1),
+            ],
+        );
+        assert_factors(
+            &univariate("(3x/2 - 1)(x^2 - 2)^2 / 5", &x),
+            &[("3x - 2", 1), ("x^2 - 2", 2)],
+        );
+        assert_factors(&univariate("7", &x), &[]);
+
+        // Irreducible over the rationals but factors modulo every prime.
+        assert_factors(
+            &univariate("x^4 - 10x^2 + 1", &x),
+            &[("x^4 - 10x^2 + 1", 1)],
+        );
+
+        // Many small factors modulo most primes, which must be recombined.
+        assert_factors(
+            &univariate("(x^4 - 10x^2 + 1)(x^4 + 1)(x^3 - 2)(5x + 3)^2", &x),
+            &[
+                ("5x + 3", 2),
+                ("x^3 - 2", 1),
+                ("x^4 + 1", 1),
+                ("x^4 - 10x^2 + 1", 1),
+            ],
+        );
+
+        // Large coefficients need the factors to be lifted far.
+        assert_factors(
+            &univariate("(x^2 - 12345x + 678910)(1000x^3 + 999x - 123456789)", &x),
+            &[
+                ("x^2 - 12345x + 678910", 1),
+                ("1000x^3 + 999x - 123456789", 1),
+            ],
+        );
+
+        let (constant, factors) = univariate("0", &x).factor();
+        assert!(constant.is_zero() && factors.is_empty());
+    }
+
+    #[test]
+    fn multivariate_factor() {
+        let (x, y, z) = (
+            Var::new_owned("x"),
+            Var::new_owned("y"),
+            Var::new_owned("z"),
+        );
+        let vars = [&x, &y, &z];
+        for (input, expected) in [
+            ("x^2 - y^2", vec![("x - y", 1), ("x + y", 1)]),
+            ("x^2 y + x y^2", vec![("x", 1), ("y", 1), ("x + y", 1)]),
+            (
+                "(x y - z^2 + 1)^2 (x + y + z) 6",
+                vec![("x + y + z", 1), ("x y - z^2 + 1", 2)],
+            ),
+            ("x^2 + y^2", vec![("x^2 + y^2", 1)]),
+            (
+                "x^4 - y^4",
+                vec![("x - y", 1), ("x + y", 1), ("x^2 + y^2", 1)],
+            ),
+        ] {
+            let p = polynomial(input, &vars);
+            let (constant, factors) = p.factor();
+
+            let mut product = Polynomial::constant(&vars, constant);
+            for (factor, multiplicity) in &factors {
+                product = &product * &factor.clone().pow(*multiplicity);
+            }
+            assert_eq!(product, p, "{input}");
+
+            assert_eq!(factors.len(), expected.len(), "{input}");
+            for (expected, multiplicity) in expected {
+                let expected = polynomial(expected, &vars);
+                assert!(
+                    factors
+                        .iter()
+                        .any(|(factor, m)| factor == &expected && *m == multiplicity),
+                    "{expected}^{multiplicity} is not a factor of {input}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn expr_factor() {
+        let x = Var::new_owned("x");
+        for input in [
+            "2x^2 - 2",
+            "x^3 - 3x^2 + 3x - 1",
+            "(x^2 - 1)/(x^2 + 2x + 1)",
+            "sin(x)^2 - 1",
+            "x^2/4 - 1/9",
+        ] {
+            let expr = parse(input).unwrap();
+            let factored = expr.factor();
+            assert!(
+                matches!(factored, Expr::Product(_) | Expr::Pow(..)),
+                "{input}"
+            );
+
+            let ctx = HashMap::from([(&x, Complex64::new(0.6, -1.1))]);
+            let (a, b) = (expr.eval(&ctx).unwrap(), factored.eval(&ctx).unwrap());
+            assert!((a - b).norm() < 1e-12, "{input}: {a} != {b}");
+        }
+
+        assert!(matches!(
+            parse("0").unwrap().factor(),
+            Expr::Const(Num::Zero)
+        ));
+        for (input, expected) in [
+            ("x^2 - 1", "(x + 1)*(x - 1)"),
+            ("2x^2 - 2", "2(x + 1)*(x - 1)"),
+            ("x/(x^2 + x)", "1/(x + 1)"),
+        ] {
+            assert_eq!(
+                parse(input).unwrap().factor().to_string(),
+                expected,
+                "{input}"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod expand {
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+
+    use crate::{
+        expand::{ExpandError, ExpandOptions},
+        parse::parse,
+        var::Var,
+    };
+
+    fn expand(input: &str) -> String {
+        parse(input).unwrap().expand().unwrap().to_string()
+    }
+
+    #[test]
+    fn distributes_products() {
+        for (input, expected) in [
+            ("(x + 1)(x + 2)", "3x + x^2 + 2"),
+            ("x (y + 2) - 2x", "x*y"),
+            ("(a + b)(c + d)", "a*c + a*d + b*c + b*d"),
+            ("(x - 1)(x + 1) + 1", "x^2"),
+            ("2 (x + y/2)", "2x + y"),
+            ("(x + 1)(x - 1) - (x^2 - 1)", "0"),
+            ("x", "x"),
+        ] {
+            assert_eq!(expand(input), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn multinomial_powers() {
+        for (input, expected) in [
+            ("(x + 1)^2", "2x + x^2 + 1"),
+            ("(x - y)^3", "x^3 - y^3 + 3x*y^2 - 3y*x^2"),
+            ("(x + y + 1)^2", "2x + 2y + x^2 + y^2 + 2x*y + 1"),
+            ("(2x)^3 (x + 1)", "8x^3 + 8x^4"),
+            ("((x + 1)^2)^2", "4x + 6x^2 + 4x^3 + x^4 + 1"),
+            ("(x + 1)^-2", "1/(2x + x^2 + 1)"),
+        ] {
+            assert_eq!(expand(input), expected, "{input}");
+        }
+
+        // The binomial coefficients of a large power.
+        let expr = parse("(x + 1)^20").unwrap().expand().unwrap();
+        let x = Var::new_owned("x");
+        let ctx = HashMap::from([(&x, Complex64::new(0.3, 0.4))]);
+        let expected = Complex64::new(1.3, 0.4).powu(20);
+        assert!((expr.eval(&ctx).unwrap() - expected).norm() < 1e-9);
+    }
+
+    #[test]
+    fn keeps_other_powers_and_functions() {
+        assert_eq!(expand("(x + 1)^(1/2)"), "sqrt(x + 1)");
+        assert_eq!(
+            expand("sin((x + 1)^2) (x + 1)"),
+            expand("x sin((x + 1)^2) + sin((x + 1)^2)")
+        );
+
+        let options = ExpandOptions {
+            functions: true,
+            ..ExpandOptions::default()
+        };
+        let expr = parse("sin((x + 1)^2) - sin(x^2 + 2x + 1)").unwrap();
+        assert_eq!(expr.expand_with(options).unwrap().to_string(), "0");
+    }
+
+    #[test]
+    fn term_limit() {
+        let options = ExpandOptions {
+            max_terms: 50,
+            ..ExpandOptions::default()
+        };
+        let expr = parse("(a + b + c + d)^10").unwrap();
+        assert!(matches!(
+            expr.expand_with(options),
+            Err(ExpandError::TooManyTerms { limit: 50 })
+        ));
+
+        let expr = parse("(a + b)(c + d)(e + f)(g + h)(i + j)(k + l)").unwrap();
+        assert!(matches!(
+            expr.expand_with(options),
+            Err(ExpandError::TooManyTerms { limit: 50 })
+        ));
+
+        let expr = parse("(x + 1)^20 (x - 1)").unwrap();
+        assert!(expr.expand_with(options).is_ok());
+        assert!(parse("(x + y)^100000").unwrap().expand().is_err());
+    }
+}
+
+#[cfg(test)]
+mod roots {
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+
+    use crate::{
+        expr::Expr,
+        parse::parse,
+        poly::{PolynomialError, SolveError},
+        var::Var,
+    };
+
+    fn solve(input: &str) -> Vec<(Expr, u32)> {
+        let x = Var::new_owned("x");
+        parse(input).unwrap().solve(&x).unwrap()
+    }
+
+    fn eval(expr: &Expr) -> Complex64 {
+        expr.eval(&HashMap::new()).unwrap()
+    }
+
+    /// Checks that the roots are distinct zeros of the polynomial whose multiplicities add up
+    /// to its degree.
+    fn assert_roots(input: &str, degree: u32) {
+        let x = Var::new_owned("x");
+        let expr = parse(input).unwrap();
+        let roots = expr.solve(&x).unwrap();
+
+        assert_eq!(roots.iter().map(|(_, m)| m).sum::<u32>(), degree, "{input}");
+        for (i, (root, _)) in roots.iter().enumerate() {
+            let value = eval(root);
+            let at_root = expr.eval(&HashMap::from([(&x, value)])).unwrap();
+            assert!(at_root.norm() < 1e-9, "{input} at {root} is {at_root}");
+
+            for (other, _) in &roots[..i] {
+                assert!((eval(other) - value).norm() > 1e-6, "{input}: {root} twice");
+            }
+        }
+    }
+
+    #[test]
+    fn rational_roots() {
+        for (input, expected) in [
+            ("2x - 3", vec![("3/2", 1)]),
+            ("x^2 - 5x + 6", vec![("2", 1), ("3", 1)]),
+            ("(x - 1)^2 (x + 2)", vec![("-2", 1), ("1", 2)]),
+            (
+                "6x^3 - 5x^2 - 2x + 1",
+                vec![("-1/2", 1), ("1/3", 1), ("1", 1)],
+            ),
+            ("x^4 - x^2", vec![("-1", 1), ("1", 1), ("0", 2)]),
+            ("x^3", vec![("0", 3)]),
+            ("7", vec![]),
+        ] {
+            let roots: Vec<(String, u32)> = solve(input)
+                .into_iter()
+                .map(|(root, m)| (root.to_string(), m))
+                .collect();
+            let expected: Vec<(String, u32)> = expected
+                .into_iter()
+                .map(|(root, m)| (root.to_string(), m))
+                .collect();
+            assert_eq!(roots, expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn quadratic() {
+        for (input, expected) in [
+            ("x^2 - 2", ["-sqrt(2)", "sqrt(2)"]),
+            ("x^2 + 1", ["-i", "i"]),
+            ("x^2 + x + 1", ["-sqrt(3)*i/2 - 1/2", "sqrt(3)*i/2 - 1/2"]),
+            ("x^2 - 2x - 1", ["-sqrt(2) + 1", "sqrt(2) + 1"]),
+        ] {
+            let roots: Vec<String> = solve(input)
+                .into_iter()
+                .map(|(root, _)| root.to_string())
+                .collect();
+            assert_eq!(roots, expected, "{input}");
+        }
+
+        assert_roots("3x^2 - 7x + 1", 2);
+        assert_roots("(x^2 - 2)^2 (2x - 1)", 5);
+    }
+
+    #[test]
+    fn cubic() {
+        assert_eq!(solve("x^3 - 2")[0].0.to_string(), "2^(1/3)");
+        assert_roots("x^3 - 2", 3);
+        assert_roots("x^3 + x + 1", 3);
+        // Three real roots, which Cardano's formula reaches through complex numbers.
+        assert_roots("x^3 - 3x + 1", 3);
+        assert_roots("2x^3 - 6x^2 + x + 5/3", 3);
+        assert_roots("(x^3 + 2x - 1)(x + 4)^2", 5);
+    }
+
+    #[test]
+    fn quartic() {
+        let roots: Vec<String> = solve("x^4 - 5x^2 + 6")
+            .into_iter()
+            .map(|(root, _)| root.to_string())
+            .collect();
+        assert_eq!(roots, ["-sqrt(3)", "sqrt(3)", "-sqrt(2)", "sqrt(2)"]);
+
+        assert_roots("x^4 + 1", 4);
+        assert_roots("x^4 + x + 1", 4);
+        assert_roots("x^4 - 2x^3 + x - 3", 4);
+        assert_roots("x^4 - 4x^2 + x + 1", 4);
+        assert_roots("3x^4 + 2x^3 - x^2 + 5x/2 - 7", 4);
+        assert_roots("x^6 - 1", 6);
+    }
+
+    #[test]
+    fn errors() {
+        let (x, y) = (Var::new_owned("x"), Var::new_owned("y"));
+        assert!(matches!(
+            parse("x^5 - x - 1").unwrap().solve(&x),
+            Err(SolveError::DegreeTooHigh { degree: 5 })
+        ));
+        assert!(matches!(
+            parse("x - x").unwrap().solve(&x),
+            Err(SolveError::ZeroPolynomial)
+        ));
+        assert!(matches!(
+            parse("x y - 1").unwrap().solve(&x),
+            Err(SolveError::Polynomial(PolynomialError::UnknownVar { .. }))
+        ));
+
+        // Rational roots are split off first, whatever the degree.
+        assert_eq!(parse("x^5 - x").unwrap().solve(&x).unwrap().len(), 5);
+
+        // Factors of any degree up to 4 are solved, whatever the degree of the polynomial and
+        // however large its rational roots.
+        let roots = solve("(x - 65537)(x - 65539)(x^3 - 2)");
+        let roots: Vec<String> = roots.iter().map(|(root, _)| root.to_string()).collect();
+        assert_eq!(roots[..3], ["65537", "65539", "2^(1/3)"]);
+        assert_eq!(roots.len(), 5);
+        assert_roots("(x^2 - 2)(x^2 + x + 1)(x^4 + 1)", 8);
+        assert!(matches!(
+            parse("(x^5 - x - 1)(x - 2)").unwrap().solve(&x),
+            Err(SolveError::DegreeTooHigh { degree: 5 })
+        ));
+        assert!(parse("x^2 - 1").unwrap().solve(&y).is_err());
+    }
+}
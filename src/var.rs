@@ -3,7 +3,7 @@ use std::sync::Arc;
 use std::collections::HashMap;
 
 /** Independent variable, unknown  */
-#[derive(PartialEq, Eq, Hash, Debug)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub struct Var {
     name: String,
 }